@@ -0,0 +1,44 @@
+use std::{io, panic, path::Path};
+
+use crate::{
+    PackOptions, PackSummary, UnpackOptions, error::ArcError, handle_pack_with, handle_unpack_with,
+};
+
+// --- Async API ---
+// Packing and unpacking read and write files from a rayon pool and spend
+// most of their time in LZSS, so the async variants hand the whole job to
+// tokio's blocking thread pool instead of running it on the runtime's
+// workers. They must be called from within a tokio runtime.
+
+/// Runs `job` with [`tokio::task::spawn_blocking`] and waits for it.
+async fn run_blocking<T: Send + 'static>(
+    job: impl FnOnce() -> Result<T, ArcError> + Send + 'static,
+) -> Result<T, ArcError> {
+    match tokio::task::spawn_blocking(job).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(io::Error::other(e).into()),
+    }
+}
+
+/// Async [`handle_unpack_with`].
+pub async fn unpack_async(
+    input_path: impl AsRef<Path>,
+    output_dir: impl AsRef<Path>,
+    options: UnpackOptions,
+) -> Result<(), ArcError> {
+    let input_path = input_path.as_ref().to_path_buf();
+    let output_dir = output_dir.as_ref().to_path_buf();
+    run_blocking(move || handle_unpack_with(input_path, output_dir, &options)).await
+}
+
+/// Async [`handle_pack_with`].
+pub async fn pack_async(
+    input_dir: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    options: PackOptions,
+) -> Result<PackSummary, ArcError> {
+    let input_dir = input_dir.as_ref().to_path_buf();
+    let output_path = output_path.as_ref().to_path_buf();
+    run_blocking(move || handle_pack_with(input_dir, output_path, &options)).await
+}
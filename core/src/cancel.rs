@@ -0,0 +1,38 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use crate::error::ArcError;
+
+// --- Cancellation ---
+// A caller keeps a clone of the token given in the options and cancels it
+// from another thread, e.g. a GUI's Cancel button. Operations check it
+// between entries and fail with `ArcError::Cancelled`.
+
+/// Shared flag asking a running operation to stop.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every operation using this token (or a clone) to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Fails with [`ArcError::Cancelled`] if `token` has been cancelled.
+pub(crate) fn check(token: Option<&CancelToken>) -> Result<(), ArcError> {
+    match token {
+        Some(token) if token.is_cancelled() => Err(ArcError::Cancelled),
+        _ => Ok(()),
+    }
+}
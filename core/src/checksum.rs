@@ -0,0 +1,103 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+};
+
+use log::info;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{ArcError, PathContext},
+    reader::{self, ArcReader},
+};
+
+// --- Checksums ---
+// Checksum files use the `sha256sum` text format: one `<hex digest>  <name>`
+// line per entry, where `name` is the archive entry name. Lines starting
+// with `#` are comments.
+
+/// Lowercase hex SHA-256 digest of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Parses a `sha256sum`-style checksum file into a name → digest map.
+pub fn read_checksum_file(path: impl AsRef<Path>) -> Result<HashMap<String, String>, ArcError> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).with_path(path)?;
+    let mut checksums = HashMap::new();
+    for (line_no, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (digest, name) = line
+            .split_once(char::is_whitespace)
+            .map(|(digest, rest)| (digest, rest.trim_start().trim_start_matches('*')))
+            .filter(|(digest, name)| digest.len() == 64 && !name.is_empty())
+            .ok_or_else(|| {
+                ArcError::InvalidFormat(format!(
+                    "{}:{}: expected `<sha256>  <name>`",
+                    path.display(),
+                    line_no + 1
+                ))
+            })?;
+        checksums.insert(name.to_string(), digest.to_ascii_lowercase());
+    }
+    Ok(checksums)
+}
+
+/// Digests of the decompressed entries of an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveChecksums {
+    /// `(name, digest)` in metadata order.
+    pub entries: Vec<(String, String)>,
+}
+
+impl ArchiveChecksums {
+    /// Digest of the whole content: the SHA-256 of the checksum lines sorted
+    /// by name. Archives with the same entries and data get the same digest,
+    /// whatever their order, compression or layout.
+    pub fn archive_digest(&self) -> String {
+        let mut sorted: Vec<_> = self.entries.iter().collect();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+        sha256_hex(checksum_lines(sorted).as_bytes())
+    }
+
+    /// The entries as a checksum file, followed by the archive digest as a
+    /// comment.
+    pub fn to_checksum_file(&self) -> String {
+        let mut content = checksum_lines(&self.entries);
+        content.push_str(&format!("# archive {}\n", self.archive_digest()));
+        content
+    }
+}
+
+fn checksum_lines<'a>(entries: impl IntoIterator<Item = &'a (String, String)>) -> String {
+    entries
+        .into_iter()
+        .map(|(name, digest)| format!("{digest}  {name}\n"))
+        .collect()
+}
+
+/// Decompresses every entry of `archive_path` in parallel and hashes it.
+pub fn archive_checksums(archive_path: impl AsRef<Path>) -> Result<ArchiveChecksums, ArcError> {
+    let archive_path = archive_path.as_ref();
+    let archive = ArcReader::open(archive_path)?;
+    let entries = archive
+        .entries()
+        .par_iter()
+        .map(|entry| -> Result<(String, String), ArcError> {
+            let mut task_reader = BufReader::new(File::open(archive_path).with_path(archive_path)?);
+            let data = reader::read_entry_data(&mut task_reader, entry)?;
+            Ok((entry.name().to_string(), sha256_hex(&data)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    info!("Hashed {} entries.", entries.len());
+    Ok(ArchiveChecksums { entries })
+}
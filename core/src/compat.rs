@@ -0,0 +1,178 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use crate::{
+    UnpackOptions, entry_output_paths,
+    error::{ArcError, PathContext},
+    reader::{self, ArcReader},
+};
+
+// --- Compatibility Check ---
+// Compares what this tool extracts from an archive against a directory
+// extracted by another tool (the original Python SilkyArcTool, GARbro, ...),
+// without writing anything to disk. Paths are compared the way unpacking
+// would lay them out below the reference directory.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// The archive has the entry but the reference has no such file.
+    MissingFromReference,
+    /// The reference has a file that matches no entry.
+    NotInArchive,
+    /// The reference has the file under a name differing only in case.
+    NameDiffers {
+        reference: PathBuf,
+    },
+    ContentDiffers {
+        size: u64,
+        reference_size: u64,
+    },
+}
+
+impl fmt::Display for DivergenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DivergenceKind::MissingFromReference => f.write_str("missing from the reference"),
+            DivergenceKind::NotInArchive => f.write_str("not in the archive"),
+            DivergenceKind::NameDiffers { reference } => {
+                write!(f, "named {:?} in the reference", reference)
+            }
+            DivergenceKind::ContentDiffers {
+                size,
+                reference_size,
+            } => write!(
+                f,
+                "content differs ({size} bytes here, {reference_size} in the reference)"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// Path relative to the reference directory.
+    pub path: PathBuf,
+    pub kind: DivergenceKind,
+}
+
+#[derive(Debug, Default)]
+pub struct CompatReport {
+    /// Entries whose content matches the reference file.
+    pub matched: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+impl CompatReport {
+    pub fn is_compatible(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+pub fn compat_check(
+    archive_path: impl AsRef<Path>,
+    reference_dir: impl AsRef<Path>,
+) -> Result<CompatReport, ArcError> {
+    let archive_path = archive_path.as_ref();
+    let reference_dir = reference_dir.as_ref();
+    info!("Comparing {:?} against {:?}", archive_path, reference_dir);
+    if !reference_dir.is_dir() {
+        return Err(ArcError::NotFound(reference_dir.to_path_buf()));
+    }
+
+    let archive = ArcReader::open(archive_path)?;
+    let paths = entry_output_paths(archive.entries(), Path::new(""), &UnpackOptions::default())?;
+    let mut reference_files: HashSet<PathBuf> = HashSet::new();
+    for entry in WalkDir::new(reference_dir).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            reference_files.insert(entry.path().strip_prefix(reference_dir)?.to_path_buf());
+        }
+    }
+    let by_lowercase: HashMap<String, &PathBuf> = reference_files
+        .iter()
+        .map(|path| (path.to_string_lossy().to_lowercase(), path))
+        .collect();
+
+    // Pair every entry with the reference file it corresponds to
+    let mut report = CompatReport::default();
+    let mut claimed = Vec::new();
+    let mut to_compare = Vec::new();
+    for (entry, path) in archive.entries().iter().zip(paths) {
+        let reference = if reference_files.contains(&path) {
+            path.clone()
+        } else if let Some(&other) = by_lowercase.get(&path.to_string_lossy().to_lowercase()) {
+            report.divergences.push(Divergence {
+                path: path.clone(),
+                kind: DivergenceKind::NameDiffers {
+                    reference: other.clone(),
+                },
+            });
+            other.clone()
+        } else {
+            report.divergences.push(Divergence {
+                path,
+                kind: DivergenceKind::MissingFromReference,
+            });
+            continue;
+        };
+        claimed.push(reference.clone());
+        to_compare.push((entry, path, reference));
+    }
+    for path in claimed {
+        reference_files.remove(&path);
+    }
+    let mut unclaimed: Vec<_> = reference_files.into_iter().collect();
+    unclaimed.sort();
+    report
+        .divergences
+        .extend(unclaimed.into_iter().map(|path| Divergence {
+            path,
+            kind: DivergenceKind::NotInArchive,
+        }));
+
+    // Compare contents in parallel
+    let results = to_compare
+        .par_iter()
+        .map(
+            |(entry, path, reference)| -> Result<Option<Divergence>, ArcError> {
+                let mut task_reader =
+                    BufReader::new(File::open(archive_path).with_path(archive_path)?);
+                let data = reader::read_entry_data(&mut task_reader, entry)?;
+                let reference_path = reference_dir.join(reference);
+                let reference_data = fs::read(&reference_path).with_path(&reference_path)?;
+                if data == reference_data {
+                    return Ok(None);
+                }
+                Ok(Some(Divergence {
+                    path: path.clone(),
+                    kind: DivergenceKind::ContentDiffers {
+                        size: data.len() as u64,
+                        reference_size: reference_data.len() as u64,
+                    },
+                }))
+            },
+        )
+        .collect::<Result<Vec<_>, _>>()?;
+    for result in results {
+        match result {
+            Some(divergence) => report.divergences.push(divergence),
+            None => report.matched += 1,
+        }
+    }
+
+    info!(
+        "{} entries match, {} divergence(s).",
+        report.matched,
+        report.divergences.len()
+    );
+    Ok(report)
+}
@@ -0,0 +1,111 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use log::info;
+use rayon::prelude::*;
+
+use crate::{
+    checksum::sha256_hex,
+    error::{ArcError, PathContext},
+    reader::{self, ArcEntry, ArcReader},
+};
+
+// --- Archive Diff ---
+// Compares two archives entry by entry, matching entries by name. Content is
+// compared by the SHA-256 of the decompressed data, so an entry that was
+// merely recompressed does not count as modified.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    /// The content differs, or with `metadata_only`, the sizes do.
+    Modified,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntryChange {
+    pub name: String,
+    pub change: Change,
+    /// The entry in the old archive, unless added.
+    pub old: Option<ArcEntry>,
+    /// The entry in the new archive, unless removed.
+    pub new: Option<ArcEntry>,
+}
+
+/// Lists the entries that differ between `old_path` and `new_path`, sorted by
+/// name. With `metadata_only`, entries count as modified when their sizes
+/// differ and no data is read.
+pub fn diff_archives(
+    old_path: impl AsRef<Path>,
+    new_path: impl AsRef<Path>,
+    metadata_only: bool,
+) -> Result<Vec<EntryChange>, ArcError> {
+    let (old_path, new_path) = (old_path.as_ref(), new_path.as_ref());
+    info!("Comparing {:?} with {:?}", old_path, new_path);
+    let old = ArcReader::open(old_path)?;
+    let new = ArcReader::open(new_path)?;
+
+    let mut changes = Vec::new();
+    let mut common = Vec::new();
+    for entry in old.entries() {
+        match new.get(entry.name()) {
+            Some(other) => common.push((entry, other)),
+            None => changes.push(EntryChange {
+                name: entry.name().to_string(),
+                change: Change::Removed,
+                old: Some(entry.clone()),
+                new: None,
+            }),
+        }
+    }
+    for entry in new
+        .entries()
+        .iter()
+        .filter(|entry| !old.contains(entry.name()))
+    {
+        changes.push(EntryChange {
+            name: entry.name().to_string(),
+            change: Change::Added,
+            old: None,
+            new: Some(entry.clone()),
+        });
+    }
+
+    // Only entries of equal size can have equal content
+    let modified = common
+        .par_iter()
+        .map(|&(old_entry, new_entry)| -> Result<bool, ArcError> {
+            if old_entry.original_size() != new_entry.original_size() {
+                return Ok(true);
+            }
+            if metadata_only {
+                return Ok(old_entry.compressed_size() != new_entry.compressed_size());
+            }
+            Ok(content_digest(old_path, old_entry)? != content_digest(new_path, new_entry)?)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for ((old_entry, new_entry), _) in common
+        .into_iter()
+        .zip(modified)
+        .filter(|(_, modified)| *modified)
+    {
+        changes.push(EntryChange {
+            name: old_entry.name().to_string(),
+            change: Change::Modified,
+            old: Some(old_entry.clone()),
+            new: Some(new_entry.clone()),
+        });
+    }
+
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    info!("{} entries differ.", changes.len());
+    Ok(changes)
+}
+
+fn content_digest(archive_path: &Path, entry: &ArcEntry) -> Result<String, ArcError> {
+    let mut task_reader = BufReader::new(File::open(archive_path).with_path(archive_path)?);
+    Ok(sha256_hex(&reader::read_entry_data(
+        &mut task_reader,
+        entry,
+    )?))
+}
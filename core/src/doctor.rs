@@ -0,0 +1,361 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use log::info;
+use rayon::prelude::*;
+
+use crate::{
+    HEADER_SIZE, decompress_data, decrypt_name,
+    error::{ArcError, PathContext},
+    format,
+};
+
+// --- Doctor Logic ---
+// Unlike unpacking, diagnosing never stops at the first problem: the metadata
+// is scanned leniently and every check runs, so a single report lists
+// everything that is wrong with an archive together with a hint on what to
+// try next.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Diagnosis {
+    /// Number of metadata entries that could be parsed.
+    pub entry_count: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl Diagnosis {
+    fn push(&mut self, severity: Severity, message: String, suggestion: Option<&str>) {
+        self.findings.push(Finding {
+            severity,
+            message,
+            suggestion: suggestion.map(str::to_string),
+        });
+    }
+
+    /// Whether any finding is an error.
+    pub fn has_errors(&self) -> bool {
+        self.findings.iter().any(|f| f.severity == Severity::Error)
+    }
+}
+
+// Entry as laid out in the metadata, before any validation
+struct RawEntry {
+    index: usize,
+    encrypted_name: Vec<u8>,
+    name: Option<String>,
+    compressed_size: u32,
+    original_size: u32,
+    offset: u32,
+}
+
+impl RawEntry {
+    fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("entry {} ({name})", self.index),
+            None => format!("entry {}", self.index),
+        }
+    }
+}
+
+pub fn diagnose(input_path: impl AsRef<Path>) -> Result<Diagnosis, ArcError> {
+    let input_path = input_path.as_ref();
+    info!("Diagnosing: {:?}", input_path);
+
+    if !input_path.is_file() {
+        return Err(ArcError::NotFound(input_path.to_path_buf()));
+    }
+    let file_len = input_path.metadata().with_path(input_path)?.len();
+    let mut reader = BufReader::new(File::open(input_path).with_path(input_path)?);
+    let mut diagnosis = Diagnosis::default();
+
+    // 1. Global header
+    if file_len < HEADER_SIZE as u64 {
+        diagnosis.push(
+            Severity::Error,
+            format!("file is only {file_len} bytes long, too short for the global header"),
+            Some("the archive is truncated or not a Silky archive at all"),
+        );
+        return Ok(diagnosis);
+    }
+    let mut header = [0u8; HEADER_SIZE as usize];
+    reader.read_exact(&mut header)?;
+    let metadata_size = format::effective_metadata_size(format::parse_header(&header)?, file_len);
+    let metadata_end = HEADER_SIZE as u64 + metadata_size as u64;
+    if metadata_end > file_len {
+        diagnosis.push(
+            Severity::Error,
+            format!(
+                "metadata section claims {metadata_size} bytes but the file is only {file_len} bytes long"
+            ),
+            Some(
+                "the archive is truncated inside its metadata, or uses a different header layout \
+                 (other Silky-family engines such as AI6WIN use formats this tool does not support)",
+            ),
+        );
+    }
+
+    // 2. Metadata entries, scanned leniently
+    let mut metadata = vec![0u8; (metadata_end.min(file_len) - HEADER_SIZE as u64) as usize];
+    reader.read_exact(&mut metadata)?;
+    let entries = scan_entries(&metadata, &mut diagnosis);
+    diagnosis.entry_count = entries.len();
+    if entries.is_empty() && metadata_size > 0 {
+        diagnosis.push(
+            Severity::Error,
+            "no metadata entry could be parsed".to_string(),
+            Some("this is probably not a Silky archive, or it uses an unsupported format variant"),
+        );
+    }
+
+    // 3. Per-entry checks
+    let undecodable = entries.iter().filter(|e| e.name.is_none()).count();
+    if undecodable > 0 {
+        diagnosis.push(
+            Severity::Error,
+            format!("{undecodable} entry name(s) cannot be decoded as CP932"),
+            Some(
+                "the archive may have been produced by a tool using another name encoding or \
+                 obfuscation",
+            ),
+        );
+    }
+    for entry in &entries {
+        check_name(entry, &mut diagnosis);
+        check_bounds(entry, metadata_end, file_len, &mut diagnosis);
+    }
+    check_overlaps(&entries, &mut diagnosis);
+    check_layout(&entries, metadata_end, file_len, &mut diagnosis);
+
+    // 4. Decompression test for every entry that fits in the file
+    let decompress_failures: Vec<Finding> = entries
+        .par_iter()
+        .filter(|e| e.offset as u64 + e.compressed_size as u64 <= file_len)
+        .filter(|e| e.compressed_size != e.original_size)
+        .map(|entry| -> Result<Option<Finding>, ArcError> {
+            let mut task_reader = BufReader::new(File::open(input_path).with_path(input_path)?);
+            task_reader.seek(SeekFrom::Start(entry.offset as u64))?;
+            let mut compressed_data = vec![0u8; entry.compressed_size as usize];
+            task_reader.read_exact(&mut compressed_data)?;
+            let message = match decompress_data(&compressed_data, entry.original_size) {
+                Ok(data) if data.len() == entry.original_size as usize => return Ok(None),
+                Ok(data) => format!(
+                    "{} decompresses to {} bytes, expected {}",
+                    entry.label(),
+                    data.len(),
+                    entry.original_size
+                ),
+                Err(e) => format!("{} fails to decompress: {e}", entry.label()),
+            };
+            Ok(Some(Finding {
+                severity: Severity::Error,
+                message,
+                suggestion: Some("the entry data is corrupt; restore it from a backup".to_string()),
+            }))
+        })
+        .filter_map(Result::transpose)
+        .collect::<Result<_, _>>()?;
+    diagnosis.findings.extend(decompress_failures);
+
+    info!(
+        "Diagnosis finished with {} finding(s).",
+        diagnosis.findings.len()
+    );
+    Ok(diagnosis)
+}
+
+fn scan_entries(mut metadata: &[u8], diagnosis: &mut Diagnosis) -> Vec<RawEntry> {
+    let mut entries = Vec::new();
+    while !metadata.is_empty() {
+        let index = entries.len();
+        let (record, len) = match format::parse_entry(metadata) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let reason = match e {
+                    ArcError::InvalidFormat(reason) => reason,
+                    e => e.to_string(),
+                };
+                diagnosis.push(
+                    Severity::Error,
+                    format!("entry {index}: {reason}"),
+                    Some("the archive appears truncated, or the header's metadata size is wrong"),
+                );
+                break;
+            }
+        };
+        entries.push(RawEntry {
+            index,
+            name: decrypt_name(&record.encrypted_name).ok(),
+            encrypted_name: record.encrypted_name,
+            compressed_size: record.compressed_size,
+            original_size: record.original_size,
+            offset: record.offset,
+        });
+        metadata = &metadata[len..];
+    }
+    entries
+}
+
+fn check_name(entry: &RawEntry, diagnosis: &mut Diagnosis) {
+    if entry.encrypted_name.is_empty() {
+        diagnosis.push(
+            Severity::Warning,
+            format!("entry {} has an empty name", entry.index),
+            None,
+        );
+        return;
+    }
+    let Some(name) = &entry.name else {
+        return;
+    };
+    if name.chars().any(char::is_control) {
+        diagnosis.push(
+            Severity::Warning,
+            format!("{} contains control characters in its name", entry.label()),
+            Some("the name encoding is probably wrong for this archive"),
+        );
+    }
+    if name.split(['\\', '/']).any(|part| part == "..") || name.starts_with(['\\', '/']) {
+        diagnosis.push(
+            Severity::Warning,
+            format!("{} points outside the extraction directory", entry.label()),
+            Some("inspect the archive before extracting it"),
+        );
+    }
+}
+
+fn check_bounds(entry: &RawEntry, metadata_end: u64, file_len: u64, diagnosis: &mut Diagnosis) {
+    let end = entry.offset as u64 + entry.compressed_size as u64;
+    if (entry.offset as u64) < metadata_end {
+        diagnosis.push(
+            Severity::Error,
+            format!(
+                "{} starts at offset {}, inside the metadata section",
+                entry.label(),
+                entry.offset
+            ),
+            None,
+        );
+    }
+    if end > file_len {
+        diagnosis.push(
+            Severity::Error,
+            format!(
+                "archive appears truncated at {}: data ends at {end} but the file is {file_len} bytes",
+                entry.label()
+            ),
+            Some("the archive was not copied completely; obtain a full copy"),
+        );
+    }
+    if entry.compressed_size == entry.original_size {
+        return;
+    }
+    if entry.original_size == 0 {
+        diagnosis.push(
+            Severity::Warning,
+            format!(
+                "{} has compressed data but an original size of 0",
+                entry.label()
+            ),
+            None,
+        );
+    }
+}
+
+fn check_overlaps(entries: &[RawEntry], diagnosis: &mut Diagnosis) {
+    let mut by_offset: Vec<&RawEntry> = entries.iter().filter(|e| e.compressed_size > 0).collect();
+    by_offset.sort_by_key(|e| (e.offset, e.compressed_size));
+    for pair in by_offset.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let a_end = a.offset as u64 + a.compressed_size as u64;
+        if a.offset == b.offset && a.compressed_size == b.compressed_size {
+            diagnosis.push(
+                Severity::Info,
+                format!("{} and {} share the same data block", a.label(), b.label()),
+                None,
+            );
+        } else if a_end > b.offset as u64 {
+            diagnosis.push(
+                Severity::Error,
+                format!("{} overlaps the data of {}", a.label(), b.label()),
+                Some("offsets in the metadata are corrupt"),
+            );
+        }
+    }
+}
+
+// Entries need not be stored in metadata order or back to back: the offsets
+// alone decide where data lives. Such layouts are harmless but worth knowing
+// about before editing an archive by hand.
+fn check_layout(entries: &[RawEntry], metadata_end: u64, file_len: u64, diagnosis: &mut Diagnosis) {
+    if entries
+        .windows(2)
+        .any(|pair| pair[1].offset < pair[0].offset)
+    {
+        diagnosis.push(
+            Severity::Info,
+            "entries are not listed in data order".to_string(),
+            None,
+        );
+    }
+
+    let mut blocks: Vec<(u64, u64)> = entries
+        .iter()
+        .filter(|e| e.compressed_size > 0)
+        .map(|e| (e.offset as u64, e.offset as u64 + e.compressed_size as u64))
+        .collect();
+    blocks.sort_unstable();
+    let (mut gaps, mut gap_bytes) = (0, 0);
+    let mut cursor = metadata_end;
+    for (start, end) in blocks {
+        if start > cursor {
+            gaps += 1;
+            gap_bytes += start - cursor;
+        }
+        cursor = cursor.max(end);
+    }
+    if gaps > 0 {
+        diagnosis.push(
+            Severity::Info,
+            format!("{gaps} gap(s) totalling {gap_bytes} bytes between data blocks"),
+            None,
+        );
+    }
+    if file_len > cursor {
+        diagnosis.push(
+            Severity::Info,
+            format!(
+                "{} trailing bytes after the last data block",
+                file_len - cursor
+            ),
+            None,
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
+
+use log::info;
+use rayon::prelude::*;
+
+use crate::{
+    checksum::sha256_hex,
+    error::{ArcError, PathContext},
+    reader::{self, ArcEntry, ArcReader},
+};
+
+// --- Duplicate Content ---
+// Groups entries whose decompressed data is identical. Only entries sharing
+// a non-zero original size with another entry are read and hashed.
+
+/// Entries with identical content.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// SHA-256 of the content, lowercase hex.
+    pub digest: String,
+    /// Original size of each copy.
+    pub size: u32,
+    pub names: Vec<String>,
+    /// Stored bytes that deduplication would save. Copies already sharing a
+    /// data block count once.
+    pub wasted_bytes: u64,
+}
+
+/// Finds groups of identical entries, largest waste first.
+pub fn find_duplicates(archive_path: impl AsRef<Path>) -> Result<Vec<DuplicateGroup>, ArcError> {
+    let archive_path = archive_path.as_ref();
+    let archive = ArcReader::open(archive_path)?;
+
+    let mut by_size: HashMap<u32, Vec<&ArcEntry>> = HashMap::new();
+    // Empty entries (and directory placeholders) are trivially identical
+    for entry in archive
+        .entries()
+        .iter()
+        .filter(|entry| entry.original_size() > 0)
+    {
+        by_size
+            .entry(entry.original_size())
+            .or_default()
+            .push(entry);
+    }
+    let candidates: Vec<&ArcEntry> = by_size
+        .into_values()
+        .filter(|entries| entries.len() > 1)
+        .flatten()
+        .collect();
+    info!("Hashing {} candidate entries.", candidates.len());
+
+    let digests = candidates
+        .par_iter()
+        .map(|entry| -> Result<String, ArcError> {
+            let mut task_reader = BufReader::new(File::open(archive_path).with_path(archive_path)?);
+            Ok(sha256_hex(&reader::read_entry_data(
+                &mut task_reader,
+                entry,
+            )?))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut by_digest: HashMap<String, Vec<&ArcEntry>> = HashMap::new();
+    for (entry, digest) in candidates.into_iter().zip(digests) {
+        by_digest.entry(digest).or_default().push(entry);
+    }
+    let mut groups: Vec<DuplicateGroup> = by_digest
+        .into_iter()
+        .filter(|(_, entries)| entries.len() > 1)
+        .map(|(digest, mut entries)| {
+            entries.sort_by_key(|entry| entry.offset());
+            let mut blocks: Vec<(u32, u32)> = entries
+                .iter()
+                .map(|entry| (entry.offset(), entry.compressed_size()))
+                .collect();
+            blocks.dedup();
+            let stored: u64 = blocks.iter().map(|&(_, size)| size as u64).sum();
+            let kept = blocks
+                .iter()
+                .map(|&(_, size)| size as u64)
+                .min()
+                .unwrap_or(0);
+            DuplicateGroup {
+                digest,
+                size: entries[0].original_size(),
+                names: entries
+                    .iter()
+                    .map(|entry| entry.name().to_string())
+                    .collect(),
+                wasted_bytes: stored - kept,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.wasted_bytes
+            .cmp(&a.wasted_bytes)
+            .then_with(|| a.names.cmp(&b.names))
+    });
+    Ok(groups)
+}
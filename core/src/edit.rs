@@ -0,0 +1,194 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+use walkdir::WalkDir;
+
+use crate::{
+    PackFileInfo, PackOptions, PackSummary,
+    error::{ArcError, PathContext},
+    name, process_pack_files,
+    reader::{self, ArcEntry, ArcReader},
+    write_pack_files,
+};
+
+// --- Archive Editing ---
+// Rewrites an archive with some entries changed. Untouched entries keep
+// their position, stored name bytes and data block as is (compressed or
+// not); only new data goes through the pack pipeline. Entries sharing a
+// data block get a copy each.
+
+/// Changes applied by [`edit_archive`].
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveEdit {
+    /// Entries to append, as `(entry name, source file)`. The names must not
+    /// be in the archive yet.
+    pub additions: Vec<(String, PathBuf)>,
+    /// Names of entries to drop. Every name must be in the archive.
+    pub removals: HashSet<String>,
+    /// Entries whose data is replaced by the content of a file, keeping
+    /// their position. Every name must be in the archive. Entries that were
+    /// stored compressed are compressed again (if that helps), even without
+    /// `PackOptions::compress`.
+    pub replacements: HashMap<String, PathBuf>,
+}
+
+/// Rewrites `archive_path` to `output_path` (which may be the same file)
+/// with `edit` applied. New data is compressed according to `options`.
+pub fn edit_archive(
+    archive_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    edit: &ArchiveEdit,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    let archive_path = archive_path.as_ref();
+    let output_path = output_path.as_ref();
+    options.parallelism.install(|| {
+        let archive = ArcReader::open(archive_path)?;
+        let mut missing: Vec<String> = edit
+            .removals
+            .iter()
+            .chain(edit.replacements.keys())
+            .filter(|name| !archive.contains(name))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            missing.sort();
+            return Err(ArcError::MissingEntries(missing));
+        }
+        let mut new_files = Vec::new();
+        let mut new_names = HashSet::new();
+        for (entry_name, path) in &edit.additions {
+            let (entry_name, encrypted_name) = options.encode_name(entry_name)?;
+            if archive.contains(&entry_name) || !new_names.insert(entry_name.clone()) {
+                return Err(ArcError::DuplicateEntry(entry_name));
+            }
+            new_files.push(source_file(entry_name, encrypted_name, path)?);
+        }
+        let metrics = options.metrics.clone().unwrap_or_default();
+        process_pack_files(&mut new_files, options, &metrics)?;
+
+        // Replacements are processed in two groups, depending on whether
+        // they get compressed
+        let (recompressed, rest): (Vec<_>, Vec<_>) = archive
+            .entries()
+            .iter()
+            .filter_map(|entry| Some((entry, edit.replacements.get(entry.name())?)))
+            .map(|(entry, path)| {
+                let file =
+                    source_file(entry.name().to_string(), entry.encrypted_name.clone(), path)?;
+                Ok((entry.is_compressed(), file))
+            })
+            .collect::<Result<Vec<_>, ArcError>>()?
+            .into_iter()
+            .partition(|(compressed, _)| *compressed && !options.compress);
+        let mut recompressed: Vec<_> = recompressed.into_iter().map(|(_, file)| file).collect();
+        let mut rest: Vec<_> = rest.into_iter().map(|(_, file)| file).collect();
+        let compress_options = PackOptions {
+            compress: true,
+            ..options.clone()
+        };
+        process_pack_files(&mut recompressed, &compress_options, &metrics)?;
+        process_pack_files(&mut rest, options, &metrics)?;
+        let mut replaced: HashMap<String, PackFileInfo> = recompressed
+            .into_iter()
+            .chain(rest)
+            .map(|file| (file.name.clone(), file))
+            .collect();
+
+        // Copy the existing data blocks as stored
+        let mut task_reader = BufReader::new(File::open(archive_path).with_path(archive_path)?);
+        let mut files = Vec::with_capacity(archive.entries().len() + new_files.len());
+        for entry in archive.entries() {
+            if edit.removals.contains(entry.name()) {
+                continue;
+            }
+            if let Some(file) = replaced.remove(entry.name()) {
+                files.push(file);
+                continue;
+            }
+            let data = reader::read_raw_entry_data(&mut task_reader, entry)?;
+            files.push(stored_file(entry, data));
+        }
+        info!(
+            "Removed {}, replaced {} and added {} entries.",
+            archive.entries().len() - files.len(),
+            edit.replacements.len(),
+            new_files.len()
+        );
+        files.extend(new_files);
+        write_pack_files(files, output_path, options, &metrics)
+    })
+}
+
+/// An existing entry copied with its stored name bytes and data block.
+pub(crate) fn stored_file(entry: &ArcEntry, data: Vec<u8>) -> PackFileInfo {
+    PackFileInfo {
+        relative_path: PathBuf::from(entry.name()),
+        name: entry.name().to_string(),
+        full_path: PathBuf::new(),
+        encrypted_name: entry.encrypted_name.clone(),
+        original_size: entry.original_size(),
+        compressed_size: entry.compressed_size(),
+        compressed_data: Some(data),
+        offset: 0,
+        source_digest: None,
+        spilled: None,
+    }
+}
+
+/// A file to read into the archive as entry `name`.
+pub(crate) fn source_file(
+    name: String,
+    encrypted_name: Vec<u8>,
+    path: &Path,
+) -> Result<PackFileInfo, ArcError> {
+    let metadata = path.metadata().with_path(path)?;
+    if !metadata.is_file() {
+        return Err(ArcError::NotFound(path.to_path_buf()));
+    }
+    Ok(PackFileInfo {
+        relative_path: path.to_path_buf(),
+        name,
+        full_path: path.to_path_buf(),
+        encrypted_name,
+        original_size: metadata.len() as u32,
+        compressed_data: None,
+        compressed_size: 0,
+        offset: 0,
+        source_digest: None,
+        spilled: None,
+    })
+}
+
+/// The edit applying the files below `overlay_dir` to the archive at
+/// `archive_path`: files named like an entry replace its data, all others
+/// are added.
+pub fn overlay_edit(
+    archive_path: impl AsRef<Path>,
+    overlay_dir: impl AsRef<Path>,
+) -> Result<ArchiveEdit, ArcError> {
+    let overlay_dir = overlay_dir.as_ref();
+    if !overlay_dir.is_dir() {
+        return Err(ArcError::NotFound(overlay_dir.to_path_buf()));
+    }
+    let archive = ArcReader::open(archive_path)?;
+    let mut edit = ArchiveEdit::default();
+    for entry in WalkDir::new(overlay_dir).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let name = name::path_to_entry_name(entry.path().strip_prefix(overlay_dir)?)?;
+        if archive.contains(&name) {
+            edit.replacements.insert(name, entry.into_path());
+        } else {
+            edit.additions.push((name, entry.into_path()));
+        }
+    }
+    Ok(edit)
+}
@@ -0,0 +1,368 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{File, OpenOptions},
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use log::{debug, info};
+
+use crate::{
+    HEADER_SIZE, PackFileInfo, PackOptions, cancel,
+    edit::source_file,
+    error::{ArcError, PathContext},
+    format::{EntryRecord, write_entry, write_header},
+    process_pack_files,
+    reader::{ArcEntry, ArcReader},
+    selection::normalize_entry_name,
+};
+
+// --- In-Place Editor ---
+// Queues changes to an archive and applies them to the file itself. Data
+// blocks that stay stay where they are: new data is appended after the
+// last of them, and only blocks the grown metadata section would overlap
+// are moved to the end. Blocks left unused by removed or replaced entries
+// become gaps (run `optimize` to drop them), except at the end of the
+// file, which is truncated.
+
+/// Where the data of an entry comes from.
+#[derive(Debug, Clone)]
+enum Data {
+    /// The entry's block in the archive, as `(offset, compressed_size)`.
+    Stored(u32, u32),
+    /// A file to read, compressed even without `PackOptions::compress` if
+    /// the flag is set.
+    Source(PathBuf, bool),
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    name: String,
+    encrypted_name: Vec<u8>,
+    original_size: u32,
+    data: Data,
+}
+
+/// What [`ArcEditor::commit`] did.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommitSummary {
+    /// Entries in metadata order, with their final offsets.
+    pub entries: Vec<ArcEntry>,
+    /// Existing data blocks moved out of the way of the metadata.
+    pub moved_blocks: usize,
+    /// Bytes written to the data section, moved blocks included.
+    pub written: u64,
+    /// Size of the archive file after the commit.
+    pub archive_size: u64,
+}
+
+/// Edits an archive in place, rewriting as little of it as possible.
+///
+/// Changes are checked when they are queued and written by
+/// [`ArcEditor::commit`]. The commit is not atomic: an interrupted commit
+/// leaves a broken archive, so keep a backup (or use
+/// [`edit_archive`](crate::edit::edit_archive), which writes a new file).
+#[derive(Debug)]
+pub struct ArcEditor {
+    path: PathBuf,
+    options: PackOptions,
+    slots: Vec<Slot>,
+}
+
+impl ArcEditor {
+    /// An editor for the archive at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ArcError> {
+        Self::with_options(path, PackOptions::default())
+    }
+
+    /// Like [`ArcEditor::open`]. `options` supply the compression settings
+    /// and name fallbacks for new data; deduplication and provenance do not
+    /// apply.
+    pub fn with_options(path: impl AsRef<Path>, options: PackOptions) -> Result<Self, ArcError> {
+        let path = path.as_ref().to_path_buf();
+        let archive = ArcReader::open(&path)?;
+        let data_start = HEADER_SIZE as u64 + archive.metadata_size() as u64;
+        let slots = archive
+            .entries()
+            .iter()
+            .map(|entry| {
+                if entry.compressed_size() > 0 && (entry.offset() as u64) < data_start {
+                    return Err(ArcError::InvalidFormat(format!(
+                        "entry data at {} lies inside the metadata (before {data_start})",
+                        entry.offset()
+                    )));
+                }
+                Ok(Slot {
+                    name: entry.name().to_string(),
+                    encrypted_name: entry.encrypted_name.clone(),
+                    original_size: entry.original_size(),
+                    data: Data::Stored(entry.offset(), entry.compressed_size()),
+                })
+            })
+            .collect::<Result<_, ArcError>>()?;
+        Ok(Self {
+            path,
+            options,
+            slots,
+        })
+    }
+
+    /// Names of the entries as they will be after the commit.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.slots.iter().map(|slot| slot.name.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_ok()
+    }
+
+    fn position(&self, name: &str) -> Result<usize, ArcError> {
+        self.slots
+            .iter()
+            .position(|slot| slot.name == name)
+            .ok_or_else(|| ArcError::MissingEntries(vec![name.to_string()]))
+    }
+
+    /// `name` (`\` or `/` separated) as stored, failing if it is taken.
+    fn encode_new_name(&self, name: &str) -> Result<(String, Vec<u8>), ArcError> {
+        let (name, encrypted_name) = self.options.encode_name(&normalize_entry_name(name))?;
+        if self.contains(&name) {
+            return Err(ArcError::DuplicateEntry(name));
+        }
+        Ok((name, encrypted_name))
+    }
+
+    /// Appends entry `name` with the content of the file at `source`.
+    pub fn add(&mut self, name: &str, source: impl AsRef<Path>) -> Result<(), ArcError> {
+        let (name, encrypted_name) = self.encode_new_name(name)?;
+        let source = source.as_ref();
+        let file = source_file(name, encrypted_name, source)?;
+        self.slots.push(Slot {
+            name: file.name,
+            encrypted_name: file.encrypted_name,
+            original_size: file.original_size,
+            data: Data::Source(source.to_path_buf(), false),
+        });
+        Ok(())
+    }
+
+    /// Drops entry `name`.
+    pub fn remove(&mut self, name: &str) -> Result<(), ArcError> {
+        let index = self.position(name)?;
+        self.slots.remove(index);
+        Ok(())
+    }
+
+    /// Renames entry `from` to `to`, keeping its position and data.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), ArcError> {
+        let index = self.position(from)?;
+        let (name, encrypted_name) = self.encode_new_name(to)?;
+        let slot = &mut self.slots[index];
+        slot.name = name;
+        slot.encrypted_name = encrypted_name;
+        Ok(())
+    }
+
+    /// Replaces the data of entry `name` with the content of the file at
+    /// `source`, keeping its position. An entry that was stored compressed
+    /// is compressed again (if that helps), even without
+    /// `PackOptions::compress`.
+    pub fn replace(&mut self, name: &str, source: impl AsRef<Path>) -> Result<(), ArcError> {
+        let index = self.position(name)?;
+        let slot = &self.slots[index];
+        let source = source.as_ref();
+        let file = source_file(slot.name.clone(), Vec::new(), source)?;
+        let recompress = match &slot.data {
+            Data::Stored(_, compressed_size) => *compressed_size != slot.original_size,
+            Data::Source(_, recompress) => *recompress,
+        };
+        let slot = &mut self.slots[index];
+        slot.original_size = file.original_size;
+        slot.data = Data::Source(source.to_path_buf(), recompress);
+        Ok(())
+    }
+
+    /// Writes the queued changes to the archive.
+    pub fn commit(self) -> Result<CommitSummary, ArcError> {
+        let options = &self.options;
+        options.parallelism.install(|| {
+            // New data is processed in two groups, depending on whether it
+            // gets compressed
+            let (recompressed, rest): (Vec<_>, Vec<_>) = self
+                .slots
+                .iter()
+                .enumerate()
+                .filter_map(|(index, slot)| match &slot.data {
+                    Data::Source(path, recompress) => Some((index, path, *recompress)),
+                    Data::Stored(..) => None,
+                })
+                .map(|(index, path, recompress)| {
+                    let slot = &self.slots[index];
+                    let file = source_file(slot.name.clone(), slot.encrypted_name.clone(), path)?;
+                    Ok((index, recompress, file))
+                })
+                .collect::<Result<Vec<_>, ArcError>>()?
+                .into_iter()
+                .partition(|(_, recompress, _)| *recompress && !options.compress);
+            let split = |files: Vec<(usize, bool, PackFileInfo)>| -> (Vec<_>, Vec<_>) {
+                files
+                    .into_iter()
+                    .map(|(index, _, file)| (index, file))
+                    .unzip()
+            };
+            let (mut indices, mut rest) = split(rest);
+            let (recompressed_indices, mut recompressed) = split(recompressed);
+            let metrics = options.metrics.clone().unwrap_or_default();
+            process_pack_files(&mut rest, options, &metrics)?;
+            let compress_options = PackOptions {
+                compress: true,
+                ..options.clone()
+            };
+            process_pack_files(&mut recompressed, &compress_options, &metrics)?;
+            indices.extend(recompressed_indices);
+            let new_files: Vec<_> = indices
+                .into_iter()
+                .zip(rest.into_iter().chain(recompressed))
+                .collect();
+
+            let mut records: Vec<EntryRecord> = self
+                .slots
+                .iter()
+                .map(|slot| {
+                    let (offset, compressed_size) = match slot.data {
+                        Data::Stored(offset, size) => (offset, size),
+                        Data::Source(..) => (0, 0),
+                    };
+                    EntryRecord {
+                        encrypted_name: slot.encrypted_name.clone(),
+                        compressed_size,
+                        original_size: slot.original_size,
+                        offset,
+                    }
+                })
+                .collect();
+            let metadata_size: usize = records.iter().map(EntryRecord::encoded_len).sum();
+            let metadata_size = u32::try_from(metadata_size)
+                .map_err(|_| ArcError::InvalidFormat("the metadata exceeds 4 GiB".to_string()))?;
+            let data_start = HEADER_SIZE as u64 + metadata_size as u64;
+
+            // Kept blocks, once each: those the metadata now overlaps are read
+            // before anything is written and go after the last one that stays
+            let blocks: BTreeSet<(u32, u32)> = records
+                .iter()
+                .zip(&self.slots)
+                .filter(|(record, slot)| {
+                    matches!(slot.data, Data::Stored(..)) && record.compressed_size > 0
+                })
+                .map(|(record, _)| (record.offset, record.compressed_size))
+                .collect();
+            let (overlapped, kept): (Vec<_>, Vec<_>) = blocks
+                .into_iter()
+                .partition(|(offset, _)| (*offset as u64) < data_start);
+            let mut end = kept
+                .iter()
+                .map(|(offset, size)| *offset as u64 + *size as u64)
+                .max()
+                .unwrap_or(0)
+                .max(data_start);
+            let mut moved = HashMap::new();
+            let mut moved_data = Vec::with_capacity(overlapped.len());
+            let mut archive = BufReader::new(File::open(&self.path).with_path(&self.path)?);
+            for (offset, size) in overlapped {
+                let mut data = vec![0; size as usize];
+                archive.seek(SeekFrom::Start(offset as u64))?;
+                archive.read_exact(&mut data)?;
+                moved.insert((offset, size), end);
+                moved_data.push((end, data));
+                end += size as u64;
+            }
+            drop(archive);
+            let mut new_data = Vec::with_capacity(new_files.len());
+            for (index, file) in &new_files {
+                let record = &mut records[*index];
+                record.compressed_size = file.compressed_size;
+                record.original_size = file.original_size;
+                new_data.push((end, file));
+                end += file.compressed_size as u64;
+            }
+            if end > u32::MAX as u64 {
+                return Err(ArcError::InvalidFormat(
+                    "the archive exceeds the 4 GiB offset limit".to_string(),
+                ));
+            }
+            for (record, slot) in records.iter_mut().zip(&self.slots) {
+                record.offset = match slot.data {
+                    Data::Stored(offset, size) => match moved.get(&(offset, size)) {
+                        Some(&position) => position as u32,
+                        // Empty entries may point anywhere outside the metadata
+                        None => offset.max(data_start as u32),
+                    },
+                    Data::Source(..) => 0,
+                };
+            }
+            for ((index, _), (position, _)) in new_files.iter().zip(&new_data) {
+                records[*index].offset = *position as u32;
+            }
+
+            // Nothing is written before this point; after it the archive is
+            // only consistent again once the metadata is
+            cancel::check(options.cancel.as_ref())?;
+            let mut output = OpenOptions::new()
+                .write(true)
+                .open(&self.path)
+                .with_path(&self.path)?;
+            let mut written = 0;
+            let mut write_block = |position: u64, data: &[u8]| -> Result<(), ArcError> {
+                debug!("Writing {} bytes at {}", data.len(), position);
+                output.seek(SeekFrom::Start(position))?;
+                output.write_all(data)?;
+                written += data.len() as u64;
+                Ok(())
+            };
+            for (position, data) in &moved_data {
+                write_block(*position, data)?;
+            }
+            // New data may wait in a spill file under a memory limit
+            for (position, file) in new_data {
+                if let Some(data) = file.payload()? {
+                    write_block(position, &data)?;
+                }
+            }
+            let mut metadata = Vec::with_capacity(data_start as usize);
+            write_header(&mut metadata, metadata_size);
+            for record in &records {
+                write_entry(&mut metadata, record)?;
+            }
+            output.seek(SeekFrom::Start(0))?;
+            output.write_all(&metadata)?;
+            output.set_len(end)?;
+            output.sync_all()?;
+
+            info!(
+                "Committed {} entries, moved {} blocks and wrote {} data bytes.",
+                records.len(),
+                moved.len(),
+                written
+            );
+            let entries = self
+                .slots
+                .iter()
+                .zip(records)
+                .map(|(slot, record)| ArcEntry {
+                    name: slot.name.clone(),
+                    encrypted_name: record.encrypted_name,
+                    compressed_size: record.compressed_size,
+                    original_size: record.original_size,
+                    offset: record.offset,
+                })
+                .collect();
+            Ok(CommitSummary {
+                entries,
+                moved_blocks: moved.len(),
+                written,
+                archive_size: end,
+            })
+        })
+    }
+}
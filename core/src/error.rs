@@ -0,0 +1,104 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ArcError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("I/O error on {path:?}: {source}")]
+    PathIo {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("File not found: {0}")]
+    NotFound(PathBuf),
+    #[error("Invalid .arc format: {0}")]
+    InvalidFormat(String),
+    #[error("Failed to decode filename (CP932): {0:?}")]
+    NameDecodeError(Vec<u8>),
+    #[error("Failed to encode filename (CP932): {0}")]
+    NameEncodeError(String),
+    #[error("Invalid entry name {0:?}: {1}")]
+    InvalidEntryName(String, NameError),
+    #[error("LZSS compression error: {0:?}")]
+    LzssCompressError(String),
+    #[error("LZSS decompression error: {0:?}")]
+    LzssDecompressError(String),
+    #[error("Walkdir error: {0}")]
+    WalkdirError(#[from] walkdir::Error),
+    #[error("Path strip prefix error: {0}")]
+    StripPrefixError(#[from] std::path::StripPrefixError),
+    #[error("Cannot get filename from path: {0:?}")]
+    NoFilename(PathBuf),
+    #[error("Output path is not specified and cannot be derived from input: {0:?}")]
+    CannotDeriveOutputPath(PathBuf),
+    #[error("No files to pack in {0:?}")]
+    EmptyInput(PathBuf),
+    #[error("Invalid output template: {0:?}")]
+    InvalidTemplate(String),
+    #[error("Invalid pattern {0:?}: {1}")]
+    InvalidPattern(String, String),
+    #[error("Entry already in the archive: {0}")]
+    DuplicateEntry(String),
+    #[error("Not in the archive: {}", .0.join(", "))]
+    MissingEntries(Vec<String>),
+    #[error("Script error: {0}")]
+    Script(String),
+    #[cfg(feature = "zip")]
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Failed to build thread pool: {0}")]
+    ThreadPool(String),
+    #[error("Verification failed for {0}: {1}")]
+    VerificationFailed(String, String),
+    #[error("{1} entries of {0} could not be extracted")]
+    ExtractFailed(String, usize),
+    #[error("{0} (warnings are errors in strict mode)")]
+    StrictWarning(String),
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+/// Logs `message` as a warning, or fails with it in strict mode.
+pub(crate) fn warn_or_fail(strict: bool, message: String) -> Result<(), ArcError> {
+    if strict {
+        return Err(ArcError::StrictWarning(message));
+    }
+    log::warn!("{message}");
+    Ok(())
+}
+
+/// Attaches the path an IO operation was working on to its error.
+pub trait PathContext<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T, ArcError>;
+}
+
+impl<T> PathContext<T> for io::Result<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T, ArcError> {
+        self.map_err(|source| ArcError::PathIo {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    #[error("name is empty")]
+    Empty,
+    #[error("name cannot be encoded by the name codec (CP932 by default)")]
+    NotEncodable,
+    #[error("encoded name is {0} bytes long, the limit is 255")]
+    TooLong(usize),
+    #[error("name contains forbidden character {0:?}")]
+    ForbiddenCharacter(char),
+    #[error("name uses '/' instead of '\\' as separator")]
+    ForwardSlash,
+    #[error("name contains an empty, '.' or '..' path component")]
+    InvalidComponent,
+}
@@ -0,0 +1,123 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::error::ArcError;
+
+// --- Binary Layout ---
+// Parsing and serialization of the archive header and metadata on plain byte
+// slices, with no file or reader types involved. The IO layer (`reader`,
+// packing) reads the bytes and hands them to these functions.
+//
+//   u32 LE  metadata_size   (excluding these 4 bytes)
+//   entries, until metadata_size bytes are consumed:
+//     u8      name_len
+//     [u8]    encrypted name
+//     u32 BE  compressed_size
+//     u32 BE  original_size
+//     u32 BE  offset         (absolute, from the start of the file)
+//   data blocks
+
+/// Size of the global header.
+pub const HEADER_SIZE: u32 = 4;
+/// Header value that versions before the format cleanup wrote for archives
+/// without entries: the end offset of the (empty) metadata instead of its
+/// size.
+pub const LEGACY_EMPTY_HEADER: u32 = HEADER_SIZE;
+/// Size of an entry's fixed fields after its name.
+pub const ENTRY_FIELDS_SIZE: usize = 12;
+
+/// A metadata entry as stored, with its name still encrypted.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryRecord {
+    pub encrypted_name: Vec<u8>,
+    pub compressed_size: u32,
+    pub original_size: u32,
+    pub offset: u32,
+}
+
+impl EntryRecord {
+    /// Number of bytes the entry takes in the metadata section.
+    pub fn encoded_len(&self) -> usize {
+        1 + self.encrypted_name.len() + ENTRY_FIELDS_SIZE
+    }
+}
+
+/// Parses the global header and returns the metadata size.
+pub fn parse_header(data: &[u8]) -> Result<u32, ArcError> {
+    if data.len() < HEADER_SIZE as usize {
+        return Err(ArcError::InvalidFormat(format!(
+            "header needs {HEADER_SIZE} bytes, got {}",
+            data.len()
+        )));
+    }
+    Ok(LittleEndian::read_u32(data))
+}
+
+/// The metadata size of an archive of `file_len` bytes whose header holds
+/// `metadata_size`. A file consisting of just the [`LEGACY_EMPTY_HEADER`] is
+/// an empty archive from an older version.
+pub fn effective_metadata_size(metadata_size: u32, file_len: u64) -> u32 {
+    if metadata_size == LEGACY_EMPTY_HEADER && file_len == HEADER_SIZE as u64 {
+        0
+    } else {
+        metadata_size
+    }
+}
+
+/// Parses one metadata entry from the start of `data` and returns it
+/// together with the number of bytes consumed.
+pub fn parse_entry(data: &[u8]) -> Result<(EntryRecord, usize), ArcError> {
+    let Some(&name_len) = data.first() else {
+        return Err(ArcError::InvalidFormat(
+            "metadata ends before an entry".to_string(),
+        ));
+    };
+    let len = 1 + name_len as usize + ENTRY_FIELDS_SIZE;
+    if data.len() < len {
+        return Err(ArcError::InvalidFormat(format!(
+            "metadata ends in the middle of an entry ({} of {len} bytes present)",
+            data.len()
+        )));
+    }
+    let (name, fields) = data[1..len].split_at(name_len as usize);
+    let record = EntryRecord {
+        encrypted_name: name.to_vec(),
+        compressed_size: BigEndian::read_u32(&fields[0..4]),
+        original_size: BigEndian::read_u32(&fields[4..8]),
+        offset: BigEndian::read_u32(&fields[8..12]),
+    };
+    Ok((record, len))
+}
+
+/// Parses a whole metadata section (without the header).
+pub fn parse_metadata(mut data: &[u8]) -> Result<Vec<EntryRecord>, ArcError> {
+    let mut records = Vec::new();
+    while !data.is_empty() {
+        let (record, len) = parse_entry(data)?;
+        records.push(record);
+        data = &data[len..];
+    }
+    Ok(records)
+}
+
+pub fn write_header(out: &mut Vec<u8>, metadata_size: u32) {
+    out.extend_from_slice(&metadata_size.to_le_bytes());
+}
+
+/// Appends `record` in its metadata layout.
+pub fn write_entry(out: &mut Vec<u8>, record: &EntryRecord) -> Result<(), ArcError> {
+    let name_len = u8::try_from(record.encrypted_name.len()).map_err(|_| {
+        ArcError::InvalidFormat(format!(
+            "entry name is {} bytes, the limit is {}",
+            record.encrypted_name.len(),
+            u8::MAX
+        ))
+    })?;
+    out.push(name_len);
+    out.extend_from_slice(&record.encrypted_name);
+    for field in [record.compressed_size, record.original_size, record.offset] {
+        out.extend_from_slice(&field.to_be_bytes());
+    }
+    Ok(())
+}
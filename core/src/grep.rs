@@ -0,0 +1,149 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use log::info;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    error::{ArcError, PathContext},
+    name::NameEncoding,
+    pattern::NamePatterns,
+    reader::{self, ArcReader},
+};
+
+// --- Content Search ---
+
+/// What [`grep_archive`] looks for.
+#[derive(Debug, Clone)]
+pub enum GrepPattern {
+    /// A regular expression matched against each line of text, decoded with
+    /// [`GrepOptions::encoding`].
+    Text(Regex),
+    /// A byte sequence searched for anywhere in the data.
+    Bytes(Vec<u8>),
+}
+
+impl GrepPattern {
+    /// A [`GrepPattern::Text`] from a regular expression.
+    pub fn text(pattern: &str, ignore_case: bool) -> Result<Self, ArcError> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map(GrepPattern::Text)
+            .map_err(|e| ArcError::InvalidPattern(pattern.to_string(), e.to_string()))
+    }
+}
+
+/// Options for [`grep_archive`].
+#[derive(Debug, Clone, Default)]
+pub struct GrepOptions {
+    /// Encoding of text entries. Scripts are usually CP932.
+    pub encoding: NameEncoding,
+    /// Only search entries matching these patterns.
+    pub include: Option<NamePatterns>,
+}
+
+/// A match inside an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    pub entry: String,
+    /// Byte offset in the decompressed entry: of the matching line for text
+    /// patterns, of the match itself for byte patterns.
+    pub offset: u64,
+    /// 1-based line number, for text patterns.
+    pub line_number: Option<usize>,
+    /// The decoded line, or the matched bytes and the ones following them
+    /// as hex.
+    pub text: String,
+}
+
+/// Number of bytes shown from a byte pattern match on.
+const BYTES_CONTEXT: usize = 16;
+
+/// Decompresses the entries of `archive_path` in parallel and searches
+/// them for `pattern`. Matches are in metadata order, then by offset.
+pub fn grep_archive(
+    archive_path: impl AsRef<Path>,
+    pattern: &GrepPattern,
+    options: &GrepOptions,
+) -> Result<Vec<GrepMatch>, ArcError> {
+    let archive_path = archive_path.as_ref();
+    let archive = ArcReader::open(archive_path)?;
+    let entries: Vec<_> = archive
+        .entries()
+        .iter()
+        .filter(|entry| {
+            options
+                .include
+                .as_ref()
+                .is_none_or(|include| include.is_match(entry.name()))
+        })
+        .collect();
+    let matches: Vec<GrepMatch> = entries
+        .par_iter()
+        .map(|entry| -> Result<Vec<GrepMatch>, ArcError> {
+            let mut task_reader = BufReader::new(File::open(archive_path).with_path(archive_path)?);
+            let data = reader::read_entry_data(&mut task_reader, entry)?;
+            let found = match pattern {
+                GrepPattern::Text(regex) => grep_lines(&data, regex, options.encoding),
+                GrepPattern::Bytes(needle) => grep_bytes(&data, needle),
+            };
+            Ok(found
+                .into_iter()
+                .map(|(offset, line_number, text)| GrepMatch {
+                    entry: entry.name().to_string(),
+                    offset,
+                    line_number,
+                    text,
+                })
+                .collect())
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+    info!(
+        "Found {} matches in {} entries.",
+        matches.len(),
+        entries.len()
+    );
+    Ok(matches)
+}
+
+/// Lines of `data` matching `regex`, as `(offset, line number, line)`.
+fn grep_lines(
+    data: &[u8],
+    regex: &Regex,
+    encoding: NameEncoding,
+) -> Vec<(u64, Option<usize>, String)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    for (index, line) in data.split(|&b| b == b'\n').enumerate() {
+        let trimmed = line.strip_suffix(b"\r").unwrap_or(line);
+        let (text, _) = encoding.encoding().decode_without_bom_handling(trimmed);
+        if regex.is_match(&text) {
+            found.push((offset as u64, Some(index + 1), text.into_owned()));
+        }
+        offset += line.len() + 1;
+    }
+    found
+}
+
+/// Occurrences of `needle` in `data`, as `(offset, None, hex)`.
+fn grep_bytes(data: &[u8], needle: &[u8]) -> Vec<(u64, Option<usize>, String)> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    data.windows(needle.len())
+        .enumerate()
+        .filter(|(_, window)| *window == needle)
+        .map(|(offset, _)| {
+            let end = (offset + needle.len().max(BYTES_CONTEXT)).min(data.len());
+            let hex: Vec<String> = data[offset..end]
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            (offset as u64, None, hex.join(" "))
+        })
+        .collect()
+}
@@ -0,0 +1,1743 @@
+// Enters a `tracing` span for the rest of the enclosing block when the
+// `tracing` feature is enabled, and expands to nothing otherwise.
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($($arg)*).entered();
+    };
+}
+
+#[cfg(feature = "tokio")]
+pub mod async_api;
+pub mod cancel;
+#[cfg(feature = "checksum")]
+pub mod checksum;
+pub mod compat;
+#[cfg(feature = "checksum")]
+pub mod diff;
+pub mod doctor;
+#[cfg(feature = "checksum")]
+pub mod dupes;
+pub mod edit;
+pub mod editor;
+pub mod error;
+pub mod format;
+#[cfg(feature = "regex")]
+pub mod grep;
+#[cfg(feature = "json")]
+pub mod manifest;
+mod memory;
+pub mod merge;
+pub mod metrics;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod name;
+pub mod normalize;
+pub mod optimize;
+pub mod parallel;
+#[cfg(feature = "json")]
+pub mod patch;
+#[cfg(feature = "patterns")]
+pub mod pattern;
+#[cfg(feature = "json")]
+pub mod provenance;
+pub mod reader;
+#[cfg(feature = "regex")]
+pub mod rename;
+pub mod selection;
+pub mod split;
+pub mod spool;
+pub mod stats;
+pub mod stream;
+#[cfg(feature = "test-support")]
+pub mod synth;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod writer;
+#[cfg(feature = "zip")]
+pub mod zip_source;
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    hash::{DefaultHasher, Hasher},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf}, // Mutex needed for parallel writing to the same archive potentially
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use log::{debug, error, info, warn};
+use lzss::{Lzss, SliceReader, SliceWriter};
+pub use name::{decrypt_name, encrypt_name, validate_entry_name};
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use crate::{
+    error::{ArcError, PathContext, warn_or_fail},
+    metrics::Metrics,
+    parallel::Parallelism,
+    reader::{ArcEntry, ArcReader, EntryFailure},
+}; // To easily walk directories for packing
+
+// --- .arc File Format ---
+// Global Header (4 bytes):
+//   - metadata_size (u32, Little Endian): Size of the metadata section, not
+//     counting this header. File data starts at 4 + metadata_size.
+//
+// Metadata Section (from offset 4 to 4 + metadata_size):
+//   Repeated File Entry structures:
+//     - name_length (u8): Length of the encrypted_name.
+//     - encrypted_name (Vec<u8>): Filename encrypted with a specific algorithm
+//       (CP932 + byte shift).
+//     - compressed_size (u32, Big Endian): Size of the file data block in the
+//       archive.
+//     - original_size (u32, Big Endian): Original size of the file before
+//       compression.
+//     - file_data_offset (u32, Big Endian): Absolute offset of this file's data
+//       block from the start of the archive.
+//
+// File Data Section (from 4 + metadata_size to EOF):
+//   Concatenated file data blocks, potentially LZSS compressed if
+// compressed_size != original_size. --- End of Format ---
+
+// Define the specific LZSS parameters based on the Python code analysis
+// N=4096 (Buffer Size) => EI=12 (since 1 << 12 = 4096)
+// F=18 (Max Match Length)
+// Threshold=2 (Min Match Length)
+// For the lzss crate: F = (1 << EJ) + Threshold => 18 = (1 << EJ) + 2 => 16 = 1
+// << EJ => EJ=4 Padding byte C = 0x00 (from python default)
+
+type SilkyLzss = Lzss<12, 4, 0x00, { 1 << 12 }, { 2 << 12 }>;
+
+pub(crate) use format::HEADER_SIZE;
+
+// --- LZSS ---
+
+/// Upper bound of how much a Silky LZSS stream can expand: a flag byte and
+/// eight 2-byte references decode to at most 8 * 18 bytes.
+const MAX_EXPANSION: usize = 9;
+
+/// Decompresses a Silky LZSS block that is expected to expand to
+/// `original_size` bytes. Pass `u32::MAX` if the size is unknown.
+pub fn decompress_data(compressed_data: &[u8], original_size: u32) -> Result<Vec<u8>, ArcError> {
+    decompress_data_tracked(compressed_data, original_size, None)
+}
+
+/// Like [`decompress_data`], counting the produced bytes in `metrics` as the
+/// block is decoded.
+pub(crate) fn decompress_data_tracked(
+    compressed_data: &[u8],
+    original_size: u32,
+    metrics: Option<&Metrics>,
+) -> Result<Vec<u8>, ArcError> {
+    // 4 times buffer size of the original data, but never more than the input
+    // can expand to, since `original_size` comes from untrusted metadata
+    let buffer_size = (original_size as usize)
+        .saturating_mul(4)
+        .min(compressed_data.len() * MAX_EXPANSION);
+    let mut decompressed_data = vec![0u8; buffer_size];
+    let result = SilkyLzss::decompress_stack(
+        SliceReader::new(compressed_data),
+        ProgressCounter::new(SliceWriter::new(&mut decompressed_data), metrics),
+    )
+    .map_err(|e| ArcError::LzssDecompressError(e.to_string()))?;
+    decompressed_data.truncate(result);
+    Ok(decompressed_data)
+}
+
+/// Compresses `data` with the Silky LZSS parameters.
+pub fn compress_data(data: &[u8]) -> Result<Vec<u8>, ArcError> {
+    compress_data_tracked(data, None)
+}
+
+/// Like [`compress_data`], counting the consumed bytes in `metrics` as the
+/// data is encoded.
+pub(crate) fn compress_data_tracked(
+    data: &[u8],
+    metrics: Option<&Metrics>,
+) -> Result<Vec<u8>, ArcError> {
+    let mut compressed_output: Vec<u8> = vec![0; data.len() * 2]; // Start with double of original size capacity
+    let compressed_len = SilkyLzss::compress_stack(
+        ProgressCounter::new(SliceReader::new(data), metrics),
+        SliceWriter::new(&mut compressed_output),
+    )
+    .map_err(|e| ArcError::LzssCompressError(format!("{e:?}")))?;
+    compressed_output.truncate(compressed_len);
+    Ok(compressed_output)
+}
+
+/// Bytes between two progress updates while (de)compressing one block.
+const PROGRESS_CHUNK: u64 = 1 << 16;
+
+/// Passes bytes through to an LZSS reader or writer and adds their count to
+/// [`Metrics::snapshot`]'s `bytes_done` every [`PROGRESS_CHUNK`] bytes, so
+/// progress moves during a single large entry.
+struct ProgressCounter<'a, T> {
+    inner: T,
+    metrics: Option<&'a Metrics>,
+    pending: u64,
+}
+
+impl<'a, T> ProgressCounter<'a, T> {
+    fn new(inner: T, metrics: Option<&'a Metrics>) -> Self {
+        Self {
+            inner,
+            metrics,
+            pending: 0,
+        }
+    }
+
+    fn count(&mut self) {
+        self.pending += 1;
+        if self.pending == PROGRESS_CHUNK {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(metrics) = self.metrics {
+            metrics.add_done(self.pending);
+        }
+        self.pending = 0;
+    }
+}
+
+impl<T: lzss::Read> lzss::Read for ProgressCounter<'_, T> {
+    type Error = T::Error;
+
+    fn read(&mut self) -> Result<Option<u8>, Self::Error> {
+        let byte = self.inner.read()?;
+        match byte {
+            Some(_) => self.count(),
+            None => self.flush(),
+        }
+        Ok(byte)
+    }
+}
+
+impl<T: lzss::Write> lzss::Write for ProgressCounter<'_, T> {
+    type Output = T::Output;
+    type Error = T::Error;
+
+    fn write(&mut self, data: u8) -> Result<(), Self::Error> {
+        self.inner.write(data)?;
+        self.count();
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Self::Output, Self::Error> {
+        self.flush();
+        self.inner.finish()
+    }
+}
+
+// --- Unpack Logic ---
+
+/// How `--resume` decides that an already extracted file can be kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeMode {
+    /// Keep files whose size matches the entry's original size.
+    Size,
+    /// Additionally compare the file's content with the entry data.
+    Content,
+}
+
+/// What extraction does with output files that already exist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replace the file.
+    #[default]
+    Replace,
+    /// Keep the file and skip the entry.
+    Skip,
+    /// Fail with an [`io::ErrorKind::AlreadyExists`] error.
+    Fail,
+}
+
+impl std::str::FromStr for OverwritePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "replace" => Ok(OverwritePolicy::Replace),
+            "skip" => Ok(OverwritePolicy::Skip),
+            "fail" | "error" => Ok(OverwritePolicy::Fail),
+            _ => Err(format!(
+                "unknown overwrite policy {s:?}, expected one of replace, skip, fail"
+            )),
+        }
+    }
+}
+
+/// Options controlling extraction.
+#[derive(Debug, Clone, Default)]
+pub struct UnpackOptions {
+    /// Skip entries that were already extracted by an interrupted run.
+    pub resume: Option<ResumeMode>,
+    /// Check every decompressed entry against its `original_size`.
+    pub verify: bool,
+    /// Expected SHA-256 digests by entry name, checked when `verify` is set.
+    #[cfg(feature = "checksum")]
+    pub checksums: Option<HashMap<String, String>>,
+    /// Thread pool the extraction runs on.
+    pub parallelism: Parallelism,
+    /// Counters updated while extracting, for callers to sample.
+    pub metrics: Option<Arc<Metrics>>,
+    /// Candidate encodings for entry names. `None` uses
+    /// [`name::DEFAULT_NAME_ENCODINGS`].
+    pub name_encodings: Option<Vec<name::NameEncoding>>,
+    /// Decrypts entry names instead of the Silky scheme, so
+    /// `name_encodings` are not tried.
+    pub name_codec: Option<Arc<dyn name::NameCodec>>,
+    /// Bytes of stored and decompressed entry data held at once while
+    /// extracting.
+    pub memory_limit: Option<u64>,
+    /// Create entries sharing a data block with an earlier entry as hard
+    /// links to that entry's file instead of writing the data again.
+    pub hardlink_shared: bool,
+    /// Fail with [`ArcError::StrictWarning`] instead of logging warnings,
+    /// e.g. when a name has to be sanitized.
+    pub strict: bool,
+    /// Only extract entries with these names (`\` separated, see
+    /// [`selection::read_entry_list`]). Names missing from the archive are
+    /// warned about.
+    pub entries: Option<HashSet<String>>,
+    /// Fail with [`ArcError::MissingEntries`] before extracting anything if
+    /// a name in `entries` is not in the archive.
+    pub require_entries: bool,
+    /// Only extract entries matching one of these patterns.
+    #[cfg(feature = "patterns")]
+    pub include: Option<pattern::NamePatterns>,
+    /// Skip entries matching one of these patterns.
+    #[cfg(feature = "patterns")]
+    pub exclude: Option<pattern::NamePatterns>,
+    /// Only extract entries whose original size is in this range.
+    pub size_range: selection::SizeRange,
+    /// Applied in order to entry names before they become output paths.
+    #[cfg(feature = "regex")]
+    pub rename_rules: Vec<rename::RenameRule>,
+    /// What to do with existing files that are not skipped by `resume`.
+    pub overwrite: OverwritePolicy,
+    /// Extract every entry directly into the output directory under its
+    /// file name. Names taken by another entry get the entry's index
+    /// appended.
+    pub flatten: bool,
+    /// Log entries that fail to extract and continue with the others,
+    /// failing with [`ArcError::ExtractFailed`] at the end.
+    pub keep_going: bool,
+    /// Checked between entries. Files created by a cancelled extraction are
+    /// removed again.
+    pub cancel: Option<cancel::CancelToken>,
+}
+
+impl UnpackOptions {
+    /// Whether `include` and `exclude` let the entry `name` through.
+    fn matches_patterns(&self, name: &str) -> bool {
+        #[cfg(feature = "patterns")]
+        {
+            self.include
+                .as_ref()
+                .is_none_or(|patterns| patterns.is_match(name))
+                && !self
+                    .exclude
+                    .as_ref()
+                    .is_some_and(|patterns| patterns.is_match(name))
+        }
+        #[cfg(not(feature = "patterns"))]
+        {
+            let _ = name;
+            true
+        }
+    }
+}
+
+pub fn handle_unpack(
+    input_path: impl AsRef<Path>,
+    output_dir: impl AsRef<Path>,
+) -> Result<(), ArcError> {
+    handle_unpack_with(input_path, output_dir, &UnpackOptions::default())
+}
+
+pub fn handle_unpack_with(
+    input_path: impl AsRef<Path>,
+    output_dir: impl AsRef<Path>,
+    options: &UnpackOptions,
+) -> Result<(), ArcError> {
+    let input_path = input_path.as_ref();
+    let output_dir = output_dir.as_ref();
+    options.parallelism.install(|| {
+        let failures = unpack_impl(input_path, output_dir, options, false)?;
+        check_failures(&input_path.to_string_lossy(), failures)
+    })
+}
+
+/// Like [`handle_unpack_with`], reading the archive from `reader` instead
+/// of a file, e.g. a `Cursor` over an archive in memory. Data blocks are read
+/// one at a time and decompressed in parallel.
+pub fn handle_unpack_from_reader<R: Read + Seek + Send>(
+    reader: R,
+    output_dir: impl AsRef<Path>,
+    options: &UnpackOptions,
+) -> Result<(), ArcError> {
+    let output_dir = output_dir.as_ref();
+    options.parallelism.install(|| {
+        info!("Starting unpack of an archive stream");
+        let archive = match &options.name_codec {
+            Some(codec) => ArcReader::with_codec(reader, codec.as_ref())?,
+            None => ArcReader::with_encodings(reader, unpack_encodings(options))?,
+        };
+        info!(
+            "Read {} file entries from metadata (names in {}).",
+            archive.entries().len(),
+            archive.name_encoding()
+        );
+        let entries = archive.entries().to_vec();
+        let archive = Mutex::new(archive);
+        let failures = extract_entries(&entries, output_dir, options, |entry| {
+            lock(&archive).read_raw_entry(entry)
+        })?;
+        check_failures("archive stream", failures)
+    })
+}
+
+/// Like [`handle_unpack_with`], but problems with single entries do not
+/// stop the extraction: metadata is parsed leniently (see
+/// [`ArcReader::open_lenient`]), entries that fail to extract or verify are
+/// skipped as with `keep_going`, and all of these are returned instead of an
+/// error. Parsing stops at the first malformed metadata record.
+pub fn handle_unpack_lenient(
+    input_path: impl AsRef<Path>,
+    output_dir: impl AsRef<Path>,
+    options: &UnpackOptions,
+) -> Result<Vec<EntryFailure>, ArcError> {
+    let input_path = input_path.as_ref();
+    let output_dir = output_dir.as_ref();
+    let options = UnpackOptions {
+        keep_going: true,
+        ..options.clone()
+    };
+    options
+        .parallelism
+        .install(|| unpack_impl(input_path, output_dir, &options, true))
+}
+
+fn unpack_encodings(options: &UnpackOptions) -> &[name::NameEncoding] {
+    options
+        .name_encodings
+        .as_deref()
+        .unwrap_or(name::DEFAULT_NAME_ENCODINGS)
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(options)))]
+fn unpack_impl(
+    input_path: &Path,
+    output_dir: &Path,
+    options: &UnpackOptions,
+    lenient: bool,
+) -> Result<Vec<EntryFailure>, ArcError> {
+    info!("Starting unpack of: {:?}", input_path);
+
+    if !input_path.exists() {
+        return Err(ArcError::NotFound(input_path.to_path_buf()));
+    }
+
+    // 1. Read global header and metadata entries
+    let (archive, mut failures) = if let Some(codec) = &options.name_codec {
+        ArcReader::open_with_codec_lenient(input_path, codec.as_ref(), lenient)?
+    } else if lenient {
+        ArcReader::open_lenient(input_path, unpack_encodings(options))?
+    } else {
+        let archive = ArcReader::open_with_encodings(input_path, unpack_encodings(options))?;
+        (archive, Vec::new())
+    };
+    info!(
+        "Read {} file entries from metadata (names in {}).",
+        archive.entries().len(),
+        archive.name_encoding()
+    );
+
+    // 2. Extract files (using Rayon for parallelism)
+    // We need to be careful with file handles for parallel seeking/reading.
+    // Cloning the reader or opening new handles per thread is necessary.
+    // Using pread might be more efficient if the OS supports it well, but less
+    // portable. Let's reopen the file for each parallel task to ensure thread
+    // safety.
+    failures.extend(extract_entries(
+        archive.entries(),
+        output_dir,
+        options,
+        |entry| {
+            // Open a *new* handle to the archive for this thread/task
+            let file = File::open(input_path).with_path(input_path)?;
+            reader::read_raw_entry_data(&mut BufReader::new(file), entry)
+        },
+    )?);
+    failures.sort_by_key(|failure| failure.index);
+    Ok(failures)
+}
+
+/// Extracts `file_entries` into `output_dir`, getting each entry's stored data
+/// block from `read_raw`. Returns the entries that failed verification, or
+/// failed to extract with `keep_going`.
+fn extract_entries(
+    file_entries: &[ArcEntry],
+    output_dir: &Path,
+    options: &UnpackOptions,
+    read_raw: impl Fn(&ArcEntry) -> Result<Vec<u8>, ArcError> + Sync,
+) -> Result<Vec<EntryFailure>, ArcError> {
+    info!("Output directory: {:?}", output_dir);
+    fs::create_dir_all(output_dir).with_path(output_dir)?; // Create output dir if needed
+
+    let skipped = AtomicUsize::new(0);
+    let metrics = options.metrics.clone().unwrap_or_default();
+    trace_span!("extract", entries = file_entries.len());
+    let failures = Mutex::new(Vec::new());
+    let output_paths = entry_output_paths(file_entries, output_dir, options)?;
+    // Entries that are not selected are neither extracted nor linked
+    let selected: Vec<bool> = file_entries
+        .iter()
+        .map(|entry| {
+            options
+                .entries
+                .as_ref()
+                .is_none_or(|names| names.contains(entry.name()))
+                && options.size_range.contains(entry.original_size() as u64)
+                && options.matches_patterns(entry.name())
+        })
+        .collect();
+    if let Some(names) = &options.entries {
+        let present: HashSet<&str> = file_entries.iter().map(ArcEntry::name).collect();
+        let mut missing: Vec<&String> = names
+            .iter()
+            .filter(|name| !present.contains(name.as_str()))
+            .collect();
+        missing.sort();
+        if options.require_entries && !missing.is_empty() {
+            return Err(ArcError::MissingEntries(
+                missing.into_iter().cloned().collect(),
+            ));
+        }
+        for name in missing {
+            warn_or_fail(
+                options.strict,
+                format!("Entry {name:?} is not in the archive"),
+            )?;
+        }
+    }
+    let sources: Vec<Option<usize>> = if options.hardlink_shared {
+        reader::shared_data_sources(file_entries)
+            .into_iter()
+            .map(|source| source.filter(|&source| selected[source]))
+            .collect()
+    } else {
+        vec![None; file_entries.len()]
+    };
+
+    metrics.add_total(
+        file_entries
+            .iter()
+            .zip(&sources)
+            .zip(&selected)
+            .filter(|((_, source), selected)| **selected && source.is_none())
+            .map(|((entry, _), _)| entry.original_size() as u64)
+            .sum(),
+    );
+
+    // Files that did not exist before, removed again if cancelled
+    let created = Mutex::new(Vec::new());
+    let budget = options.memory_limit.map(memory::MemoryBudget::new);
+    let extract_entry = |index: usize,
+                         entry: &ArcEntry,
+                         output_file_path: &Path|
+     -> Result<(), ArcError> {
+        cancel::check(options.cancel.as_ref())?;
+        trace_span!(
+            "unpack_entry",
+            name = entry.name(),
+            compressed_size = entry.compressed_size(),
+            original_size = entry.original_size()
+        );
+
+        if name::is_directory_entry(entry.name()) && entry.original_size() == 0 {
+            fs::create_dir_all(output_file_path).with_path(output_file_path)?;
+            metrics.entry_done();
+            debug!("Created directory: {}", entry.name());
+            return Ok(());
+        }
+
+        // A previous run may already have extracted this entry
+        let existing_size = fs::metadata(output_file_path).ok().map(|m| m.len());
+        let size_matches = existing_size == Some(entry.original_size() as u64);
+        if options.resume == Some(ResumeMode::Size) && size_matches {
+            skipped.fetch_add(1, Ordering::Relaxed);
+            metrics.add_done(entry.original_size() as u64);
+            metrics.entry_done();
+            debug!("Skipped (already extracted): {}", entry.name());
+            return Ok(());
+        }
+        // Content resume decides about such files once the data is read
+        let content_resume = options.resume == Some(ResumeMode::Content) && size_matches;
+        if existing_size.is_some()
+            && !content_resume
+            && keep_existing(output_file_path, options.overwrite)?
+        {
+            skipped.fetch_add(1, Ordering::Relaxed);
+            metrics.add_done(entry.original_size() as u64);
+            metrics.entry_done();
+            return Ok(());
+        }
+
+        // Ensure parent directory exists for the output file
+        if let Some(parent) = output_file_path.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+
+        let _reservation = memory::reserve(
+            budget.as_ref(),
+            entry.compressed_size() as u64 + entry.original_size() as u64,
+        );
+        let raw_data = metrics.time_io(|| read_raw(entry))?;
+        metrics.add_read(raw_data.len());
+        let final_data = metrics
+            .time_compression(|| reader::decode_entry_data(entry, raw_data, Some(&metrics)))?;
+
+        if options.verify
+            && let Err(reason) = verify_entry_data(entry, &final_data, options)
+        {
+            error!("Verification failed for {}: {}", entry.name(), reason);
+            lock(&failures).push(EntryFailure {
+                index,
+                name: entry.name().to_string(),
+                error: ArcError::VerificationFailed(entry.name().to_string(), reason),
+            });
+            return Ok(());
+        }
+
+        if content_resume && fs::read(output_file_path).with_path(output_file_path)? == final_data {
+            skipped.fetch_add(1, Ordering::Relaxed);
+            metrics.entry_done();
+            debug!("Skipped (already extracted): {}", entry.name());
+            return Ok(());
+        }
+        if content_resume && keep_existing(output_file_path, options.overwrite)? {
+            skipped.fetch_add(1, Ordering::Relaxed);
+            metrics.entry_done();
+            return Ok(());
+        }
+
+        // Write the final data to the output file
+        if existing_size.is_none() {
+            lock(&created).push(output_file_path.to_path_buf());
+        }
+        metrics
+            .time_io(|| fs::write(output_file_path, &final_data))
+            .with_path(output_file_path)?;
+        metrics.add_written(final_data.len());
+        metrics.entry_done();
+
+        info!("Unpacked: {}", entry.name());
+        Ok(())
+    };
+    let extracted = file_entries
+        .par_iter()
+        .enumerate()
+        .zip(&output_paths)
+        .zip(&sources)
+        .zip(&selected)
+        .filter(|((_, source), selected)| **selected && source.is_none())
+        .map(|((((index, entry), output_file_path), _), _)| {
+            match extract_entry(index, entry, output_file_path) {
+                Err(e) if options.keep_going && !matches!(e, ArcError::Cancelled) => {
+                    error!("Failed to extract {}: {}", entry.name(), e);
+                    lock(&failures).push(EntryFailure {
+                        index,
+                        name: entry.name().to_string(),
+                        error: e,
+                    });
+                    Ok(())
+                }
+                result => result,
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|_| {
+            link_shared_entries(
+                file_entries,
+                &output_paths,
+                &sources,
+                &selected,
+                options,
+                &metrics,
+                &created,
+            )
+        });
+    if let Err(ArcError::Cancelled) = extracted {
+        let created = created.into_inner().unwrap_or_else(PoisonError::into_inner);
+        info!("Cancelled, removing {} extracted files.", created.len());
+        for path in created {
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Failed to remove {:?}: {}", path, e);
+            }
+        }
+    }
+    extracted?; // Propagate the first error
+
+    let skipped = skipped.into_inner();
+    if skipped > 0 {
+        info!("Skipped {} already extracted entries.", skipped);
+    }
+    let mut failures = failures
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner);
+    failures.sort_by_key(|failure| failure.index);
+    info!("=== Unpack finished ===");
+    Ok(failures)
+}
+
+/// Turns the entries extraction skipped over into the error reported
+/// outside lenient mode.
+fn check_failures(archive_name: &str, failures: Vec<EntryFailure>) -> Result<(), ArcError> {
+    let failed = failures
+        .iter()
+        .filter(|failure| matches!(failure.error, ArcError::VerificationFailed(..)))
+        .count();
+    let errors = failures.len() - failed;
+    if errors > 0 {
+        return Err(ArcError::ExtractFailed(archive_name.to_string(), errors));
+    }
+    if failed > 0 {
+        return Err(ArcError::VerificationFailed(
+            archive_name.to_string(),
+            format!("{failed} entries are corrupt and were not extracted"),
+        ));
+    }
+    Ok(())
+}
+
+/// Step 3 of extraction: links entries sharing a data block to the file
+/// extracted first.
+fn link_shared_entries(
+    file_entries: &[ArcEntry],
+    output_paths: &[PathBuf],
+    sources: &[Option<usize>],
+    selected: &[bool],
+    options: &UnpackOptions,
+    metrics: &Metrics,
+    created: &Mutex<Vec<PathBuf>>,
+) -> Result<(), ArcError> {
+    for (((entry, link_path), source), selected) in file_entries
+        .iter()
+        .zip(output_paths)
+        .zip(sources)
+        .zip(selected)
+    {
+        cancel::check(options.cancel.as_ref())?;
+        let (Some(source), true) = (*source, *selected) else {
+            continue;
+        };
+        let source_path = &output_paths[source];
+        if link_path == source_path || !source_path.is_file() {
+            // Same output file, or the source failed verification
+            continue;
+        }
+        if let Some(parent) = link_path.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+        if link_path.exists() {
+            if keep_existing(link_path, options.overwrite)? {
+                continue;
+            }
+            fs::remove_file(link_path).with_path(link_path)?;
+        } else {
+            lock(created).push(link_path.clone());
+        }
+        fs::hard_link(source_path, link_path).with_path(link_path)?;
+        metrics.entry_done();
+        info!(
+            "Linked: {} -> {}",
+            entry.name(),
+            file_entries[source].name()
+        );
+    }
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether the existing file at `path` is kept, according to `policy`.
+fn keep_existing(path: &Path, policy: OverwritePolicy) -> Result<bool, ArcError> {
+    match policy {
+        OverwritePolicy::Replace => Ok(false),
+        OverwritePolicy::Skip => {
+            debug!("Kept existing file: {:?}", path);
+            Ok(true)
+        }
+        OverwritePolicy::Fail => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "the file already exists",
+        ))
+        .with_path(path),
+    }
+}
+
+/// Computes where each entry gets extracted to. The rename rules of
+/// `options` are applied first; names that are then empty or contain control
+/// characters are sanitized (see [`name::sanitize_entry_name`]) with a
+/// warning. A renamed entry never takes the path of another entry.
+pub(crate) fn entry_output_paths(
+    entries: &[ArcEntry],
+    output_dir: &Path,
+    options: &UnpackOptions,
+) -> Result<Vec<PathBuf>, ArcError> {
+    let mut taken: HashSet<PathBuf> = entries
+        .iter()
+        .map(|entry| name::entry_name_to_path(entry.name()))
+        .collect();
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            if options.flatten && name::is_directory_entry(entry.name()) {
+                // Nothing to create below the output directory
+                return Ok(output_dir.to_path_buf());
+            }
+            #[cfg(feature = "regex")]
+            let mut renamed = rename::apply_rename_rules(&options.rename_rules, entry.name());
+            #[cfg(not(feature = "regex"))]
+            let mut renamed = Cow::Borrowed(entry.name());
+            if options.flatten
+                && let Some((_, file_name)) = renamed.rsplit_once(name::SEPARATOR)
+            {
+                renamed = Cow::Owned(file_name.to_string());
+            }
+            let sanitized = name::sanitize_entry_name(&renamed, index);
+            let mut path = name::entry_name_to_path(&sanitized);
+            if sanitized != entry.name() && !taken.insert(path.clone()) {
+                path = name::entry_name_to_path(&format!("{sanitized}_{index}"));
+                taken.insert(path.clone());
+            }
+            if sanitized != renamed {
+                warn_or_fail(
+                    options.strict,
+                    format!("Entry {index} {:?} extracted as {path:?}", entry.name()),
+                )?;
+            } else if renamed != entry.name() {
+                debug!("Renamed {:?} to {:?}", entry.name(), path);
+            }
+            Ok(output_dir.join(path))
+        })
+        .collect()
+}
+
+/// Checks decompressed entry data against the metadata and, if provided, the
+/// expected checksum.
+fn verify_entry_data(entry: &ArcEntry, data: &[u8], options: &UnpackOptions) -> Result<(), String> {
+    if data.len() != entry.original_size() as usize {
+        return Err(format!(
+            "decompressed to {} bytes, expected {}",
+            data.len(),
+            entry.original_size()
+        ));
+    }
+    #[cfg(not(feature = "checksum"))]
+    let _ = options;
+    #[cfg(feature = "checksum")]
+    if let Some(expected) = options
+        .checksums
+        .as_ref()
+        .and_then(|checksums| checksums.get(entry.name()))
+    {
+        let actual = checksum::sha256_hex(data);
+        if &actual != expected {
+            return Err(format!(
+                "checksum {actual} does not match expected {expected}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+// --- Pack Logic ---
+
+/// Order of the entries packed from a directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntryOrder {
+    /// The order the directory walk finds files in, which depends on the
+    /// file system.
+    #[default]
+    Walk,
+    /// Sorted by entry name, so the archive is the same on every system.
+    Name,
+}
+
+impl std::str::FromStr for EntryOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "walk" => Ok(EntryOrder::Walk),
+            "name" => Ok(EntryOrder::Name),
+            _ => Err(format!(
+                "unknown entry order {s:?}, expected one of walk, name"
+            )),
+        }
+    }
+}
+
+// Intermediate structure for packing
+#[derive(Debug)]
+struct PackFileInfo {
+    relative_path: PathBuf,
+    name: String, // Entry name, using '\' as separator
+    full_path: PathBuf,
+    encrypted_name: Vec<u8>,
+    original_size: u32,
+    // These are determined after processing
+    compressed_data: Option<Vec<u8>>, // Holds compressed or original data
+    compressed_size: u32,
+    offset: u32, // Placeholder
+    // SHA-256 of the source file, for the provenance entry
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
+    source_digest: Option<String>,
+    // Where the processed data went instead of `compressed_data` under a
+    // memory limit, as (spill file, offset, length)
+    spilled: Option<(Arc<memory::SpillFile>, u64, u32)>,
+}
+
+impl PackFileInfo {
+    /// The processed data, from memory or the spill file.
+    fn payload(&self) -> Result<Option<Cow<'_, [u8]>>, ArcError> {
+        Ok(match (&self.compressed_data, &self.spilled) {
+            (Some(data), _) => Some(Cow::Borrowed(data)),
+            (None, Some((spill, offset, len))) => Some(Cow::Owned(spill.load(*offset, *len)?)),
+            (None, None) => None,
+        })
+    }
+
+    /// Length of the processed data, wherever it is kept.
+    fn payload_len(&self) -> usize {
+        match (&self.compressed_data, &self.spilled) {
+            (Some(data), _) => data.len(),
+            (None, Some((_, _, len))) => *len as usize,
+            (None, None) => 0,
+        }
+    }
+
+    fn to_record(&self) -> format::EntryRecord {
+        format::EntryRecord {
+            encrypted_name: self.encrypted_name.clone(),
+            compressed_size: self.compressed_size,
+            original_size: self.original_size,
+            offset: self.offset,
+        }
+    }
+
+    /// Metadata entry as it is written to the archive.
+    fn to_entry(&self) -> ArcEntry {
+        ArcEntry {
+            name: self.name.clone(),
+            encrypted_name: self.encrypted_name.clone(),
+            compressed_size: self.compressed_size,
+            original_size: self.original_size,
+            offset: self.offset,
+        }
+    }
+}
+
+/// Layout of a written archive.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackSummary {
+    /// Size of the metadata section, not counting the global header.
+    pub metadata_size: u32,
+    /// Total size of all data blocks.
+    pub data_size: u64,
+    /// Entries in the order they were written, with their final offsets.
+    pub entries: Vec<ArcEntry>,
+}
+
+impl PackSummary {
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Size of the whole archive file.
+    pub fn archive_size(&self) -> u64 {
+        HEADER_SIZE as u64 + self.metadata_size as u64 + self.data_size
+    }
+}
+
+/// Walks `input_dir` recursively and prepares the initial metadata of every
+/// file found.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(options)))]
+fn collect_pack_files(
+    input_dir: &Path,
+    options: &PackOptions,
+) -> Result<Vec<PackFileInfo>, ArcError> {
+    let mut files_to_pack: Vec<PackFileInfo> = Vec::new();
+    for entry_result in WalkDir::new(input_dir).into_iter().filter_map(|e| e.ok()) {
+        cancel::check(options.cancel.as_ref())?;
+        let path = entry_result.path();
+        if options.keep_empty_dirs && path != input_dir && is_empty_dir(path)? {
+            files_to_pack.push(directory_placeholder(input_dir, path, options)?);
+        } else if path.is_file() {
+            let relative_path = path.strip_prefix(input_dir)?.to_path_buf();
+            if let Some(filter) = &options.filter
+                && !filter.matches(&relative_path)
+            {
+                debug!("Filtered out: {:?}", relative_path);
+                continue;
+            }
+            let entry_name = name::path_to_entry_name(&relative_path)?;
+            let (name, encrypted_name) = options.encode_file_name(&entry_name)?;
+            if name != options.separator.apply(&entry_name) {
+                // The stored name then differs from the file name on disk
+                warn_or_fail(
+                    options.strict,
+                    format!(
+                        "{relative_path:?} contains ambiguous characters, storing it as {name:?}"
+                    ),
+                )?;
+            }
+            if options.replaces_with_provenance(&name) {
+                // Left over from unpacking an archive built with provenance
+                debug!("Replacing {:?} with fresh provenance", relative_path);
+                continue;
+            }
+            let metadata = fs::metadata(path).with_path(path)?;
+
+            files_to_pack.push(PackFileInfo {
+                relative_path, // Keep original relative path for clarity
+                name,
+                full_path: path.to_path_buf(),
+                encrypted_name,
+                original_size: metadata.len() as u32,
+                compressed_data: None, // Will be filled next
+                compressed_size: 0,    // Placeholder
+                offset: 0,             // Placeholder
+                source_digest: None,
+                spilled: None,
+            });
+        }
+    }
+
+    if options.order == EntryOrder::Name {
+        files_to_pack.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    Ok(files_to_pack)
+}
+
+fn is_empty_dir(path: &Path) -> Result<bool, ArcError> {
+    Ok(path.is_dir() && fs::read_dir(path).with_path(path)?.next().is_none())
+}
+
+/// The zero-byte entry recording the empty directory `path`, named like the
+/// directory with a trailing separator.
+fn directory_placeholder(
+    input_dir: &Path,
+    path: &Path,
+    options: &PackOptions,
+) -> Result<PackFileInfo, ArcError> {
+    let relative_path = path.strip_prefix(input_dir)?.to_path_buf();
+    let (mut name, _) = options.encode_file_name(&name::path_to_entry_name(&relative_path)?)?;
+    name.push(options.separator.separator());
+    let encrypted_name = name::encrypt_with(&name, options.codec())?;
+    debug!("Keeping empty directory {:?}", relative_path);
+    Ok(PackFileInfo {
+        relative_path,
+        name,
+        full_path: path.to_path_buf(),
+        encrypted_name,
+        original_size: 0,
+        compressed_data: None,
+        compressed_size: 0,
+        offset: 0,
+        source_digest: None,
+        spilled: None,
+    })
+}
+
+/// Reads every file's data and compresses it (if enabled and beneficial).
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(files_to_pack), fields(files = files_to_pack.len())))]
+fn process_pack_files(
+    files_to_pack: &mut [PackFileInfo],
+    options: &PackOptions,
+    metrics: &Metrics,
+) -> Result<(), ArcError> {
+    let compress = options.compress;
+    let min_compress_size = options
+        .min_compress_size
+        .unwrap_or(DEFAULT_MIN_COMPRESS_SIZE)
+        .max(1);
+    metrics.add_total(
+        files_to_pack
+            .iter()
+            .map(|file_info| file_info.original_size as u64)
+            .sum(),
+    );
+    let budget = options.memory_limit.map(memory::MemoryBudget::new);
+    let spill = match options.memory_limit {
+        Some(_) => Some(Arc::new(memory::SpillFile::new(
+            options.temp_dir.as_deref(),
+        )?)),
+        None => None,
+    };
+    files_to_pack
+        .par_iter_mut() // Use par_iter_mut to modify items in place
+        .map(|file_info| -> Result<(), ArcError> {
+            cancel::check(options.cancel.as_ref())?;
+            // The source data, and the compressed copy if there is one
+            let size = file_info.original_size as u64;
+            let needed = size
+                + if compress {
+                    memory::compressed_bound(size)
+                } else {
+                    0
+                };
+            let _reservation = memory::reserve(budget.as_ref(), needed);
+            trace_span!(
+                "pack_entry",
+                name = file_info.name.as_str(),
+                original_size = file_info.original_size
+            );
+            // Sources read into memory up front (e.g. from a zip) carry their data
+            let file_data = match file_info.compressed_data.take() {
+                Some(data) => data,
+                None => read_pack_source(file_info, metrics)?,
+            };
+            metrics.add_read(file_data.len());
+            assert_eq!(file_data.len() as u32, file_info.original_size); // Sanity check
+            #[cfg(feature = "json")]
+            if options.provenance {
+                file_info.source_digest = Some(checksum::sha256_hex(&file_data));
+            }
+
+            let forced_store = options.forced_store(&file_info.name);
+            if compress && !forced_store && file_info.original_size >= min_compress_size {
+                // Don't try to compress empty or tiny files
+                match metrics.time_compression(|| compress_data_tracked(&file_data, Some(metrics)))
+                {
+                    Ok(compressed_output) => {
+                        // Only use compressed data if it's actually smaller
+                        if (compressed_output.len() as u32) < file_info.original_size {
+                            file_info.compressed_size = compressed_output.len() as u32;
+                            file_info.compressed_data = Some(compressed_output);
+                            info!(
+                                "Compressed: {:?} ({} -> {} bytes)",
+                                file_info.relative_path,
+                                file_info.original_size,
+                                file_info.compressed_size
+                            );
+                        } else {
+                            // Compression didn't help, store original data
+                            file_info.compressed_data = Some(file_data);
+                            file_info.compressed_size = file_info.original_size;
+                            info!(
+                                "Storing uncompressed (LZSS ineffective): {:?}",
+                                file_info.relative_path
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        // Handle compression error, e.g., log it and store uncompressed
+                        warn_or_fail(
+                            options.strict,
+                            format!(
+                                "LZSS compression failed for {:?}: {:?}. Storing uncompressed.",
+                                file_info.relative_path, e
+                            ),
+                        )?;
+                        file_info.compressed_data = Some(file_data);
+                        file_info.compressed_size = file_info.original_size;
+                        // Optionally return an error: return
+                        // Err(ArcError::LzssCompressError(e));
+                    }
+                }
+            } else {
+                // Store original data if compression is disabled or file is too small
+                metrics.add_done(file_data.len() as u64);
+                file_info.compressed_data = Some(file_data);
+                file_info.compressed_size = file_info.original_size;
+                if compress && forced_store {
+                    info!(
+                        "Storing uncompressed (store pattern): {:?}",
+                        file_info.relative_path
+                    );
+                } else if compress {
+                    info!(
+                        "Storing uncompressed (smaller than {} bytes): {:?}",
+                        min_compress_size, file_info.relative_path
+                    );
+                } else {
+                    info!("Storing uncompressed: {:?}", file_info.relative_path);
+                }
+            }
+            if let Some(spill) = &spill
+                && let Some(data) = file_info.compressed_data.take()
+            {
+                let offset = spill.store(&data)?;
+                file_info.spilled = Some((spill.clone(), offset, data.len() as u32));
+            }
+            metrics.entry_done();
+            Ok(())
+        })
+        .collect::<Result<Vec<_>, ArcError>>()?; // Collect results and propagate errors
+    Ok(())
+}
+
+/// Reads the data of a file to pack. Directory placeholders have none.
+fn read_pack_source(file_info: &PackFileInfo, metrics: &Metrics) -> Result<Vec<u8>, ArcError> {
+    if name::is_directory_entry(&file_info.name) {
+        return Ok(Vec::new());
+    }
+    metrics
+        .time_io(|| fs::read(&file_info.full_path))
+        .with_path(&file_info.full_path)
+}
+
+/// Appends the reserved provenance entry describing the processed `files`,
+/// if `options` ask for one.
+fn add_provenance(files: &mut Vec<PackFileInfo>, options: &PackOptions) -> Result<(), ArcError> {
+    #[cfg(feature = "json")]
+    if options.provenance {
+        files.push(provenance_file(files, options)?);
+    }
+    let _ = (files, options);
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn provenance_file(
+    files: &[PackFileInfo],
+    options: &PackOptions,
+) -> Result<PackFileInfo, ArcError> {
+    let sources = files
+        .iter()
+        .map(|file| {
+            (
+                file.name.as_str(),
+                file.source_digest.clone().unwrap_or_default(),
+            )
+        })
+        .collect();
+    let data = provenance::provenance_json(sources, options);
+    let encrypted_name = name::encrypt_with(provenance::PROVENANCE_NAME, options.codec())?;
+    Ok(PackFileInfo {
+        relative_path: PathBuf::from(provenance::PROVENANCE_NAME),
+        name: provenance::PROVENANCE_NAME.to_string(),
+        full_path: PathBuf::new(),
+        encrypted_name,
+        original_size: data.len() as u32,
+        compressed_size: data.len() as u32,
+        compressed_data: Some(data),
+        offset: 0,
+        source_digest: None,
+        spilled: None,
+    })
+}
+
+/// For every file, the index of an earlier file with an identical data
+/// block it can point at instead of storing its own, with `dedup` set. Only
+/// blocks of matching sizes are hashed; empty files never share.
+fn shared_blocks(
+    files_to_pack: &[PackFileInfo],
+    dedup: bool,
+) -> Result<Vec<Option<usize>>, ArcError> {
+    let mut shared = vec![None; files_to_pack.len()];
+    if !dedup {
+        return Ok(shared);
+    }
+    let mut size_counts: HashMap<(u32, u32), usize> = HashMap::new();
+    for file_info in files_to_pack {
+        *size_counts
+            .entry((file_info.compressed_size, file_info.original_size))
+            .or_default() += 1;
+    }
+    // Blocks with the same hash are compared byte by byte before sharing
+    let mut firsts_by_hash: HashMap<(u32, u64), Vec<usize>> = HashMap::new();
+    for (index, file_info) in files_to_pack.iter().enumerate() {
+        let sizes = (file_info.compressed_size, file_info.original_size);
+        if file_info.compressed_size == 0 || size_counts[&sizes] < 2 {
+            continue;
+        }
+        let Some(data) = file_info.payload()? else {
+            continue;
+        };
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&data);
+        let firsts = firsts_by_hash
+            .entry((file_info.original_size, hasher.finish()))
+            .or_default();
+        let mut same = None;
+        for &first in firsts.iter() {
+            if files_to_pack[first].payload()?.as_deref() == Some(&*data) {
+                same = Some(first);
+                break;
+            }
+        }
+        if let Some(first) = same {
+            debug!(
+                "{:?} has the same content as {:?}, sharing its data",
+                file_info.relative_path, files_to_pack[first].relative_path
+            );
+            shared[index] = Some(first);
+        } else {
+            firsts.push(index);
+        }
+    }
+    Ok(shared)
+}
+
+/// Lays out the archive and returns the metadata block size. Files with a
+/// `shared` source point at its data block instead of getting their own.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+fn assign_offsets(
+    files_to_pack: &mut [PackFileInfo],
+    shared: &[Option<usize>],
+) -> Result<u32, ArcError> {
+    // Offsets are u32, so neither the metadata nor the data may reach past 4 GiB
+    let too_large =
+        || ArcError::InvalidFormat("the archive exceeds the 4 GiB offset limit".to_string());
+    let metadata_size: usize = files_to_pack
+        .iter()
+        .map(|file_info| {
+            1 // name_length
+            + file_info.encrypted_name.len()
+            + 4 // compressed_size
+            + 4 // original_size
+            + 4 // offset
+        })
+        .sum();
+    let metadata_block_size = u32::try_from(metadata_size).map_err(|_| too_large())?;
+    // Start with global header size
+    let mut current_offset = metadata_block_size
+        .checked_add(HEADER_SIZE)
+        .ok_or_else(too_large)?;
+    debug!("Calculated metadata_block_size: {metadata_block_size}");
+
+    // Assign final offsets
+    for index in 0..files_to_pack.len() {
+        if let Some(first) = shared[index] {
+            files_to_pack[index].offset = files_to_pack[first].offset;
+            continue;
+        }
+        let file_info = &mut files_to_pack[index];
+        file_info.offset = current_offset;
+        // Ensure the data is there (should be unless there was an error before)
+        let data_len = file_info.payload_len();
+        // Sanity check: data length should match calculated compressed_size
+        if data_len as u32 != file_info.compressed_size {
+            error!(
+                "Internal inconsistency for {:?}: stored data length {} != calculated compressed_size {}",
+                file_info.relative_path, data_len, file_info.compressed_size
+            );
+            // Potentially return an error here
+        }
+        current_offset = current_offset
+            .checked_add(file_info.compressed_size)
+            .ok_or_else(too_large)?;
+        //println!("  Assigning offset {} to {:?}", file_info.offset,
+        // file_info.relative_path);
+    }
+
+    Ok(metadata_block_size)
+}
+
+/// Files smaller than this are stored without attempting compression, since
+/// LZSS hardly ever wins on them.
+pub const DEFAULT_MIN_COMPRESS_SIZE: u32 = 256;
+
+/// Options controlling packing.
+#[derive(Debug, Clone, Default)]
+pub struct PackOptions {
+    /// Enable LZSS compression.
+    pub compress: bool,
+    /// Store files smaller than this many bytes uncompressed. `None` uses
+    /// [`DEFAULT_MIN_COMPRESS_SIZE`].
+    pub min_compress_size: Option<u32>,
+    /// Entries matching these patterns are always stored uncompressed.
+    #[cfg(feature = "patterns")]
+    pub store_patterns: Option<pattern::NamePatterns>,
+    /// Add a [`provenance::PROVENANCE_NAME`] entry describing the build. A
+    /// file of that name in the input is replaced.
+    #[cfg(feature = "json")]
+    pub provenance: bool,
+    /// Record empty directories as zero-byte entries named with a trailing
+    /// `\`, which unpacking turns back into directories.
+    pub keep_empty_dirs: bool,
+    /// Directory for the scratch file the archive is written to before being
+    /// moved into place. Defaults to the output's directory.
+    pub temp_dir: Option<PathBuf>,
+    /// Store files with identical content once, pointing all their entries
+    /// at the same data block.
+    pub dedup: bool,
+    /// Write a header-only archive when the input contains no files instead
+    /// of failing with [`ArcError::EmptyInput`].
+    pub allow_empty: bool,
+    /// Thread pool reading and compression run on.
+    pub parallelism: Parallelism,
+    /// Counters updated while packing, for callers to sample.
+    pub metrics: Option<Arc<Metrics>>,
+    /// `(from, to)` character replacements applied to names before encoding.
+    /// `None` uses [`name::CP932_FALLBACKS`].
+    pub name_fallbacks: Option<Vec<(char, char)>>,
+    /// Fail with [`ArcError::StrictWarning`] instead of logging warnings,
+    /// e.g. when a name has to be mapped or compression fails.
+    pub strict: bool,
+    /// Only pack the files this accepts. Empty directory placeholders are
+    /// not filtered.
+    pub filter: Option<selection::PackFilter>,
+    /// Checked between entries. A cancelled pack leaves no output file.
+    pub cancel: Option<cancel::CancelToken>,
+    /// Encrypts new entry names. `None` uses [`name::SilkyCodec`]. Entries
+    /// copied from an existing archive keep their stored name bytes.
+    pub name_codec: Option<Arc<dyn name::NameCodec>>,
+    /// Bytes of source data and compression output held at once while
+    /// processing files. Processed data then waits in a temporary file (in
+    /// `temp_dir`) until the archive is written. Blocks copied from an
+    /// existing archive by edits are not counted.
+    pub memory_limit: Option<u64>,
+    /// Separator in the names of files packed from a directory.
+    pub separator: name::SeparatorStyle,
+    /// Order of the files packed from a directory.
+    pub order: EntryOrder,
+}
+
+impl PackOptions {
+    /// Whether an input file named `name` gives way to a fresh provenance
+    /// entry.
+    fn replaces_with_provenance(&self, name: &str) -> bool {
+        #[cfg(feature = "json")]
+        return self.provenance && name == provenance::PROVENANCE_NAME;
+        #[cfg(not(feature = "json"))]
+        {
+            let _ = name;
+            false
+        }
+    }
+
+    /// Whether `store_patterns` keep the entry `name` uncompressed.
+    fn forced_store(&self, name: &str) -> bool {
+        #[cfg(feature = "patterns")]
+        if let Some(patterns) = &self.store_patterns {
+            return patterns.is_match(name);
+        }
+        let _ = name;
+        false
+    }
+
+    /// `name` validated, with the fallbacks applied and encrypted with the
+    /// codec.
+    pub(crate) fn encode_name(&self, name: &str) -> Result<(String, Vec<u8>), ArcError> {
+        let fallbacks = self
+            .name_fallbacks
+            .as_deref()
+            .unwrap_or(name::CP932_FALLBACKS);
+        name::encode_entry_name_as(name, fallbacks, self.codec())
+    }
+
+    /// Like [`Self::encode_name`], with the separator switched to
+    /// `separator` after validation.
+    fn encode_file_name(&self, name: &str) -> Result<(String, Vec<u8>), ArcError> {
+        let (name, encrypted_name) = self.encode_name(name)?;
+        match self.separator {
+            name::SeparatorStyle::Backslash => Ok((name, encrypted_name)),
+            style => {
+                let name = style.apply(&name).into_owned();
+                let encrypted_name = name::encrypt_with(&name, self.codec())?;
+                Ok((name, encrypted_name))
+            }
+        }
+    }
+
+    pub(crate) fn codec(&self) -> &dyn name::NameCodec {
+        match &self.name_codec {
+            Some(codec) => codec.as_ref(),
+            None => &name::SilkyCodec {
+                encoding: name::NameEncoding::Cp932,
+            },
+        }
+    }
+}
+
+pub fn handle_pack(
+    input_dir: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    compress: bool,
+) -> Result<PackSummary, ArcError> {
+    handle_pack_with(
+        input_dir,
+        output_path,
+        &PackOptions {
+            compress,
+            allow_empty: true,
+            ..Default::default()
+        },
+    )
+}
+
+pub fn handle_pack_with(
+    input_dir: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    let input_dir = input_dir.as_ref();
+    let output_path = output_path.as_ref();
+    options
+        .parallelism
+        .install(|| pack_impl(input_dir, output_path, options))
+}
+
+/// Like [`handle_pack_with`], writing the archive to `writer` instead of a
+/// file, e.g. a `Cursor` to build it in memory. Nothing is spooled, so
+/// `writer` receives a partial archive if packing fails.
+pub fn handle_pack_to_writer<W: Write + Seek>(
+    input_dir: impl AsRef<Path>,
+    writer: W,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    let input_dir = input_dir.as_ref();
+    let metrics = options.metrics.clone().unwrap_or_default();
+    let files_to_pack = options
+        .parallelism
+        .install(|| prepare_pack_files(input_dir, options, &metrics))?;
+    write_pack_files_to(files_to_pack, writer, options, &metrics)
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(options), fields(compress = options.compress)))]
+fn pack_impl(
+    input_dir: &Path,
+    output_path: &Path,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    info!("Output archive: {:?}", output_path);
+    let metrics = options.metrics.clone().unwrap_or_default();
+    let files_to_pack = prepare_pack_files(input_dir, options, &metrics)?;
+    write_pack_files(files_to_pack, output_path, options, &metrics)
+}
+
+/// Collects and processes the files of `input_dir`, adding the provenance
+/// entry if requested.
+fn prepare_pack_files(
+    input_dir: &Path,
+    options: &PackOptions,
+    metrics: &Metrics,
+) -> Result<Vec<PackFileInfo>, ArcError> {
+    info!("Starting pack of directory: {:?}", input_dir);
+    info!("Compression enabled: {}", options.compress);
+
+    if !input_dir.is_dir() {
+        return Err(ArcError::NotFound(input_dir.to_path_buf()));
+    }
+
+    // 1. Collect all files recursively and prepare initial metadata
+    let mut files_to_pack = collect_pack_files(input_dir, options)?;
+
+    if files_to_pack.is_empty() && !options.allow_empty {
+        return Err(ArcError::EmptyInput(input_dir.to_path_buf()));
+    }
+
+    if files_to_pack.is_empty() {
+        // The archive will contain the global header only
+        info!("Input directory is empty. Creating an empty archive.");
+    }
+
+    // 2. Read file data and compress in parallel (if enabled)
+    process_pack_files(&mut files_to_pack, options, metrics)?;
+    add_provenance(&mut files_to_pack, options)?;
+    Ok(files_to_pack)
+}
+
+/// Lays out the processed `files_to_pack` and writes them as an archive to
+/// `output_path`, through a spool file.
+fn write_pack_files(
+    files_to_pack: Vec<PackFileInfo>,
+    output_path: &Path,
+    options: &PackOptions,
+    metrics: &Metrics,
+) -> Result<PackSummary, ArcError> {
+    let spool = spool::create_spool_file(output_path, options.temp_dir.as_deref())?;
+    let summary = write_pack_files_to(files_to_pack, spool.as_file(), options, metrics)?;
+    spool::persist_spool_file(spool, output_path)?;
+    info!("=== Pack finished ===");
+    Ok(summary)
+}
+
+/// Lays out the processed `files_to_pack` and writes them as an archive to
+/// `writer`, which must be at the start of the archive.
+fn write_pack_files_to<W: Write + Seek>(
+    mut files_to_pack: Vec<PackFileInfo>,
+    writer: W,
+    options: &PackOptions,
+    metrics: &Metrics,
+) -> Result<PackSummary, ArcError> {
+    // 3. Calculate metadata size and file offsets (Sequentially)
+    let shared = shared_blocks(&files_to_pack, options.dedup)?;
+    let metadata_block_size = assign_offsets(&mut files_to_pack, &shared)?;
+    let shared_count = shared.iter().flatten().count();
+    if shared_count > 0 {
+        info!("{shared_count} entries share the data of an identical file.");
+    }
+
+    let summary = PackSummary {
+        metadata_size: metadata_block_size,
+        data_size: files_to_pack
+            .iter()
+            .zip(&shared)
+            .filter(|(_, shared)| shared.is_none())
+            .map(|(f, _)| f.compressed_size as u64)
+            .sum(),
+        entries: files_to_pack.iter().map(PackFileInfo::to_entry).collect(),
+    };
+
+    // 4. Write the archive file (Sequentially)
+    trace_span!(
+        "write",
+        entries = summary.entry_count(),
+        size = summary.archive_size()
+    );
+    let mut writer = BufWriter::new(writer);
+
+    // Write global header and metadata entries
+    let mut metadata = Vec::with_capacity((HEADER_SIZE + metadata_block_size) as usize);
+    format::write_header(&mut metadata, metadata_block_size);
+    for file_info in &files_to_pack {
+        format::write_entry(&mut metadata, &file_info.to_record())?;
+    }
+    writer.write_all(&metadata)?;
+    info!("Metadata written.");
+
+    // Write file data blocks
+    for (file_info, shared) in files_to_pack.into_iter().zip(shared) {
+        cancel::check(options.cancel.as_ref())?;
+        if shared.is_some() {
+            // Written with the file it shares the block with
+            continue;
+        }
+        if let Some(data) = file_info.payload()? {
+            // Sanity check seek position (optional but good)
+            let current_pos = writer.stream_position()?;
+            if current_pos != file_info.offset as u64 {
+                error!(
+                    "Mismatch writing file data for {:?}. Expected offset {}, current position {}",
+                    file_info.relative_path, file_info.offset, current_pos
+                );
+                // Attempt to seek to the correct position
+                writer.seek(SeekFrom::Start(file_info.offset as u64))?;
+            }
+            metrics.time_io(|| writer.write_all(&data))?;
+            metrics.add_written(data.len());
+            info!("Wrote data for: {:?}", file_info.relative_path);
+        } else {
+            // This shouldn't happen if processing was successful
+            return Err(ArcError::InvalidFormat(format!(
+                "Missing processed data for {:?}",
+                file_info.relative_path
+            )));
+        }
+    }
+    info!("File data written.");
+
+    writer.flush()?; // Ensure all buffered data is written to the file
+    Ok(summary)
+}
+
+/// Runs the pack pipeline without writing anything and returns the entries
+/// the archive would contain.
+pub fn pack_dry_run(
+    input_dir: impl AsRef<Path>,
+    compress: bool,
+) -> Result<Vec<ArcEntry>, ArcError> {
+    let input_dir = input_dir.as_ref();
+    if !input_dir.is_dir() {
+        return Err(ArcError::NotFound(input_dir.to_path_buf()));
+    }
+
+    let options = PackOptions {
+        compress,
+        ..Default::default()
+    };
+    let mut files_to_pack = collect_pack_files(input_dir, &options)?;
+    process_pack_files(&mut files_to_pack, &options, &Metrics::default())?;
+    let shared = shared_blocks(&files_to_pack, options.dedup)?;
+    assign_offsets(&mut files_to_pack, &shared)?;
+    Ok(files_to_pack.iter().map(PackFileInfo::to_entry).collect())
+}
+
+/// Reopens a freshly packed archive and checks that every entry decompresses
+/// to exactly the bytes of its source file in `input_dir`.
+pub fn verify_pack(
+    input_dir: impl AsRef<Path>,
+    archive_path: impl AsRef<Path>,
+) -> Result<(), ArcError> {
+    verify_pack_with(input_dir, archive_path, &PackOptions::default())
+}
+
+/// Like [`verify_pack`], matching files to entries the way packing with
+/// `options` names them.
+pub fn verify_pack_with(
+    input_dir: impl AsRef<Path>,
+    archive_path: impl AsRef<Path>,
+    options: &PackOptions,
+) -> Result<(), ArcError> {
+    let input_dir = input_dir.as_ref();
+    let archive_path = archive_path.as_ref();
+    info!("Verifying {:?} against {:?}", archive_path, input_dir);
+
+    let archive = ArcReader::open(archive_path)?;
+    let files = collect_pack_files(input_dir, options)?;
+    // The provenance entry has no source file
+    let entries: Vec<&ArcEntry> = archive
+        .entries()
+        .iter()
+        .filter(|entry| !options.replaces_with_provenance(entry.name()))
+        .collect();
+    if files.len() != entries.len() {
+        return Err(ArcError::VerificationFailed(
+            archive_path.to_string_lossy().into_owned(),
+            format!(
+                "archive has {} entries but the directory has {} files",
+                entries.len(),
+                files.len()
+            ),
+        ));
+    }
+
+    let entries_by_name: HashMap<&[u8], &ArcEntry> = entries
+        .into_iter()
+        .map(|entry| (entry.encrypted_name.as_slice(), entry))
+        .collect();
+
+    files
+        .par_iter()
+        .map(|file_info| -> Result<(), ArcError> {
+            let entry = entries_by_name
+                .get(file_info.encrypted_name.as_slice())
+                .ok_or_else(|| {
+                    ArcError::VerificationFailed(
+                        file_info.relative_path.to_string_lossy().into_owned(),
+                        "missing from the archive".to_string(),
+                    )
+                })?;
+            let mut task_reader = BufReader::new(File::open(archive_path).with_path(archive_path)?);
+            let data = reader::read_entry_data(&mut task_reader, entry)?;
+            if data != read_pack_source(file_info, &Metrics::default())? {
+                return Err(ArcError::VerificationFailed(
+                    entry.name().to_string(),
+                    "content differs from the source file".to_string(),
+                ));
+            }
+            Ok(())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!("Verified {} entries.", files.len());
+    Ok(())
+}
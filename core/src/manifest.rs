@@ -0,0 +1,147 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+use serde_json::Value;
+
+use crate::{
+    PackOptions, PackSummary, add_provenance,
+    edit::source_file,
+    error::{ArcError, PathContext},
+    name, process_pack_files,
+    selection::normalize_entry_name,
+    write_pack_files,
+};
+
+// --- Pack Manifests ---
+// A manifest is a JSON array of objects, packed in order:
+//
+//   [
+//     { "source": "text/main.txt", "name": "script/main.txt", "compress": true
+// },     { "source": "gfx/title.png" }
+//   ]
+//
+// `source` is relative to the manifest's directory. `name` defaults to the
+// source path and may use `/` as separator. `compress` overrides
+// `PackOptions::compress` for the file.
+
+/// One file listed in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub source: PathBuf,
+    /// Entry name, using `\` as separator.
+    pub name: String,
+    /// Overrides `PackOptions::compress` for this file. Store patterns and
+    /// the minimum compression size still apply.
+    pub compress: Option<bool>,
+}
+
+/// Reads the manifest at `path`, resolving sources against its directory.
+pub fn read_manifest(path: impl AsRef<Path>) -> Result<Vec<ManifestEntry>, ArcError> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).with_path(path)?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    parse_manifest(&content, base)
+        .map_err(|e| ArcError::InvalidFormat(format!("{}: {e}", path.display())))
+}
+
+/// Parses manifest JSON, resolving relative sources against `base`.
+pub fn parse_manifest(content: &str, base: &Path) -> Result<Vec<ManifestEntry>, String> {
+    let document: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let items = document
+        .as_array()
+        .ok_or("the manifest must be an array of entries")?;
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let field = |key: &str| item.get(key).filter(|value| !value.is_null());
+            let source = field("source")
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("entry {index}: \"source\" must be a string"))?;
+            let name = match field("name") {
+                Some(value) => value
+                    .as_str()
+                    .map(normalize_entry_name)
+                    .ok_or_else(|| format!("entry {index}: \"name\" must be a string"))?,
+                None => name::path_to_entry_name(Path::new(source))
+                    .map_err(|e| format!("entry {index}: {e}"))?,
+            };
+            let compress = match field("compress") {
+                Some(value) => Some(
+                    value
+                        .as_bool()
+                        .ok_or_else(|| format!("entry {index}: \"compress\" must be a boolean"))?,
+                ),
+                None => None,
+            };
+            Ok(ManifestEntry {
+                source: base.join(source),
+                name,
+                compress,
+            })
+        })
+        .collect()
+}
+
+/// Packs the files listed in the manifest at `manifest_path` into an archive
+/// at `output_path`, in manifest order.
+pub fn pack_manifest(
+    manifest_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    let manifest_path = manifest_path.as_ref();
+    let entries = read_manifest(manifest_path)?;
+    if entries.is_empty() && !options.allow_empty {
+        return Err(ArcError::EmptyInput(manifest_path.to_path_buf()));
+    }
+    pack_manifest_entries(&entries, output_path, options)
+}
+
+/// Packs `entries` into an archive at `output_path`, in order.
+pub fn pack_manifest_entries(
+    entries: &[ManifestEntry],
+    output_path: impl AsRef<Path>,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    let output_path = output_path.as_ref();
+    options.parallelism.install(|| {
+        let mut names = HashSet::new();
+        let mut compressed = Vec::new();
+        let mut stored = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let (entry_name, encrypted_name) = options.encode_name(&entry.name)?;
+            if !names.insert(entry_name.clone()) {
+                return Err(ArcError::DuplicateEntry(entry_name));
+            }
+            let file = source_file(entry_name, encrypted_name, &entry.source)?;
+            if entry.compress.unwrap_or(options.compress) {
+                compressed.push((index, file));
+            } else {
+                stored.push((index, file));
+            }
+        }
+
+        // Files are processed in two groups, then put back in manifest order
+        let metrics = options.metrics.clone().unwrap_or_default();
+        let mut groups = Vec::new();
+        for (compress, group) in [(true, compressed), (false, stored)] {
+            let (indices, mut files): (Vec<_>, Vec<_>) = group.into_iter().unzip();
+            let group_options = PackOptions {
+                compress,
+                ..options.clone()
+            };
+            process_pack_files(&mut files, &group_options, &metrics)?;
+            groups.extend(indices.into_iter().zip(files));
+        }
+        groups.sort_by_key(|(index, _)| *index);
+        let mut files: Vec<_> = groups.into_iter().map(|(_, file)| file).collect();
+        info!("Packing {} entries from the manifest.", files.len());
+        add_provenance(&mut files, options)?;
+        write_pack_files(files, output_path, options, &metrics)
+    })
+}
@@ -0,0 +1,118 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{Condvar, Mutex},
+};
+
+use crate::{error::ArcError, lock};
+
+// --- Memory Budget ---
+// `memory_limit` bounds the entry buffers held at once. Workers reserve the
+// bytes an entry needs before reading it and wait while other entries hold
+// the budget, which throttles the parallel iterators. An entry larger than
+// the whole budget runs alone. Packing keeps every processed payload until
+// the archive is written, so with a limit payloads are moved to a spill
+// file right after processing instead.
+
+/// Upper bound of LZSS output: one flag bit per literal byte.
+pub(crate) fn compressed_bound(size: u64) -> u64 {
+    size + size.div_ceil(8)
+}
+
+/// Bytes that may be reserved at once.
+#[derive(Debug)]
+pub(crate) struct MemoryBudget {
+    limit: u64,
+    used: Mutex<u64>,
+    released: Condvar,
+}
+
+impl MemoryBudget {
+    pub(crate) fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Waits until `bytes` fit into the budget and reserves them until the
+    /// returned guard is dropped.
+    pub(crate) fn reserve(&self, bytes: u64) -> Reservation<'_> {
+        let bytes = bytes.min(self.limit);
+        let mut used = lock(&self.used);
+        while *used > 0 && *used + bytes > self.limit {
+            used = self
+                .released
+                .wait(used)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        *used += bytes;
+        Reservation {
+            budget: self,
+            bytes,
+        }
+    }
+}
+
+/// Bytes reserved from a [`MemoryBudget`].
+#[derive(Debug)]
+pub(crate) struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        *lock(&self.budget.used) -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+/// Reserves `bytes` from `budget`, if there is one.
+pub(crate) fn reserve(budget: Option<&MemoryBudget>, bytes: u64) -> Option<Reservation<'_>> {
+    budget.map(|budget| budget.reserve(bytes))
+}
+
+/// Anonymous temporary file holding processed payloads until they are
+/// written.
+#[derive(Debug)]
+pub(crate) struct SpillFile {
+    // File and its current length
+    file: Mutex<(File, u64)>,
+}
+
+impl SpillFile {
+    /// A spill file in `temp_dir`, or the system's temporary directory.
+    pub(crate) fn new(temp_dir: Option<&Path>) -> Result<Self, ArcError> {
+        let file = match temp_dir {
+            Some(dir) => tempfile::tempfile_in(dir)?,
+            None => tempfile::tempfile()?,
+        };
+        Ok(Self {
+            file: Mutex::new((file, 0)),
+        })
+    }
+
+    /// Appends `data` and returns its offset.
+    pub(crate) fn store(&self, data: &[u8]) -> Result<u64, ArcError> {
+        let mut guard = lock(&self.file);
+        let (file, len) = &mut *guard;
+        let offset = *len;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(data)?;
+        *len += data.len() as u64;
+        Ok(offset)
+    }
+
+    /// The `size` bytes stored at `offset`.
+    pub(crate) fn load(&self, offset: u64, size: u32) -> Result<Vec<u8>, ArcError> {
+        let mut guard = lock(&self.file);
+        let (file, _) = &mut *guard;
+        let mut data = vec![0; size as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
@@ -0,0 +1,101 @@
+use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
+
+use log::{info, warn};
+
+use crate::{
+    PackOptions, PackSummary,
+    edit::stored_file,
+    error::{ArcError, PathContext},
+    reader::{self, ArcReader},
+    write_pack_files,
+};
+
+// --- Archive Merging ---
+
+/// What [`merge_archives`] does when several inputs contain an entry with
+/// the same name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Fail with [`ArcError::DuplicateEntry`].
+    #[default]
+    Error,
+    /// Keep the entry from the first archive that has it.
+    KeepFirst,
+    /// Use the data from the last archive that has it, at the position of
+    /// the first.
+    KeepLast,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "error" => Ok(ConflictPolicy::Error),
+            "keep-first" | "first" => Ok(ConflictPolicy::KeepFirst),
+            "keep-last" | "last" => Ok(ConflictPolicy::KeepLast),
+            _ => Err(format!(
+                "unknown conflict policy {s:?}, expected one of error, keep-first, keep-last"
+            )),
+        }
+    }
+}
+
+/// Concatenates the entries of `archives`, in order, into a new archive at
+/// `output_path`. Data blocks are copied as stored; entries sharing a block
+/// get a copy each.
+pub fn merge_archives<P: AsRef<Path>>(
+    archives: &[P],
+    output_path: impl AsRef<Path>,
+    on_conflict: ConflictPolicy,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    let output_path = output_path.as_ref();
+    let mut files = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut first_encoding = None;
+    for archive_path in archives {
+        let archive_path = archive_path.as_ref();
+        let archive = ArcReader::open(archive_path)?;
+        let encoding = *first_encoding.get_or_insert(archive.name_encoding());
+        if archive.name_encoding() != encoding {
+            warn!(
+                "{:?} stores names in {}, the first archive in {}; names are copied as stored",
+                archive_path,
+                archive.name_encoding(),
+                encoding
+            );
+        }
+        let mut task_reader = BufReader::new(File::open(archive_path).with_path(archive_path)?);
+        for entry in archive.entries() {
+            let position = positions.get(entry.name()).copied();
+            if position.is_some() && on_conflict == ConflictPolicy::KeepFirst {
+                info!(
+                    "Skipping {:?} from {:?}, already merged",
+                    entry.name(),
+                    archive_path
+                );
+                continue;
+            }
+            if position.is_some() && on_conflict == ConflictPolicy::Error {
+                return Err(ArcError::DuplicateEntry(entry.name().to_string()));
+            }
+            let data = reader::read_raw_entry_data(&mut task_reader, entry)?;
+            let file = stored_file(entry, data);
+            match position {
+                Some(position) => files[position] = file,
+                None => {
+                    positions.insert(entry.name().to_string(), files.len());
+                    files.push(file);
+                }
+            }
+        }
+    }
+    info!(
+        "Merged {} archives into {} entries.",
+        archives.len(),
+        files.len()
+    );
+    let metrics = options.metrics.clone().unwrap_or_default();
+    write_pack_files(files, output_path, options, &metrics)
+}
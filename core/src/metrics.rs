@@ -0,0 +1,95 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+// --- Runtime Metrics ---
+// Counters are updated from the worker threads as an operation progresses, so
+// a caller holding the same `Arc<Metrics>` can sample them at any time, e.g.
+// to drive a progress display.
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    entries_processed: AtomicU64,
+    bytes_done: AtomicU64,
+    bytes_total: AtomicU64,
+    compression_nanos: AtomicU64,
+    io_nanos: AtomicU64,
+}
+
+/// Point-in-time copy of [`Metrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub entries_processed: u64,
+    /// Original (uncompressed) bytes of entry data handled so far. Large
+    /// entries advance this in chunks while they are being (de)compressed.
+    pub bytes_done: u64,
+    /// Original bytes of all entries the operation will handle, known once
+    /// the input has been scanned.
+    pub bytes_total: u64,
+    /// Time spent in LZSS compression and decompression, summed over all
+    /// threads.
+    pub compression_time: Duration,
+    /// Time spent reading and writing files, summed over all threads.
+    pub io_time: Duration,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            entries_processed: self.entries_processed.load(Ordering::Relaxed),
+            bytes_done: self.bytes_done.load(Ordering::Relaxed),
+            bytes_total: self.bytes_total.load(Ordering::Relaxed),
+            compression_time: Duration::from_nanos(self.compression_nanos.load(Ordering::Relaxed)),
+            io_time: Duration::from_nanos(self.io_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub(crate) fn add_read(&self, bytes: usize) {
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_written(&self, bytes: usize) {
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_done(&self, bytes: u64) {
+        self.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_total(&self, bytes: u64) {
+        self.bytes_total.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn entry_done(&self) {
+        self.entries_processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Runs `op`, accounting its duration as IO time.
+    pub(crate) fn time_io<R>(&self, op: impl FnOnce() -> R) -> R {
+        Self::timed(&self.io_nanos, op)
+    }
+
+    /// Runs `op`, accounting its duration as compression time.
+    pub(crate) fn time_compression<R>(&self, op: impl FnOnce() -> R) -> R {
+        Self::timed(&self.compression_nanos, op)
+    }
+
+    fn timed<R>(counter: &AtomicU64, op: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = op();
+        counter.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        result
+    }
+}
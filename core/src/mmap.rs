@@ -0,0 +1,74 @@
+use std::{borrow::Cow, fs::File, io::Cursor, path::Path};
+
+use memmap2::Mmap;
+
+use crate::{
+    decompress_data,
+    error::{ArcError, PathContext},
+    reader::{ArcEntry, ArcReader},
+};
+
+// --- Memory-Mapped Archives ---
+// For read-heavy consumers: stored entries are borrowed straight from the
+// mapping, and only compressed entries get a buffer of their own.
+
+/// An archive mapped into memory.
+#[derive(Debug)]
+pub struct MappedArchive {
+    archive: ArcReader<Cursor<Mmap>>,
+}
+
+impl MappedArchive {
+    /// Maps the archive at `path` and parses its metadata.
+    ///
+    /// The file must not be modified while it is mapped, or the data seen
+    /// through the mapping changes under the borrowed slices.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ArcError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(ArcError::NotFound(path.to_path_buf()));
+        }
+        let file = File::open(path).with_path(path)?;
+        // SAFETY: see the requirement documented above
+        let map = unsafe { Mmap::map(&file) }.with_path(path)?;
+        Ok(Self {
+            archive: ArcReader::new(Cursor::new(map))?,
+        })
+    }
+
+    /// Entries in metadata order.
+    pub fn entries(&self) -> &[ArcEntry] {
+        self.archive.entries()
+    }
+
+    /// The entry named `name`, see [`ArcReader::get`].
+    pub fn get(&self, name: &str) -> Option<&ArcEntry> {
+        self.archive.get(name)
+    }
+
+    /// The entry's data block as stored in the archive, without copying.
+    pub fn stored_bytes(&self, entry: &ArcEntry) -> Result<&[u8], ArcError> {
+        let map = self.archive.get_ref().get_ref();
+        let start = entry.offset() as usize;
+        let end = start + entry.compressed_size() as usize;
+        map.get(start..end).ok_or_else(|| {
+            ArcError::InvalidFormat(format!(
+                "data of {:?} is truncated: {} of {} bytes present",
+                entry.name(),
+                map.len().saturating_sub(start),
+                entry.compressed_size()
+            ))
+        })
+    }
+
+    /// The entry's original data: borrowed from the mapping for stored
+    /// entries, decompressed into a new buffer otherwise.
+    pub fn entry_bytes(&self, entry: &ArcEntry) -> Result<Cow<'_, [u8]>, ArcError> {
+        let stored = self.stored_bytes(entry)?;
+        if entry.compressed_size() == entry.original_size() {
+            Ok(Cow::Borrowed(stored))
+        } else {
+            decompress_data(stored, entry.original_size()).map(Cow::Owned)
+        }
+    }
+}
@@ -0,0 +1,463 @@
+use std::{
+    borrow::Cow,
+    path::{Component, Path, PathBuf},
+};
+
+use encoding_rs::{BIG5, Encoding, GBK, SHIFT_JIS, UTF_8}; // SHIFT_JIS is CP932
+use log::info;
+
+use crate::error::{ArcError, NameError};
+
+/// Path separator used inside entry names.
+pub const SEPARATOR: char = '\\';
+/// Maximum length of an encrypted entry name, limited by its u8 length field.
+pub const MAX_NAME_LEN: usize = u8::MAX as usize;
+/// Characters Windows (and therefore the engine) does not accept in names.
+pub const FORBIDDEN_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+// --- Name Encryption/Decryption ---
+// (Ported from Python's decrypt_name)
+pub fn decrypt_name(encrypted: &[u8]) -> Result<String, ArcError> {
+    SilkyCodec::default().decrypt(encrypted)
+}
+
+/// Decrypts a name stored with another encoding than CP932, as found in
+/// fan-modified archives.
+pub fn decrypt_name_as(encrypted: &[u8], encoding: NameEncoding) -> Result<String, ArcError> {
+    SilkyCodec { encoding }.decrypt(encrypted)
+}
+
+pub(crate) fn unshift_name(encrypted: &[u8]) -> Vec<u8> {
+    let mut tester = Vec::with_capacity(encrypted.len());
+    for (k, &byte) in encrypted.iter().rev().enumerate() {
+        // k starts at 0, Python's k started at 1
+        tester.push(byte.wrapping_add((k + 1) as u8));
+    }
+    tester.reverse(); // Because we pushed in reverse order
+    tester
+}
+
+// (Ported from Python's encrypt_name)
+pub fn encrypt_name(name: &str) -> Result<Vec<u8>, ArcError> {
+    SilkyCodec::default().encrypt(name)
+}
+
+/// Inverse of `unshift_name`: encrypts already encoded name bytes.
+pub(crate) fn shift_name(encoded: &[u8]) -> Vec<u8> {
+    let mut tester = Vec::with_capacity(encoded.len());
+    for (k, &byte) in encoded.iter().rev().enumerate() {
+        // k starts at 0, Python's k started at 1
+        tester.push(byte.wrapping_sub((k + 1) as u8));
+    }
+    tester.reverse(); // Because we pushed in reverse order
+    tester
+}
+
+// --- Name Codecs ---
+// The functions above implement the Silky scheme. Related engines hide
+// names in other ways; implementing `NameCodec` for their scheme and passing
+// it in the pack/unpack options (or to `ArcReader::with_codec`) is enough
+// to support them. Entry names still go through the usual validation, with
+// the codec deciding which names it can encode.
+
+/// Converts entry names to the bytes stored in the metadata and back.
+pub trait NameCodec: std::fmt::Debug + Send + Sync {
+    /// Fails with [`ArcError::NameEncodeError`] if the scheme cannot
+    /// represent `name`.
+    fn encrypt(&self, name: &str) -> Result<Vec<u8>, ArcError>;
+    fn decrypt(&self, encrypted: &[u8]) -> Result<String, ArcError>;
+}
+
+/// The Silky scheme: the name in `encoding`, with every byte shifted down
+/// by its distance from the end of the name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SilkyCodec {
+    pub encoding: NameEncoding,
+}
+
+impl NameCodec for SilkyCodec {
+    fn encrypt(&self, name: &str) -> Result<Vec<u8>, ArcError> {
+        let (encoded_bytes, _encoding_used, had_errors) = self.encoding.encoding().encode(name);
+        if had_errors {
+            return Err(ArcError::NameEncodeError(name.to_string()));
+        }
+        Ok(shift_name(&encoded_bytes))
+    }
+
+    fn decrypt(&self, encrypted: &[u8]) -> Result<String, ArcError> {
+        let tester = unshift_name(encrypted);
+        let (cow, _encoding_used, had_errors) = self.encoding.encoding().decode(&tester);
+        if had_errors {
+            Err(ArcError::NameDecodeError(encrypted.to_vec()))
+        } else {
+            Ok(cow.into_owned())
+        }
+    }
+}
+
+// --- Encoding Detection ---
+// The engine itself only knows CP932, but translated archives in circulation
+// often store names in the translator's local code page. Candidates are tried
+// in order and the first one that decodes every name cleanly wins; if none
+// does, the candidate with the fewest suspicious characters is used.
+
+/// Encodings entry names can be decoded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum NameEncoding {
+    #[default]
+    Cp932,
+    Gbk,
+    Big5,
+    Utf8,
+}
+
+/// Candidates tried when decoding names, in order of preference.
+pub const DEFAULT_NAME_ENCODINGS: &[NameEncoding] = &[
+    NameEncoding::Cp932,
+    NameEncoding::Gbk,
+    NameEncoding::Big5,
+    NameEncoding::Utf8,
+];
+
+impl NameEncoding {
+    pub(crate) fn encoding(self) -> &'static Encoding {
+        match self {
+            NameEncoding::Cp932 => SHIFT_JIS,
+            NameEncoding::Gbk => GBK,
+            NameEncoding::Big5 => BIG5,
+            NameEncoding::Utf8 => UTF_8,
+        }
+    }
+}
+
+impl std::str::FromStr for NameEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "cp932" | "shiftjis" | "sjis" => Ok(NameEncoding::Cp932),
+            "gbk" => Ok(NameEncoding::Gbk),
+            "big5" => Ok(NameEncoding::Big5),
+            "utf8" => Ok(NameEncoding::Utf8),
+            _ => Err(format!(
+                "unknown encoding {s:?}, expected one of cp932, gbk, big5, utf8"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for NameEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            NameEncoding::Cp932 => "CP932",
+            NameEncoding::Gbk => "GBK",
+            NameEncoding::Big5 => "Big5",
+            NameEncoding::Utf8 => "UTF-8",
+        })
+    }
+}
+
+fn is_half_width_katakana(c: char) -> bool {
+    ('\u{FF61}'..='\u{FF9F}').contains(&c)
+}
+
+// Counts characters that rarely occur in real file names but are typical for
+// text decoded with the wrong code page. Most GBK and Big5 lead bytes turn
+// into half-width katakana under CP932, mixed with kanji from the remaining
+// byte pairs; half-width katakana next to ASCII or each other is left alone,
+// since older games do use it in names.
+fn count_suspicious(name: &str) -> usize {
+    let chars: Vec<char> = name.chars().collect();
+    let is_wide = |i: usize| {
+        chars
+            .get(i)
+            .is_some_and(|&c| !c.is_ascii() && !is_half_width_katakana(c))
+    };
+    (0..chars.len())
+        .filter(|&i| {
+            let c = chars[i];
+            c.is_control()
+                || c == char::REPLACEMENT_CHARACTER
+                || ('\u{E000}'..='\u{F8FF}').contains(&c)
+                || (is_half_width_katakana(c) && (is_wide(i + 1) || i > 0 && is_wide(i - 1)))
+        })
+        .count()
+}
+
+/// Picks the encoding to decode `names` (as stored, i.e. encrypted) with and
+/// returns it together with the decoded names, or `None` if no candidate can
+/// decode all of them.
+pub fn detect_name_encoding(
+    names: &[&[u8]],
+    candidates: &[NameEncoding],
+) -> Option<(NameEncoding, Vec<String>)> {
+    let mut best: Option<(usize, NameEncoding, Vec<String>)> = None;
+    for &encoding in candidates {
+        let Ok(decoded) = names
+            .iter()
+            .map(|name| decrypt_name_as(name, encoding))
+            .collect::<Result<Vec<_>, _>>()
+        else {
+            continue;
+        };
+        let suspicious: usize = decoded.iter().map(|name| count_suspicious(name)).sum();
+        if suspicious == 0 {
+            best = Some((0, encoding, decoded));
+            break;
+        }
+        if best.as_ref().is_none_or(|(score, ..)| suspicious < *score) {
+            best = Some((suspicious, encoding, decoded));
+        }
+    }
+    let (_, encoding, decoded) = best?;
+    if encoding != candidates[0] {
+        info!(
+            "Entry names are not {}, decoded them as {}",
+            candidates[0], encoding
+        );
+    }
+    Some((encoding, decoded))
+}
+
+// --- CP932 Mapping ---
+// encoding_rs implements Shift_JIS as specified by WHATWG, which matches
+// Windows' CP932, the code page the engine looks names up with. Strict JIS X
+// 0208 maps a few characters to different code points, so names typed on
+// other systems may contain characters CP932 cannot encode (e.g. the wave
+// dash U+301C), or that come back as a different character after a round
+// trip (e.g. the minus sign U+2212). Such characters are replaced with the
+// CP932 character for the same bytes before encoding.
+
+/// Default `(from, to)` replacements applied to names before encoding.
+pub const CP932_FALLBACKS: &[(char, char)] = &[
+    ('\u{301C}', '\u{FF5E}'), // WAVE DASH -> FULLWIDTH TILDE
+    ('\u{2016}', '\u{2225}'), // DOUBLE VERTICAL LINE -> PARALLEL TO
+    ('\u{2212}', '\u{FF0D}'), // MINUS SIGN -> FULLWIDTH HYPHEN-MINUS
+    ('\u{2014}', '\u{2015}'), // EM DASH -> HORIZONTAL BAR
+    ('\u{00A2}', '\u{FFE0}'), // CENT SIGN -> FULLWIDTH CENT SIGN
+    ('\u{00A3}', '\u{FFE1}'), // POUND SIGN -> FULLWIDTH POUND SIGN
+    ('\u{00AC}', '\u{FFE2}'), // NOT SIGN -> FULLWIDTH NOT SIGN
+    ('\u{00A6}', '\u{FFE4}'), // BROKEN BAR -> FULLWIDTH BROKEN BAR
+];
+
+/// Applies `fallbacks` to every character of `name`. Borrows `name` when no
+/// character needs replacing.
+pub fn map_cp932_fallbacks<'a>(name: &'a str, fallbacks: &[(char, char)]) -> Cow<'a, str> {
+    let replacement = |c: char| {
+        fallbacks
+            .iter()
+            .find(|(from, _)| *from == c)
+            .map(|(_, to)| *to)
+    };
+    if name.chars().all(|c| replacement(c).is_none()) {
+        return Cow::Borrowed(name);
+    }
+    Cow::Owned(name.chars().map(|c| replacement(c).unwrap_or(c)).collect())
+}
+
+// --- Path Conversion ---
+// Entry names always use `\` as separator, regardless of the platform the
+// archive is packed or unpacked on.
+
+/// Converts a path relative to the packed directory into an entry name.
+pub fn path_to_entry_name(path: &Path) -> Result<String, ArcError> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => {
+                parts.push(part.to_str().ok_or_else(|| {
+                    ArcError::NameEncodeError(path.to_string_lossy().into_owned())
+                })?)
+            }
+            Component::CurDir => {}
+            _ => {
+                return Err(ArcError::NameEncodeError(
+                    path.to_string_lossy().into_owned(),
+                ));
+            }
+        }
+    }
+    Ok(parts.join(&SEPARATOR.to_string()))
+}
+
+/// Converts an entry name into a relative platform path. Both `\` and `/`
+/// are treated as separators; empty, `.` and `..` components are dropped so
+/// the result never escapes the directory it is joined to.
+pub fn entry_name_to_path(name: &str) -> PathBuf {
+    name.split([SEPARATOR, '/'])
+        .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+        .collect()
+}
+
+/// Makes an entry name from an untrusted archive usable as a file name:
+/// control characters (including NUL) become `_`, and a name without any
+/// usable path component becomes `_unnamed_<index>`, `index` being the
+/// entry's position in the metadata. Borrows `name` if it needs no change.
+pub fn sanitize_entry_name(name: &str, index: usize) -> Cow<'_, str> {
+    let name = if name.chars().any(char::is_control) {
+        Cow::Owned(
+            name.chars()
+                .map(|c| if c.is_control() { '_' } else { c })
+                .collect(),
+        )
+    } else {
+        Cow::Borrowed(name)
+    };
+    if entry_name_to_path(&name).as_os_str().is_empty() {
+        return Cow::Owned(format!("_unnamed_{index}"));
+    }
+    name
+}
+
+/// Whether `name` is a placeholder for an empty directory, as written by
+/// packing with `PackOptions::keep_empty_dirs`: a zero-byte entry whose name
+/// ends with a separator.
+pub fn is_directory_entry(name: &str) -> bool {
+    name.ends_with([SEPARATOR, '/'])
+}
+
+/// Separator written into the names of packed files. The engine expects
+/// `\`; some tools for related engines write `/`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SeparatorStyle {
+    /// `\`, as the engine writes it.
+    #[default]
+    Backslash,
+    /// `/`.
+    Slash,
+}
+
+impl SeparatorStyle {
+    pub fn separator(self) -> char {
+        match self {
+            SeparatorStyle::Backslash => SEPARATOR,
+            SeparatorStyle::Slash => '/',
+        }
+    }
+
+    /// `name`, which uses [`SEPARATOR`], with this style's separator.
+    pub fn apply(self, name: &str) -> Cow<'_, str> {
+        match self {
+            SeparatorStyle::Backslash => Cow::Borrowed(name),
+            SeparatorStyle::Slash => Cow::Owned(name.replace(SEPARATOR, "/")),
+        }
+    }
+}
+
+impl std::str::FromStr for SeparatorStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "backslash" | "\\" => Ok(SeparatorStyle::Backslash),
+            "slash" | "/" => Ok(SeparatorStyle::Slash),
+            _ => Err(format!(
+                "unknown separator {s:?}, expected one of backslash, slash"
+            )),
+        }
+    }
+}
+
+// --- Validation ---
+
+/// Checks that `name` can be stored in an archive and found by the engine:
+/// it must be CP932 encodable, fit into the u8 length field, use `\` as the
+/// only separator with no empty, `.` or `..` components, and contain no
+/// control or Windows-forbidden characters.
+pub fn validate_entry_name(name: &str) -> Result<(), NameError> {
+    validate_entry_name_with(name, &SilkyCodec::default())
+}
+
+/// Like [`validate_entry_name`], checking encodability and length against
+/// `codec` instead of the default [`SilkyCodec`].
+pub fn validate_entry_name_with(name: &str, codec: &dyn NameCodec) -> Result<(), NameError> {
+    match encrypt_validated(name, codec) {
+        Ok(_) => Ok(()),
+        Err(ArcError::InvalidEntryName(_, e)) => Err(e),
+        Err(_) => Err(NameError::NotEncodable),
+    }
+}
+
+// The checks that do not depend on the codec.
+fn validate_structure(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| FORBIDDEN_CHARS.contains(c) || c.is_control())
+    {
+        return Err(NameError::ForbiddenCharacter(c));
+    }
+    if name.contains('/') {
+        return Err(NameError::ForwardSlash);
+    }
+    if name
+        .split(SEPARATOR)
+        .any(|part| part.is_empty() || part == "." || part == "..")
+    {
+        return Err(NameError::InvalidComponent);
+    }
+    Ok(())
+}
+
+/// Converts a relative path into a validated entry name and its encrypted
+/// form, applying [`CP932_FALLBACKS`].
+pub fn encode_path(path: &Path) -> Result<(String, Vec<u8>), ArcError> {
+    encode_path_with(path, CP932_FALLBACKS)
+}
+
+/// Like [`encode_path`], with custom fallback mappings.
+pub fn encode_path_with(
+    path: &Path,
+    fallbacks: &[(char, char)],
+) -> Result<(String, Vec<u8>), ArcError> {
+    encode_entry_name_with(&path_to_entry_name(path)?, fallbacks)
+}
+
+/// Like [`encode_path_with`], for a name already using `\` as separator.
+pub fn encode_entry_name_with(
+    name: &str,
+    fallbacks: &[(char, char)],
+) -> Result<(String, Vec<u8>), ArcError> {
+    encode_entry_name_as(name, fallbacks, &SilkyCodec::default())
+}
+
+/// Like [`encode_entry_name_with`], encrypting the name with `codec`.
+pub fn encode_entry_name_as(
+    name: &str,
+    fallbacks: &[(char, char)],
+    codec: &dyn NameCodec,
+) -> Result<(String, Vec<u8>), ArcError> {
+    let name = map_cp932_fallbacks(name, fallbacks).into_owned();
+    let encrypted = encrypt_validated(&name, codec)?;
+    Ok((name, encrypted))
+}
+
+/// `name` validated and encrypted with `codec`; every failure, including a
+/// name the codec cannot encode, is an [`ArcError::InvalidEntryName`].
+fn encrypt_validated(name: &str, codec: &dyn NameCodec) -> Result<Vec<u8>, ArcError> {
+    validate_structure(name).map_err(|e| ArcError::InvalidEntryName(name.to_string(), e))?;
+    encrypt_with(name, codec).map_err(|e| match e {
+        ArcError::NameEncodeError(_) => {
+            ArcError::InvalidEntryName(name.to_string(), NameError::NotEncodable)
+        }
+        e => e,
+    })
+}
+
+/// `name` encrypted with `codec`, failing if it does not fit the length
+/// field.
+pub(crate) fn encrypt_with(name: &str, codec: &dyn NameCodec) -> Result<Vec<u8>, ArcError> {
+    let encrypted = codec.encrypt(name)?;
+    if encrypted.len() > MAX_NAME_LEN {
+        return Err(ArcError::InvalidEntryName(
+            name.to_string(),
+            NameError::TooLong(encrypted.len()),
+        ));
+    }
+    Ok(encrypted)
+}
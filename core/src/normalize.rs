@@ -0,0 +1,141 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use log::info;
+
+use crate::{
+    HEADER_SIZE,
+    error::{ArcError, NameError, PathContext},
+    format::{EntryRecord, write_entry, write_header},
+    name::{self, shift_name, unshift_name},
+    reader::ArcReader,
+    spool::{create_spool_file, persist_spool_file},
+};
+
+// --- Name Normalization ---
+// Some tools write entry names with `/` separators, which the engine does
+// not resolve. Normalizing rewrites the metadata only: the data section is
+// copied byte for byte, with offsets moved by the change in metadata size.
+// Names are edited as encoded bytes, so the archive's name encoding is kept.
+// `/`, `.` and `\` never occur as the second byte of a double-byte
+// character in any supported encoding, so the edits cannot split one.
+
+/// Options for [`normalize_archive`].
+#[derive(Debug, Clone, Default)]
+pub struct NormalizeOptions {
+    /// Also drop leading `.\` components.
+    pub trim_current_dir: bool,
+}
+
+/// `encoded` with `/` turned into `\`, and leading `.\` dropped with
+/// `trim_current_dir`.
+fn normalize_name_bytes(encoded: &[u8], trim_current_dir: bool) -> Vec<u8> {
+    let mut normalized: Vec<u8> = encoded
+        .iter()
+        .map(|&b| if b == b'/' { b'\\' } else { b })
+        .collect();
+    if trim_current_dir {
+        let mut start = 0;
+        while normalized[start..].starts_with(b".\\") {
+            start += 2;
+        }
+        normalized.drain(..start);
+    }
+    normalized
+}
+
+/// Rewrites `archive_path` to `output_path` (which may be the same file)
+/// with normalized entry names, returning the `(old, new)` names of the
+/// renamed entries. Nothing is written if no name changes and the output
+/// is the archive itself.
+pub fn normalize_archive(
+    archive_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    options: &NormalizeOptions,
+) -> Result<Vec<(String, String)>, ArcError> {
+    let archive_path = archive_path.as_ref();
+    let output_path = output_path.as_ref();
+    let archive = ArcReader::open(archive_path)?;
+    let encoding = archive.name_encoding();
+
+    let mut renamed = Vec::new();
+    let mut names = HashSet::new();
+    let mut records = Vec::with_capacity(archive.entries().len());
+    for entry in archive.entries() {
+        let encoded = unshift_name(&entry.encrypted_name);
+        let normalized = normalize_name_bytes(&encoded, options.trim_current_dir);
+        if normalized.is_empty() {
+            return Err(ArcError::InvalidEntryName(
+                entry.name().to_string(),
+                NameError::Empty,
+            ));
+        }
+        let encrypted_name = shift_name(&normalized);
+        let new_name = name::decrypt_name_as(&encrypted_name, encoding)?;
+        if normalized != encoded {
+            renamed.push((entry.name().to_string(), new_name.clone()));
+        }
+        if !names.insert(new_name.clone()) {
+            return Err(ArcError::DuplicateEntry(new_name));
+        }
+        records.push(EntryRecord {
+            encrypted_name,
+            compressed_size: entry.compressed_size(),
+            original_size: entry.original_size(),
+            offset: entry.offset(),
+        });
+    }
+    let same_file = fs::canonicalize(output_path)
+        .is_ok_and(|output| fs::canonicalize(archive_path).is_ok_and(|input| input == output));
+    if renamed.is_empty() && same_file {
+        info!("All {} names are already normalized.", records.len());
+        return Ok(renamed);
+    }
+
+    // Move the offsets along with the start of the data section
+    let old_end = HEADER_SIZE as u64 + archive.metadata_size() as u64;
+    let metadata_size: usize = records.iter().map(EntryRecord::encoded_len).sum();
+    let new_end = HEADER_SIZE as u64 + metadata_size as u64;
+    for record in &mut records {
+        let offset = record.offset as u64;
+        if offset < old_end {
+            // Empty entries may point anywhere
+            if record.compressed_size == 0 {
+                continue;
+            }
+            return Err(ArcError::InvalidFormat(format!(
+                "entry data at {offset} lies inside the metadata (before {old_end})"
+            )));
+        }
+        record.offset = u32::try_from(offset - old_end + new_end).map_err(|_| {
+            ArcError::InvalidFormat("the archive exceeds the 4 GiB offset limit".to_string())
+        })?;
+    }
+    let mut metadata = Vec::with_capacity(new_end as usize);
+    write_header(&mut metadata, metadata_size as u32);
+    for record in &records {
+        write_entry(&mut metadata, record)?;
+    }
+
+    let spool = create_spool_file(output_path, None)?;
+    {
+        let mut writer = BufWriter::new(spool.as_file());
+        writer.write_all(&metadata)?;
+        let mut source = File::open(archive_path).with_path(archive_path)?;
+        source.seek(SeekFrom::Start(old_end))?;
+        io::copy(&mut source, &mut writer)?;
+        writer.flush()?;
+    }
+    persist_spool_file(spool, output_path)?;
+    info!(
+        "Renamed {} of {} entries in {:?}.",
+        renamed.len(),
+        records.len(),
+        output_path
+    );
+    Ok(renamed)
+}
@@ -0,0 +1,97 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use log::info;
+
+use crate::{
+    PackOptions, PackSummary,
+    edit::stored_file,
+    error::{ArcError, PathContext},
+    process_pack_files,
+    reader::{self, ArcReader},
+    write_pack_files,
+};
+
+// --- Archive Optimization ---
+// Edited or hand-built archives can have gaps between data blocks, blocks
+// out of metadata order or trailing bytes. Rewriting them lays the blocks
+// out back to back in metadata order.
+
+/// What [`optimize_archive`] did.
+#[derive(Debug, Clone)]
+pub struct OptimizeSummary {
+    /// Size of the archive before optimizing.
+    pub original_size: u64,
+    /// Stored entries that are compressed now.
+    pub recompressed: usize,
+    /// The rewritten archive.
+    pub pack: PackSummary,
+}
+
+impl OptimizeSummary {
+    /// Bytes saved, zero if the archive grew.
+    pub fn saved(&self) -> u64 {
+        self.original_size.saturating_sub(self.pack.archive_size())
+    }
+}
+
+/// Rewrites `archive_path` to `output_path` (which may be the same file)
+/// with contiguous data blocks. Compressed blocks are copied as stored.
+/// With `options.compress`, stored entries are compressed again where that
+/// helps, following the store patterns and minimum size of `options`.
+/// With `options.dedup`, identical blocks are stored once, as when packing.
+pub fn optimize_archive(
+    archive_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    options: &PackOptions,
+) -> Result<OptimizeSummary, ArcError> {
+    let archive_path = archive_path.as_ref();
+    let output_path = output_path.as_ref();
+    options.parallelism.install(|| {
+        let original_size = archive_path.metadata().with_path(archive_path)?.len();
+        let archive = ArcReader::open(archive_path)?;
+        let mut task_reader = BufReader::new(File::open(archive_path).with_path(archive_path)?);
+        let mut files = archive
+            .entries()
+            .iter()
+            .map(|entry| {
+                let data = reader::read_raw_entry_data(&mut task_reader, entry)?;
+                Ok(stored_file(entry, data))
+            })
+            .collect::<Result<Vec<_>, ArcError>>()?;
+
+        let metrics = options.metrics.clone().unwrap_or_default();
+        let mut recompressed = 0;
+        if options.compress {
+            // Stored entries go through the pack pipeline, then everything is
+            // put back in metadata order
+            let (stored, kept): (Vec<_>, Vec<_>) = files
+                .into_iter()
+                .enumerate()
+                .partition(|(_, file)| file.compressed_size == file.original_size);
+            let (indices, mut stored): (Vec<_>, Vec<_>) = stored.into_iter().unzip();
+            process_pack_files(&mut stored, options, &metrics)?;
+            recompressed = stored
+                .iter()
+                .filter(|file| file.compressed_size != file.original_size)
+                .count();
+            let mut all: Vec<_> = kept
+                .into_iter()
+                .chain(indices.into_iter().zip(stored))
+                .collect();
+            all.sort_by_key(|(index, _)| *index);
+            files = all.into_iter().map(|(_, file)| file).collect();
+        }
+
+        info!(
+            "Rewriting {} entries, {} of them compressed again.",
+            files.len(),
+            recompressed
+        );
+        let pack = write_pack_files(files, output_path, options, &metrics)?;
+        Ok(OptimizeSummary {
+            original_size,
+            recompressed,
+            pack,
+        })
+    })
+}
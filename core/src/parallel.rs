@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::error::ArcError;
+
+// --- Parallelism ---
+
+/// Where the parallel parts of an operation run.
+#[derive(Debug, Clone, Default)]
+pub enum Parallelism {
+    /// Rayon's global thread pool.
+    #[default]
+    Global,
+    /// A dedicated pool with this many threads, built for the operation.
+    Threads(usize),
+    /// A pool provided by the caller, e.g. to isolate this crate's CPU usage
+    /// from the host application's own parallel workloads.
+    Pool(Arc<ThreadPool>),
+}
+
+impl Parallelism {
+    /// Runs `op` so that every rayon call inside it uses the selected pool.
+    pub(crate) fn install<R: Send>(
+        &self,
+        op: impl FnOnce() -> Result<R, ArcError> + Send,
+    ) -> Result<R, ArcError> {
+        match self {
+            Parallelism::Global => op(),
+            Parallelism::Threads(n) => ThreadPoolBuilder::new()
+                .num_threads(*n)
+                .build()
+                .map_err(|e| ArcError::ThreadPool(e.to_string()))?
+                .install(op),
+            Parallelism::Pool(pool) => pool.install(op),
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use log::info;
+use serde_json::{Value, json};
+
+use crate::{
+    PackFileInfo, PackOptions, PackSummary,
+    checksum::sha256_hex,
+    error::{ArcError, PathContext},
+    reader::{self, ArcReader},
+    write_pack_files,
+};
+
+// --- Archive Patches ---
+// A patch rebuilds a new version of an archive from an old one. It starts
+// with `PATCH_MAGIC`, a u32 LE length and a JSON header, followed by the
+// data blocks the base archive does not have:
+//
+//   {
+//     "format": 1,
+//     "entries": [
+//       {
+//         "name": "...", "encrypted_name": "<hex>",
+//         "compressed_size": 812, "original_size": 2048,
+//         "base": { "name": "...", "sha256": "<hex>" }
+//       },
+//       { "name": "...", ..., "data_offset": 0 }
+//     ]
+//   }
+//
+// Entries are listed in the order of the new archive. An entry with `base`
+// copies the stored block of that base entry, whose SHA-256 must match;
+// otherwise the block is at `data_offset` in the patch's data section.
+// Blocks are compared as stored, so applying a patch reproduces the new
+// archive's entries byte for byte.
+
+/// Magic bytes at the start of a patch file.
+pub const PATCH_MAGIC: &[u8; 8] = b"SARCPAT\x01";
+
+/// What a written patch contains.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchSummary {
+    /// Entries whose data is copied from the base archive.
+    pub copied: usize,
+    /// Entries whose data is stored in the patch.
+    pub included: usize,
+    /// Size of the patch file.
+    pub patch_size: u64,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Writes a patch turning `old_path` into `new_path` to `output_path`. Data
+/// blocks the old archive already has (under any name) are referenced
+/// instead of included.
+pub fn make_patch(
+    old_path: impl AsRef<Path>,
+    new_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+) -> Result<PatchSummary, ArcError> {
+    let (old_path, new_path) = (old_path.as_ref(), new_path.as_ref());
+    let output_path = output_path.as_ref();
+    info!("Making a patch from {:?} to {:?}", old_path, new_path);
+    let old = ArcReader::open(old_path)?;
+    let new = ArcReader::open(new_path)?;
+
+    // Base blocks by digest of the stored data, preferring the entry of the
+    // same name
+    let mut old_reader = BufReader::new(File::open(old_path).with_path(old_path)?);
+    let mut base_blocks: HashMap<(u32, u32, String), String> = HashMap::new();
+    for entry in old.entries() {
+        let data = reader::read_raw_entry_data(&mut old_reader, entry)?;
+        let key = (
+            entry.compressed_size(),
+            entry.original_size(),
+            sha256_hex(&data),
+        );
+        if new.contains(entry.name()) || !base_blocks.contains_key(&key) {
+            base_blocks.insert(key, entry.name().to_string());
+        }
+    }
+
+    let mut new_reader = BufReader::new(File::open(new_path).with_path(new_path)?);
+    let mut entries = Vec::with_capacity(new.entries().len());
+    let mut data_section = Vec::new();
+    let mut summary = PatchSummary::default();
+    for entry in new.entries() {
+        let data = reader::read_raw_entry_data(&mut new_reader, entry)?;
+        let digest = sha256_hex(&data);
+        let mut record = json!({
+            "name": entry.name(),
+            "encrypted_name": to_hex(entry.encrypted_name()),
+            "compressed_size": entry.compressed_size(),
+            "original_size": entry.original_size(),
+        });
+        let key = (entry.compressed_size(), entry.original_size(), digest);
+        match base_blocks.get(&key) {
+            Some(base_name) => {
+                record["base"] = json!({ "name": base_name, "sha256": key.2 });
+                summary.copied += 1;
+            }
+            None => {
+                record["data_offset"] = json!(data_section.len());
+                data_section.extend_from_slice(&data);
+                summary.included += 1;
+            }
+        }
+        entries.push(record);
+    }
+
+    let header = json!({ "format": 1, "entries": entries }).to_string();
+    let mut writer = BufWriter::new(File::create(output_path).with_path(output_path)?);
+    writer.write_all(PATCH_MAGIC)?;
+    writer.write_all(&(header.len() as u32).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    writer.write_all(&data_section)?;
+    writer.flush()?;
+    summary.patch_size = (PATCH_MAGIC.len() + 4 + header.len() + data_section.len()) as u64;
+    info!(
+        "Patch has {} entries from the base and {} included ({} bytes).",
+        summary.copied, summary.included, summary.patch_size
+    );
+    Ok(summary)
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Where the data of a patched entry comes from.
+enum PatchSource {
+    /// The stored block of this base entry, with its expected SHA-256.
+    Base { name: String, sha256: String },
+    /// This offset in the patch's data section.
+    Included(u64),
+}
+
+/// One entry of the patched archive.
+struct PatchRecord {
+    name: String,
+    encrypted_name: Vec<u8>,
+    compressed_size: u32,
+    original_size: u32,
+    source: PatchSource,
+}
+
+/// Reads the header of the patch at `patch_path`, returning its records and
+/// the offset of the data section.
+fn read_patch_header(patch_path: &Path) -> Result<(Vec<PatchRecord>, u64), ArcError> {
+    let invalid = |message: String| ArcError::InvalidFormat(format!("patch header: {message}"));
+    let mut file = BufReader::new(File::open(patch_path).with_path(patch_path)?);
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != PATCH_MAGIC {
+        return Err(ArcError::InvalidFormat(format!(
+            "{} is not a patch file",
+            patch_path.display()
+        )));
+    }
+    let mut length = [0u8; 4];
+    file.read_exact(&mut length)?;
+    let length = u32::from_le_bytes(length);
+    let mut header = Vec::new();
+    file.take(length as u64).read_to_end(&mut header)?;
+    let document: Value = serde_json::from_slice(&header).map_err(|e| invalid(e.to_string()))?;
+    if document["format"] != 1 {
+        return Err(invalid("unsupported format version".to_string()));
+    }
+    let entries = document["entries"]
+        .as_array()
+        .ok_or_else(|| invalid("missing entry list".to_string()))?;
+
+    let records = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let field = |key: &str| invalid(format!("entry {index}: bad or missing {key:?}"));
+            let str_field = |key: &str| entry[key].as_str().ok_or_else(|| field(key));
+            let size_field = |key: &str| {
+                entry[key]
+                    .as_u64()
+                    .and_then(|size| u32::try_from(size).ok())
+                    .ok_or_else(|| field(key))
+            };
+            let source = match entry.get("base") {
+                Some(base) => PatchSource::Base {
+                    name: base["name"]
+                        .as_str()
+                        .ok_or_else(|| field("base"))?
+                        .to_string(),
+                    sha256: base["sha256"]
+                        .as_str()
+                        .ok_or_else(|| field("base"))?
+                        .to_string(),
+                },
+                None => PatchSource::Included(
+                    entry["data_offset"]
+                        .as_u64()
+                        .ok_or_else(|| field("data_offset"))?,
+                ),
+            };
+            Ok(PatchRecord {
+                name: str_field("name")?.to_string(),
+                encrypted_name: from_hex(str_field("encrypted_name")?)
+                    .ok_or_else(|| field("encrypted_name"))?,
+                compressed_size: size_field("compressed_size")?,
+                original_size: size_field("original_size")?,
+                source,
+            })
+        })
+        .collect::<Result<Vec<_>, ArcError>>()?;
+    Ok((records, (PATCH_MAGIC.len() + 4) as u64 + length as u64))
+}
+
+/// Applies the patch at `patch_path` to `base_path` and writes the patched
+/// archive to `output_path` (which may be the base itself). Every base
+/// entry the patch uses is checked against its recorded SHA-256 first;
+/// nothing is written if one differs or is missing.
+pub fn apply_patch(
+    base_path: impl AsRef<Path>,
+    patch_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    let (base_path, patch_path) = (base_path.as_ref(), patch_path.as_ref());
+    let output_path = output_path.as_ref();
+    info!("Applying {:?} to {:?}", patch_path, base_path);
+    let base = ArcReader::open(base_path)?;
+    let (records, data_start) = read_patch_header(patch_path)?;
+
+    let mut base_reader = BufReader::new(File::open(base_path).with_path(base_path)?);
+    let mut base_blocks: HashMap<&str, Vec<u8>> = HashMap::new();
+    for record in &records {
+        let PatchSource::Base { name, sha256 } = &record.source else {
+            continue;
+        };
+        if base_blocks.contains_key(name.as_str()) {
+            continue;
+        }
+        let entry = base
+            .get(name)
+            .ok_or_else(|| ArcError::MissingEntries(vec![name.clone()]))?;
+        let data = reader::read_raw_entry_data(&mut base_reader, entry)?;
+        if sha256_hex(&data) != *sha256 {
+            return Err(ArcError::VerificationFailed(
+                name.clone(),
+                "the base archive's entry differs from the one the patch was made for".to_string(),
+            ));
+        }
+        base_blocks.insert(name, data);
+    }
+
+    let mut patch_reader = BufReader::new(File::open(patch_path).with_path(patch_path)?);
+    let files = records
+        .iter()
+        .map(|record| {
+            let data = match &record.source {
+                PatchSource::Base { name, .. } => base_blocks[name.as_str()].clone(),
+                PatchSource::Included(offset) => {
+                    patch_reader.seek(SeekFrom::Start(data_start + offset))?;
+                    let mut data = Vec::new();
+                    (&mut patch_reader)
+                        .take(record.compressed_size as u64)
+                        .read_to_end(&mut data)?;
+                    data
+                }
+            };
+            if data.len() != record.compressed_size as usize {
+                return Err(ArcError::InvalidFormat(format!(
+                    "patch data of {:?} is truncated",
+                    record.name
+                )));
+            }
+            Ok(PackFileInfo {
+                relative_path: PathBuf::from(&record.name),
+                name: record.name.clone(),
+                full_path: PathBuf::new(),
+                encrypted_name: record.encrypted_name.clone(),
+                original_size: record.original_size,
+                compressed_size: record.compressed_size,
+                compressed_data: Some(data),
+                offset: 0,
+                source_digest: None,
+                spilled: None,
+            })
+        })
+        .collect::<Result<Vec<_>, ArcError>>()?;
+    info!(
+        "Patched archive has {} entries, {} taken from the base.",
+        files.len(),
+        records
+            .iter()
+            .filter(|record| matches!(record.source, PatchSource::Base { .. }))
+            .count()
+    );
+    let metrics = options.metrics.clone().unwrap_or_default();
+    write_pack_files(files, output_path, options, &metrics)
+}
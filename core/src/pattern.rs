@@ -0,0 +1,50 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::{error::ArcError, name::SEPARATOR};
+
+// --- Name Patterns ---
+
+/// A set of glob patterns (e.g. `*.ogg`) matched case-insensitively against
+/// entry names. `\` and `/` are both accepted as separators, and `*` also
+/// matches across them, so `*.ogg` matches `bgm\01.ogg`.
+#[derive(Debug, Clone)]
+pub struct NamePatterns {
+    set: GlobSet,
+    patterns: Vec<String>,
+}
+
+impl NamePatterns {
+    pub fn new<I, S>(patterns: I) -> Result<Self, ArcError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns: Vec<String> = patterns
+            .into_iter()
+            .map(|pattern| pattern.as_ref().to_string())
+            .collect();
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &patterns {
+            let glob = GlobBuilder::new(&pattern.replace(SEPARATOR, "/"))
+                .case_insensitive(true)
+                .backslash_escape(false)
+                .build()
+                .map_err(|e| ArcError::InvalidPattern(pattern.to_string(), e.kind().to_string()))?;
+            builder.add(glob);
+        }
+        let set = builder
+            .build()
+            .map_err(|e| ArcError::InvalidPattern(String::new(), e.to_string()))?;
+        Ok(Self { set, patterns })
+    }
+
+    /// The patterns as given.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether any pattern matches the entry name.
+    pub fn is_match(&self, name: &str) -> bool {
+        self.set.is_match(name.replace(SEPARATOR, "/"))
+    }
+}
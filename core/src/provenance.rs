@@ -0,0 +1,71 @@
+use std::{
+    env,
+    io::{Read, Seek},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+
+use crate::{PackOptions, checksum::sha256_hex, error::ArcError, reader::ArcReader};
+
+// --- Provenance ---
+// With `PackOptions::provenance`, a reserved JSON entry records how the
+// archive was built: tool version, time, a hash of the sources and the pack
+// settings. The engine never looks at it, but patched archives found in the
+// wild can be traced back to their build.
+
+/// Name of the reserved provenance entry.
+pub const PROVENANCE_NAME: &str = "__silkyarctool__.json";
+
+/// Builds the provenance document. `sources` are `(entry name, SHA-256 hex)`
+/// pairs of the packed files; their `sha256sum`-style manifest, sorted by
+/// name, is hashed so the sources can be compared without listing them.
+///
+/// The timestamp honours `SOURCE_DATE_EPOCH` for reproducible builds.
+pub(crate) fn provenance_json(mut sources: Vec<(&str, String)>, options: &PackOptions) -> Vec<u8> {
+    sources.sort_unstable();
+    let manifest: String = sources
+        .iter()
+        .map(|(name, digest)| format!("{digest}  {name}\n"))
+        .collect();
+    let created = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs())
+        });
+    #[cfg(feature = "patterns")]
+    let store_patterns = options
+        .store_patterns
+        .as_ref()
+        .map(|patterns| patterns.patterns());
+    // Without pattern support nothing is stored by pattern
+    #[cfg(not(feature = "patterns"))]
+    let store_patterns: Option<()> = None;
+    let document = json!({
+        "tool": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "created": created,
+        "source_files": sources.len(),
+        "source_manifest_sha256": sha256_hex(manifest.as_bytes()),
+        "settings": {
+            "compress": options.compress,
+            "min_compress_size": options.min_compress_size,
+            "store_patterns": store_patterns,
+        },
+    });
+    serde_json::to_vec_pretty(&document).expect("JSON values always serialize")
+}
+
+/// Reads the provenance entry of `archive`, if it has one.
+pub fn read_provenance<R: Read + Seek>(
+    archive: &mut ArcReader<R>,
+) -> Result<Option<String>, ArcError> {
+    let Some(entry) = archive.get(PROVENANCE_NAME).cloned() else {
+        return Ok(None);
+    };
+    let data = archive.read_entry(&entry)?;
+    Ok(Some(String::from_utf8_lossy(&data).into_owned()))
+}
@@ -0,0 +1,482 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::OnceLock,
+};
+
+use log::debug;
+
+use crate::{
+    HEADER_SIZE, decompress_data_tracked,
+    error::{ArcError, PathContext},
+    format,
+    metrics::Metrics,
+    name::{self, DEFAULT_NAME_ENCODINGS, NameCodec, NameEncoding},
+    stream::EntryReader,
+};
+
+// --- Metadata Parsing ---
+
+/// A single file entry from the archive's metadata section.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArcEntry {
+    pub(crate) encrypted_name: Vec<u8>,
+    pub(crate) name: String, // Decrypted name
+    pub(crate) compressed_size: u32,
+    pub(crate) original_size: u32,
+    pub(crate) offset: u32,
+}
+
+impl ArcEntry {
+    /// Decrypted entry name, using `\` as separator.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name bytes as stored in the metadata (encrypted).
+    pub fn encrypted_name(&self) -> &[u8] {
+        &self.encrypted_name
+    }
+
+    /// Size of the (possibly compressed) data block in the archive.
+    pub fn compressed_size(&self) -> u32 {
+        self.compressed_size
+    }
+
+    /// Size of the file once decompressed.
+    pub fn original_size(&self) -> u32 {
+        self.original_size
+    }
+
+    /// Absolute offset of the data block from the start of the archive.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Whether the data block is LZSS compressed, which the format marks by
+    /// a compressed size that differs from the original size.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed_size != self.original_size
+    }
+}
+
+/// A problem with a single entry that a lenient operation skipped over.
+#[derive(Debug)]
+pub struct EntryFailure {
+    /// Index of the entry in the metadata. For a malformed metadata record,
+    /// the index it would have had.
+    pub index: usize,
+    /// Entry name, lossily decoded if necessary. Empty for a malformed
+    /// metadata record.
+    pub name: String,
+    pub error: ArcError,
+}
+
+/// Parsed archive metadata together with the underlying reader.
+#[derive(Debug)]
+pub struct ArcReader<R> {
+    reader: R,
+    metadata_size: u32,
+    entries: Vec<ArcEntry>,
+    name_encoding: NameEncoding,
+    // Entry index by name, built on the first lookup
+    index: OnceLock<HashMap<String, usize>>,
+}
+
+impl ArcReader<BufReader<File>> {
+    /// Opens an archive file and parses its metadata section. The data
+    /// section is not read.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ArcError> {
+        Self::open_with_encodings(path, DEFAULT_NAME_ENCODINGS)
+    }
+
+    /// Like [`ArcReader::open`], detecting the name encoding among
+    /// `encodings`.
+    pub fn open_with_encodings(
+        path: impl AsRef<Path>,
+        encodings: &[NameEncoding],
+    ) -> Result<Self, ArcError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(ArcError::NotFound(path.to_path_buf()));
+        }
+        Self::with_encodings(BufReader::new(File::open(path).with_path(path)?), encodings)
+    }
+
+    /// Like [`ArcReader::open_with_encodings`], but keeps going past broken
+    /// entries, see [`ArcReader::with_encodings_lenient`].
+    pub fn open_lenient(
+        path: impl AsRef<Path>,
+        encodings: &[NameEncoding],
+    ) -> Result<(Self, Vec<EntryFailure>), ArcError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(ArcError::NotFound(path.to_path_buf()));
+        }
+        Self::with_encodings_lenient(BufReader::new(File::open(path).with_path(path)?), encodings)
+    }
+
+    /// Like [`ArcReader::open`], decrypting names with `codec`, see
+    /// [`ArcReader::with_codec`].
+    pub fn open_with_codec(
+        path: impl AsRef<Path>,
+        codec: &dyn NameCodec,
+    ) -> Result<Self, ArcError> {
+        Ok(Self::open_with_codec_lenient(path, codec, false)?.0)
+    }
+
+    pub(crate) fn open_with_codec_lenient(
+        path: impl AsRef<Path>,
+        codec: &dyn NameCodec,
+        lenient: bool,
+    ) -> Result<(Self, Vec<EntryFailure>), ArcError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(ArcError::NotFound(path.to_path_buf()));
+        }
+        Self::with_codec_lenient(
+            BufReader::new(File::open(path).with_path(path)?),
+            codec,
+            lenient,
+        )
+    }
+}
+
+impl<R: Read + Seek> ArcReader<R> {
+    pub fn new(reader: R) -> Result<Self, ArcError> {
+        Self::with_encodings(reader, DEFAULT_NAME_ENCODINGS)
+    }
+
+    /// Parses the metadata, decoding names with the first of `encodings`
+    /// that fits them (see [`name::detect_name_encoding`]).
+    pub fn with_encodings(mut reader: R, encodings: &[NameEncoding]) -> Result<Self, ArcError> {
+        let (metadata_size, mut entries, _) = read_metadata(&mut reader, false)?;
+        let raw_names: Vec<&[u8]> = entries
+            .iter()
+            .map(|e| e.encrypted_name.as_slice())
+            .collect();
+        let Some((name_encoding, names)) = name::detect_name_encoding(&raw_names, encodings) else {
+            // Report the first name the preferred encoding fails on. Every
+            // name fails if there are no candidates at all.
+            let preferred = encodings.first().copied().unwrap_or_default();
+            let failed = raw_names
+                .iter()
+                .find(|raw_name| name::decrypt_name_as(raw_name, preferred).is_err())
+                .or(raw_names.first());
+            return Err(ArcError::NameDecodeError(
+                failed.map(|raw_name| raw_name.to_vec()).unwrap_or_default(),
+            ));
+        };
+        for (entry, name) in entries.iter_mut().zip(names) {
+            entry.name = name;
+        }
+        Ok(Self {
+            reader,
+            metadata_size,
+            entries,
+            name_encoding,
+            index: OnceLock::new(),
+        })
+    }
+
+    /// Like [`ArcReader::with_encodings`], but only a missing or invalid
+    /// header is an error. Metadata records are parsed up to the first
+    /// malformed or truncated one, and if no encoding fits all names, names
+    /// the first of `encodings` cannot decode are decoded lossily. These
+    /// problems are returned along with the reader.
+    pub fn with_encodings_lenient(
+        mut reader: R,
+        encodings: &[NameEncoding],
+    ) -> Result<(Self, Vec<EntryFailure>), ArcError> {
+        let (metadata_size, mut entries, mut failures) = read_metadata(&mut reader, true)?;
+        let raw_names: Vec<&[u8]> = entries
+            .iter()
+            .map(|e| e.encrypted_name.as_slice())
+            .collect();
+        let (name_encoding, names) = match name::detect_name_encoding(&raw_names, encodings) {
+            Some(detected) => detected,
+            None => {
+                let preferred = encodings.first().copied().unwrap_or_default();
+                let mut names = Vec::with_capacity(raw_names.len());
+                for (index, raw_name) in raw_names.into_iter().enumerate() {
+                    let name = name::decrypt_name_as(raw_name, preferred).unwrap_or_else(|error| {
+                        let unshifted = name::unshift_name(raw_name);
+                        let name = preferred.encoding().decode(&unshifted).0.into_owned();
+                        failures.push(EntryFailure {
+                            index,
+                            name: name.clone(),
+                            error,
+                        });
+                        name
+                    });
+                    names.push(name);
+                }
+                (preferred, names)
+            }
+        };
+        for (entry, name) in entries.iter_mut().zip(names) {
+            entry.name = name;
+        }
+        failures.sort_by_key(|failure| failure.index);
+        let archive = Self {
+            reader,
+            metadata_size,
+            entries,
+            name_encoding,
+            index: OnceLock::new(),
+        };
+        Ok((archive, failures))
+    }
+
+    /// Parses the metadata, decrypting names with `codec` instead of the
+    /// Silky scheme, for archives of related engines.
+    /// [`ArcReader::name_encoding`] then reports the default encoding.
+    pub fn with_codec(reader: R, codec: &dyn NameCodec) -> Result<Self, ArcError> {
+        Ok(Self::with_codec_lenient(reader, codec, false)?.0)
+    }
+
+    /// With `lenient`, metadata is parsed as by
+    /// [`ArcReader::with_encodings_lenient`], but names `codec` fails on
+    /// are still an error.
+    pub(crate) fn with_codec_lenient(
+        mut reader: R,
+        codec: &dyn NameCodec,
+        lenient: bool,
+    ) -> Result<(Self, Vec<EntryFailure>), ArcError> {
+        let (metadata_size, mut entries, failures) = read_metadata(&mut reader, lenient)?;
+        for entry in &mut entries {
+            entry.name = codec.decrypt(&entry.encrypted_name)?;
+        }
+        let archive = Self {
+            reader,
+            metadata_size,
+            entries,
+            name_encoding: NameEncoding::default(),
+            index: OnceLock::new(),
+        };
+        Ok((archive, failures))
+    }
+
+    /// Entries in metadata order.
+    pub fn entries(&self) -> &[ArcEntry] {
+        &self.entries
+    }
+
+    /// The entry named `name` (`\` separated). If several entries share the
+    /// name, the first one is returned.
+    pub fn get(&self, name: &str) -> Option<&ArcEntry> {
+        let index = self.index.get_or_init(|| {
+            let mut index = HashMap::with_capacity(self.entries.len());
+            for (position, entry) in self.entries.iter().enumerate() {
+                index.entry(entry.name.clone()).or_insert(position);
+            }
+            index
+        });
+        index.get(name).map(|&position| &self.entries[position])
+    }
+
+    /// Whether an entry named `name` exists.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Size of the metadata section, not counting the global header.
+    pub fn metadata_size(&self) -> u32 {
+        self.metadata_size
+    }
+
+    /// Encoding the entry names were decoded with.
+    pub fn name_encoding(&self) -> NameEncoding {
+        self.name_encoding
+    }
+
+    /// The underlying reader.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Reads an entry's data, decompressing it if needed.
+    pub fn read_entry(&mut self, entry: &ArcEntry) -> Result<Vec<u8>, ArcError> {
+        read_entry_data(&mut self.reader, entry)
+    }
+
+    /// Reads an entry's data block as stored in the archive.
+    pub(crate) fn read_raw_entry(&mut self, entry: &ArcEntry) -> Result<Vec<u8>, ArcError> {
+        read_raw_entry_data(&mut self.reader, entry)
+    }
+
+    /// A reader over the data of the entry named `name`, decompressed as it
+    /// is read, so memory use does not grow with the entry's size.
+    pub fn entry_reader(&mut self, name: &str) -> Result<EntryReader<&mut R>, ArcError> {
+        let entry = self
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ArcError::MissingEntries(vec![name.to_string()]))?;
+        self.reader.seek(SeekFrom::Start(entry.offset as u64))?;
+        Ok(EntryReader::new(&mut self.reader, &entry))
+    }
+
+    /// Streams the data of the entry named `name` into `writer`, returning
+    /// the number of bytes written.
+    pub fn extract_entry_to(
+        &mut self,
+        name: &str,
+        writer: &mut impl Write,
+    ) -> Result<u64, ArcError> {
+        Ok(io::copy(&mut self.entry_reader(name)?, writer)?)
+    }
+}
+
+/// Reads the global header and all metadata entries, leaving `reader`
+/// positioned at the start of the file data section. Names are left empty
+/// until the encoding has been detected. A `lenient` read keeps the records
+/// before a truncated or malformed one and reports the problem instead.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+fn read_metadata<R: Read + Seek>(
+    reader: &mut R,
+    lenient: bool,
+) -> Result<(u32, Vec<ArcEntry>, Vec<EntryFailure>), ArcError> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut header = [0u8; HEADER_SIZE as usize];
+    reader.read_exact(&mut header)?;
+    let mut metadata_size = format::parse_header(&header)?;
+    if metadata_size == format::LEGACY_EMPTY_HEADER {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(HEADER_SIZE as u64))?;
+        metadata_size = format::effective_metadata_size(metadata_size, file_len);
+    }
+    debug!("Metadata block size: {}", metadata_size);
+
+    let mut metadata = Vec::new();
+    reader
+        .by_ref()
+        .take(metadata_size as u64)
+        .read_to_end(&mut metadata)?;
+    let mut problem = None;
+    if metadata.len() != metadata_size as usize {
+        let error = ArcError::InvalidFormat(format!(
+            "metadata section claims {metadata_size} bytes but only {} are present",
+            metadata.len()
+        ));
+        if !lenient {
+            return Err(error);
+        }
+        problem = Some(error);
+    }
+
+    let records = if lenient {
+        let mut records = Vec::new();
+        let mut data = metadata.as_slice();
+        while !data.is_empty() {
+            match format::parse_entry(data) {
+                Ok((record, len)) => {
+                    records.push(record);
+                    data = &data[len..];
+                }
+                Err(error) => {
+                    // A record cut off by truncation is reported as such
+                    problem.get_or_insert(error);
+                    break;
+                }
+            }
+        }
+        records
+    } else {
+        format::parse_metadata(&metadata)?
+    };
+    let failures = problem
+        .map(|error| EntryFailure {
+            index: records.len(),
+            name: String::new(),
+            error,
+        })
+        .into_iter()
+        .collect();
+
+    let entries: Vec<ArcEntry> = records
+        .into_iter()
+        .map(|record| ArcEntry {
+            encrypted_name: record.encrypted_name, // Keep for potential packing later if needed
+            name: String::new(),
+            compressed_size: record.compressed_size,
+            original_size: record.original_size,
+            offset: record.offset,
+        })
+        .collect();
+    debug!("Read {} file entries from metadata.", entries.len());
+    Ok((metadata_size, entries, failures))
+}
+
+/// Reads an entry's data block and decompresses it if needed.
+pub(crate) fn read_entry_data<R: Read + Seek>(
+    reader: &mut R,
+    entry: &ArcEntry,
+) -> Result<Vec<u8>, ArcError> {
+    let raw_data = read_raw_entry_data(reader, entry)?;
+    decode_entry_data(entry, raw_data, None)
+}
+
+/// Reads an entry's data block as stored in the archive.
+pub(crate) fn read_raw_entry_data<R: Read + Seek>(
+    reader: &mut R,
+    entry: &ArcEntry,
+) -> Result<Vec<u8>, ArcError> {
+    // Seek and read the (potentially compressed) data. The size comes from the
+    // metadata, so only allocate what is actually there.
+    reader.seek(SeekFrom::Start(entry.offset as u64))?;
+    let mut compressed_data = Vec::new();
+    reader
+        .take(entry.compressed_size as u64)
+        .read_to_end(&mut compressed_data)?;
+    if compressed_data.len() != entry.compressed_size as usize {
+        return Err(ArcError::InvalidFormat(format!(
+            "data of {:?} is truncated: {} of {} bytes present",
+            entry.name,
+            compressed_data.len(),
+            entry.compressed_size
+        )));
+    }
+    Ok(compressed_data)
+}
+
+/// Turns a stored data block into the entry's original data, counting the
+/// produced bytes in `metrics` if given.
+pub(crate) fn decode_entry_data(
+    entry: &ArcEntry,
+    raw_data: Vec<u8>,
+    metrics: Option<&Metrics>,
+) -> Result<Vec<u8>, ArcError> {
+    if entry.is_compressed() {
+        decompress_data_tracked(&raw_data, entry.original_size, metrics)
+    } else {
+        // Data is not compressed
+        if let Some(metrics) = metrics {
+            metrics.add_done(raw_data.len() as u64);
+        }
+        Ok(raw_data)
+    }
+}
+
+/// For every entry, the index of the first entry stored in the same data
+/// block, or `None` for entries that own their block. Deduplicated archives
+/// point several entries at one block; empty entries never share.
+pub fn shared_data_sources(entries: &[ArcEntry]) -> Vec<Option<usize>> {
+    let mut first_by_block: HashMap<(u32, u32), usize> = HashMap::new();
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            if entry.compressed_size == 0 {
+                return None;
+            }
+            let first = *first_by_block
+                .entry((entry.offset, entry.compressed_size))
+                .or_insert(index);
+            (first != index).then_some(first)
+        })
+        .collect()
+}
@@ -0,0 +1,102 @@
+use std::{borrow::Cow, str::FromStr};
+
+use regex::Regex;
+
+use crate::error::ArcError;
+
+// --- Rename Rules ---
+// sed-style substitutions applied to entry names before they become output
+// paths, e.g. `s/^voice\\//` to drop a leading `voice\` directory. Names use
+// `\` as separator when the rules run.
+
+/// A single `s<d>pattern<d>replacement<d>[g]` substitution. Any character can
+/// be the delimiter `<d>`; `\<d>` stands for a literal delimiter. In the
+/// replacement, `\1`..`\9` and `&` refer to groups as in sed.
+#[derive(Debug, Clone)]
+pub struct RenameRule {
+    regex: Regex,
+    /// Replacement in `regex` syntax.
+    replacement: String,
+    global: bool,
+}
+
+impl RenameRule {
+    /// Applies the rule, replacing the first match or, with `g`, all of them.
+    pub fn apply<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match self.global {
+            true => self.regex.replace_all(name, self.replacement.as_str()),
+            false => self.regex.replace(name, self.replacement.as_str()),
+        }
+    }
+}
+
+impl FromStr for RenameRule {
+    type Err = ArcError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| ArcError::InvalidPattern(rule.to_string(), reason.to_string());
+        let mut chars = rule.chars();
+        if chars.next() != Some('s') {
+            return Err(invalid("expected `s/pattern/replacement/`"));
+        }
+        let delimiter = chars.next().ok_or_else(|| invalid("missing delimiter"))?;
+
+        // Split into pattern, replacement and flags at unescaped delimiters
+        let mut parts = vec![String::new()];
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(next) if next == delimiter => parts.last_mut().unwrap().push(next),
+                    Some(next) => parts.last_mut().unwrap().extend(['\\', next]),
+                    None => return Err(invalid("trailing backslash")),
+                },
+                c if c == delimiter && parts.len() < 3 => parts.push(String::new()),
+                c => parts.last_mut().unwrap().push(c),
+            }
+        }
+        let [pattern, replacement, flags] = <[String; 3]>::try_from(parts)
+            .map_err(|_| invalid("expected `s/pattern/replacement/`"))?;
+        let global = match flags.as_str() {
+            "" => false,
+            "g" => true,
+            _ => return Err(invalid("unknown flags, only `g` is supported")),
+        };
+        let regex = Regex::new(&pattern).map_err(|e| invalid(&e.to_string()))?;
+        Ok(Self {
+            regex,
+            replacement: sed_to_regex_replacement(&replacement),
+            global,
+        })
+    }
+}
+
+/// Translates sed's `\1` and `&` group references into `regex` syntax,
+/// escaping `$`.
+fn sed_to_regex_replacement(replacement: &str) -> String {
+    let mut translated = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => translated.push_str(&format!("${{{digit}}}")),
+                Some('$') => translated.push_str("$$"),
+                None => translated.push('\\'),
+                Some(other) => translated.push(other),
+            },
+            '&' => translated.push_str("${0}"),
+            '$' => translated.push_str("$$"),
+            c => translated.push(c),
+        }
+    }
+    translated
+}
+
+/// Applies all `rules` to `name`, in order.
+pub fn apply_rename_rules<'a>(rules: &[RenameRule], name: &'a str) -> Cow<'a, str> {
+    rules
+        .iter()
+        .fold(Cow::Borrowed(name), |name, rule| match rule.apply(&name) {
+            Cow::Borrowed(_) => name,
+            Cow::Owned(renamed) => Cow::Owned(renamed),
+        })
+}
@@ -0,0 +1,74 @@
+use std::{collections::HashSet, fmt, fs, path::Path, sync::Arc};
+
+use crate::{
+    error::{ArcError, PathContext},
+    name::SEPARATOR,
+};
+
+// --- Entry Selection ---
+
+/// Reads a list of entry names, one per line, or NUL separated if the file
+/// contains a NUL byte (as written by `find -print0`). Empty names are
+/// skipped and `/` is accepted as a separator.
+pub fn read_entry_list(path: impl AsRef<Path>) -> Result<HashSet<String>, ArcError> {
+    let path = path.as_ref();
+    let content = fs::read(path).with_path(path)?;
+    let content = String::from_utf8(content).map_err(|_| {
+        ArcError::InvalidFormat(format!("{}: entry list is not UTF-8", path.display()))
+    })?;
+    Ok(parse_entry_list(&content))
+}
+
+/// Parses the content of an entry list, see [`read_entry_list`].
+pub fn parse_entry_list(content: &str) -> HashSet<String> {
+    let names: Box<dyn Iterator<Item = &str>> = if content.contains('\0') {
+        Box::new(content.split('\0'))
+    } else {
+        Box::new(content.lines().map(str::trim))
+    };
+    names
+        .filter(|name| !name.is_empty())
+        .map(normalize_entry_name)
+        .collect()
+}
+
+/// Accepts `/` as a separator in a user supplied entry name.
+pub fn normalize_entry_name(name: &str) -> String {
+    name.replace('/', &SEPARATOR.to_string())
+}
+
+/// Inclusive bounds on an entry's original size. Unset bounds do not
+/// restrict.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeRange {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+impl SizeRange {
+    pub fn contains(&self, size: u64) -> bool {
+        self.min.is_none_or(|min| size >= min) && self.max.is_none_or(|max| size <= max)
+    }
+}
+
+/// Caller supplied predicate deciding which files of the input directory
+/// are packed. It is given paths relative to the input directory.
+#[derive(Clone)]
+pub struct PackFilter(Arc<dyn Fn(&Path) -> bool + Send + Sync>);
+
+impl PackFilter {
+    pub fn new(filter: impl Fn(&Path) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(filter))
+    }
+
+    /// Whether the file at `relative_path` is packed.
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        (self.0)(relative_path)
+    }
+}
+
+impl fmt::Debug for PackFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PackFilter(..)")
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+
+use crate::{
+    HEADER_SIZE, PackOptions, PackSummary,
+    edit::stored_file,
+    error::{ArcError, PathContext, warn_or_fail},
+    reader::{self, ArcEntry, ArcReader},
+    write_pack_files,
+};
+
+// --- Archive Splitting ---
+
+/// Bytes `entry` takes in an archive: its metadata record and data block.
+fn entry_footprint(entry: &ArcEntry) -> u64 {
+    (1 + entry.encrypted_name.len() + 12) as u64 + entry.compressed_size() as u64
+}
+
+/// Groups `entries` in order into parts whose archives stay within
+/// `max_size` bytes, as index ranges. An entry too large for any part gets
+/// a part of its own.
+pub fn plan_split(entries: &[ArcEntry], max_size: u64) -> Vec<std::ops::Range<usize>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut size = HEADER_SIZE as u64;
+    for (index, entry) in entries.iter().enumerate() {
+        let footprint = entry_footprint(entry);
+        if index > start && size + footprint > max_size {
+            parts.push(start..index);
+            start = index;
+            size = HEADER_SIZE as u64;
+        }
+        size += footprint;
+    }
+    if start < entries.len() {
+        parts.push(start..entries.len());
+    }
+    parts
+}
+
+/// Splits `archive_path` on entry boundaries into archives of at most
+/// `max_size` bytes each, written to `output_dir` as `<stem>_001.arc`,
+/// `<stem>_002.arc`, ... Every part is a complete archive. Data blocks are
+/// copied as stored.
+pub fn split_archive(
+    archive_path: impl AsRef<Path>,
+    output_dir: impl AsRef<Path>,
+    max_size: u64,
+    options: &PackOptions,
+) -> Result<Vec<(PathBuf, PackSummary)>, ArcError> {
+    let archive_path = archive_path.as_ref();
+    let output_dir = output_dir.as_ref();
+    let archive = ArcReader::open(archive_path)?;
+    let stem = archive_path
+        .file_stem()
+        .ok_or_else(|| ArcError::NoFilename(archive_path.to_path_buf()))?
+        .to_string_lossy();
+    let entries = archive.entries();
+    for entry in entries {
+        if HEADER_SIZE as u64 + entry_footprint(entry) > max_size {
+            warn_or_fail(
+                options.strict,
+                format!(
+                    "{:?} alone takes {} bytes, more than the maximum part size",
+                    entry.name(),
+                    HEADER_SIZE as u64 + entry_footprint(entry)
+                ),
+            )?;
+        }
+    }
+
+    let metrics = options.metrics.clone().unwrap_or_default();
+    let mut task_reader = BufReader::new(File::open(archive_path).with_path(archive_path)?);
+    let mut written = Vec::new();
+    for (number, range) in plan_split(entries, max_size).into_iter().enumerate() {
+        let files = entries[range]
+            .iter()
+            .map(|entry| {
+                let data = reader::read_raw_entry_data(&mut task_reader, entry)?;
+                Ok(stored_file(entry, data))
+            })
+            .collect::<Result<Vec<_>, ArcError>>()?;
+        let output_path = output_dir.join(format!("{stem}_{:03}.arc", number + 1));
+        let summary = write_pack_files(files, &output_path, options, &metrics)?;
+        info!(
+            "Wrote part {:?} with {} entries ({} bytes)",
+            output_path,
+            summary.entry_count(),
+            summary.archive_size()
+        );
+        written.push((output_path, summary));
+    }
+    Ok(written)
+}
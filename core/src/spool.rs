@@ -0,0 +1,57 @@
+use std::{
+    fs,
+    path::{self, Path, PathBuf},
+};
+
+use log::debug;
+use tempfile::NamedTempFile;
+
+use crate::error::{ArcError, PathContext};
+
+// --- Spool Files ---
+// Archives are first written to a scratch file and only moved over the final
+// output once complete, so a failed or interrupted run never leaves a
+// half-written archive behind. By default the scratch file lives next to the
+// output, which keeps the final move a cheap rename on the same filesystem.
+
+/// Directory scratch files for `output` are created in.
+fn spool_dir(output: &Path, temp_dir: Option<&Path>) -> Result<PathBuf, ArcError> {
+    match temp_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir).with_path(dir)?;
+            Ok(dir.to_path_buf())
+        }
+        None => Ok(path::absolute(output)
+            .with_path(output)?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()),
+    }
+}
+
+/// Creates a scratch file that will eventually replace `output`.
+pub fn create_spool_file(
+    output: &Path,
+    temp_dir: Option<&Path>,
+) -> Result<NamedTempFile, ArcError> {
+    let dir = spool_dir(output, temp_dir)?;
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(".silkyarc").suffix(".tmp");
+    // Scratch files are private by default; the finished archive should get
+    // the usual permissions of a newly created file instead.
+    #[cfg(unix)]
+    builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o644));
+    let spool = builder.tempfile_in(&dir).with_path(&dir)?;
+    debug!("Spooling {:?} to {:?}", output, spool.path());
+    Ok(spool)
+}
+
+/// Moves a completed scratch file to `output`, copying it when the temp
+/// directory is on another filesystem.
+pub fn persist_spool_file(spool: NamedTempFile, output: &Path) -> Result<(), ArcError> {
+    if let Err(e) = spool.persist(output) {
+        debug!("Renaming spool file failed ({}), copying instead", e.error);
+        fs::copy(e.file.path(), output).with_path(output)?;
+    }
+    Ok(())
+}
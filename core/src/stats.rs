@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use crate::reader::{ArcEntry, shared_data_sources};
+
+// --- Compression Statistics ---
+
+/// Aggregated sizes of all entries sharing one file extension.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionStats {
+    /// Lowercased extension without the dot, empty for files without one.
+    pub extension: String,
+    pub count: usize,
+    /// Number of entries stored LZSS compressed.
+    pub compressed_count: usize,
+    pub original_size: u64,
+    /// Stored bytes. Data blocks shared by several entries count once.
+    pub compressed_size: u64,
+    /// Original and stored bytes of the entries stored compressed.
+    pub lzss_original_size: u64,
+    pub lzss_compressed_size: u64,
+}
+
+/// LZSS saving below which compressing an extension is considered wasted
+/// effort.
+pub const WASTED_LZSS_SAVING: f64 = 0.1;
+
+impl ExtensionStats {
+    /// Compressed size relative to the original size (1.0 means no gain).
+    pub fn ratio(&self) -> f64 {
+        if self.original_size == 0 {
+            1.0
+        } else {
+            self.compressed_size as f64 / self.original_size as f64
+        }
+    }
+
+    /// Fraction of the original size LZSS saved on the compressed entries,
+    /// `None` if none is compressed.
+    pub fn lzss_saving(&self) -> Option<f64> {
+        (self.lzss_original_size > 0)
+            .then(|| 1.0 - self.lzss_compressed_size as f64 / self.lzss_original_size as f64)
+    }
+
+    /// Whether the entries are compressed although that saves less than
+    /// [`WASTED_LZSS_SAVING`].
+    pub fn lzss_wasted(&self) -> bool {
+        self.lzss_saving()
+            .is_some_and(|saving| saving < WASTED_LZSS_SAVING)
+    }
+}
+
+/// Lowercased extension of an entry name, looking only at the last path
+/// component.
+pub fn entry_extension(name: &str) -> String {
+    let file_name = name.rsplit(['\\', '/']).next().unwrap_or(name);
+    match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext.to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+/// Groups entries by extension, largest original size first.
+pub fn extension_stats(entries: &[ArcEntry]) -> Vec<ExtensionStats> {
+    let mut groups: HashMap<String, ExtensionStats> = HashMap::new();
+    for (entry, source) in entries.iter().zip(shared_data_sources(entries)) {
+        let extension = entry_extension(entry.name());
+        let stats = groups
+            .entry(extension.clone())
+            .or_insert_with(|| ExtensionStats {
+                extension,
+                ..Default::default()
+            });
+        stats.count += 1;
+        if entry.is_compressed() {
+            stats.compressed_count += 1;
+            stats.lzss_original_size += entry.original_size() as u64;
+            stats.lzss_compressed_size += entry.compressed_size() as u64;
+        }
+        stats.original_size += entry.original_size() as u64;
+        if source.is_none() {
+            stats.compressed_size += entry.compressed_size() as u64;
+        }
+    }
+
+    let mut stats: Vec<_> = groups.into_values().collect();
+    stats.sort_by(|a, b| {
+        b.original_size
+            .cmp(&a.original_size)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    stats
+}
+
+/// Totals over all entries of an archive.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveTotals {
+    pub count: usize,
+    /// Number of entries stored LZSS compressed.
+    pub compressed_count: usize,
+    pub original_size: u64,
+    /// Stored bytes. Data blocks shared by several entries count once.
+    pub compressed_size: u64,
+}
+
+impl ArchiveTotals {
+    /// Number of entries stored uncompressed.
+    pub fn stored_count(&self) -> usize {
+        self.count - self.compressed_count
+    }
+
+    /// Compressed size relative to the original size (1.0 means no gain).
+    pub fn ratio(&self) -> f64 {
+        if self.original_size == 0 {
+            1.0
+        } else {
+            self.compressed_size as f64 / self.original_size as f64
+        }
+    }
+}
+
+/// Sums up the per-extension statistics of `entries`.
+pub fn archive_totals(entries: &[ArcEntry]) -> ArchiveTotals {
+    extension_stats(entries)
+        .into_iter()
+        .fold(ArchiveTotals::default(), |totals, stats| ArchiveTotals {
+            count: totals.count + stats.count,
+            compressed_count: totals.compressed_count + stats.compressed_count,
+            original_size: totals.original_size + stats.original_size,
+            compressed_size: totals.compressed_size + stats.compressed_size,
+        })
+}
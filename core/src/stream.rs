@@ -0,0 +1,151 @@
+use std::io::{self, BufRead, BufReader, Read, Take};
+
+use crate::reader::ArcEntry;
+
+// --- Streaming Entry Reader ---
+// Decodes an entry while it is read, keeping only the 4 KiB LZSS window
+// instead of the whole entry. The decoder follows the bit stream of the
+// lzss crate with the Silky parameters (see `SilkyLzss`): a 1 bit
+// followed by a literal byte, or a 0 bit followed by a 12-bit window
+// position and a 4-bit length, which copies length + 2 bytes.
+
+const WINDOW_SIZE: usize = 1 << 12;
+/// Position the first decoded byte is stored at: N - F with F = 16 + 1.
+const WINDOW_START: usize = WINDOW_SIZE - 17;
+const MIN_MATCH: usize = 2;
+
+/// Incremental decoder state.
+#[derive(Debug)]
+struct Decoder {
+    window: Box<[u8; WINDOW_SIZE]>,
+    position: usize,
+    bits: u32,
+    bit_count: u8,
+    // Back reference still being copied
+    copy_from: usize,
+    copy_left: usize,
+}
+
+impl Decoder {
+    fn new() -> Self {
+        Self {
+            window: Box::new([0; WINDOW_SIZE]),
+            position: WINDOW_START,
+            bits: 0,
+            bit_count: 0,
+            copy_from: 0,
+            copy_left: 0,
+        }
+    }
+
+    /// The next `count` bits of `input`, or `None` at its end.
+    fn read_bits(&mut self, input: &mut impl BufRead, count: u8) -> io::Result<Option<u32>> {
+        while self.bit_count < count {
+            let Some(&byte) = input.fill_buf()?.first() else {
+                return Ok(None);
+            };
+            input.consume(1);
+            self.bits = (self.bits << 8) | byte as u32;
+            self.bit_count += 8;
+        }
+        self.bit_count -= count;
+        Ok(Some((self.bits >> self.bit_count) & ((1 << count) - 1)))
+    }
+
+    fn push(&mut self, byte: u8) -> u8 {
+        self.window[self.position] = byte;
+        self.position = (self.position + 1) % WINDOW_SIZE;
+        byte
+    }
+
+    /// The next decoded byte, or `None` once `input` is exhausted.
+    fn next_byte(&mut self, input: &mut impl BufRead) -> io::Result<Option<u8>> {
+        if self.copy_left == 0 {
+            let Some(token) = self.read_bits(input, 9)? else {
+                return Ok(None);
+            };
+            if token & 0x100 != 0 {
+                return Ok(Some(self.push(token as u8)));
+            }
+            let Some(low) = self.read_bits(input, 8)? else {
+                return Ok(None);
+            };
+            let reference = (token << 8) | low;
+            self.copy_from = (reference >> 4) as usize;
+            self.copy_left = (reference & 0xf) as usize + MIN_MATCH;
+        }
+        let byte = self.window[self.copy_from % WINDOW_SIZE];
+        self.copy_from += 1;
+        self.copy_left -= 1;
+        Ok(Some(self.push(byte)))
+    }
+}
+
+/// Reads the data of one entry, decompressing it on the fly. Created by
+/// [`ArcReader::entry_reader`](crate::reader::ArcReader::entry_reader).
+#[derive(Debug)]
+pub struct EntryReader<R> {
+    name: String,
+    input: BufReader<Take<R>>,
+    // `None` for stored entries
+    decoder: Option<Decoder>,
+    finished: bool,
+}
+
+impl<R: Read> EntryReader<R> {
+    /// A reader for `entry`, with `reader` positioned at its data block.
+    pub(crate) fn new(reader: R, entry: &ArcEntry) -> Self {
+        let compressed = entry.is_compressed();
+        Self {
+            name: entry.name().to_string(),
+            input: BufReader::new(reader.take(entry.compressed_size() as u64)),
+            decoder: compressed.then(Decoder::new),
+            finished: false,
+        }
+    }
+
+    /// Fails if the archive ended before the whole data block was read.
+    fn check_complete(&mut self) -> io::Result<()> {
+        self.finished = true;
+        let missing = self.input.get_ref().limit();
+        if missing > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "data of {:?} is truncated: {missing} bytes missing",
+                    self.name
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for EntryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.finished || buf.is_empty() {
+            return Ok(0);
+        }
+        let Some(decoder) = &mut self.decoder else {
+            let read = self.input.read(buf)?;
+            if read == 0 {
+                self.check_complete()?;
+            }
+            return Ok(read);
+        };
+        let mut filled = 0;
+        while filled < buf.len() {
+            match decoder.next_byte(&mut self.input)? {
+                Some(byte) => {
+                    buf[filled] = byte;
+                    filled += 1;
+                }
+                None => break,
+            }
+        }
+        if filled == 0 {
+            self.check_complete()?;
+        }
+        Ok(filled)
+    }
+}
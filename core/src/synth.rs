@@ -0,0 +1,214 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    HEADER_SIZE, PackFileInfo, assign_offsets, compress_data, encrypt_name,
+    error::{ArcError, PathContext},
+    format::{self, EntryRecord},
+    memory::SpillFile,
+    name,
+};
+
+// --- Synthetic Archives ---
+// Generates valid archives from a seed, for tests that should not depend on
+// real game data. The same options always produce byte-identical output.
+
+/// Parameters of a generated archive.
+#[derive(Debug, Clone)]
+pub struct SynthOptions {
+    pub seed: u64,
+    pub entries: usize,
+    /// Inclusive range of entry data sizes in bytes.
+    pub size_range: (usize, usize),
+    /// Share of data bytes that repeat earlier data, from 0.0 (random) to 1.0
+    /// (one repeated byte).
+    pub compressibility: f64,
+    /// LZSS compress entries where that makes them smaller.
+    pub compress: bool,
+    /// Mix in names that stress the name codec: CP932 text, nested
+    /// directories, ambiguous code points and names of the maximum length.
+    pub name_edge_cases: bool,
+}
+
+impl Default for SynthOptions {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            entries: 16,
+            size_range: (0, 4096),
+            compressibility: 0.5,
+            compress: true,
+            name_edge_cases: true,
+        }
+    }
+}
+
+/// An entry of a generated archive with its original data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SynthEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SynthArchive {
+    /// The complete archive file.
+    pub bytes: Vec<u8>,
+    /// Entries in metadata order.
+    pub entries: Vec<SynthEntry>,
+}
+
+impl SynthArchive {
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<(), ArcError> {
+        let path = path.as_ref();
+        fs::write(path, &self.bytes).with_path(path)
+    }
+
+    /// Writes the entries as files below `dir`, as unpacking would.
+    pub fn write_files(&self, dir: impl AsRef<Path>) -> Result<(), ArcError> {
+        for entry in &self.entries {
+            let path = dir.as_ref().join(name::entry_name_to_path(&entry.name));
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).with_path(parent)?;
+            }
+            fs::write(&path, &entry.data).with_path(&path)?;
+        }
+        Ok(())
+    }
+}
+
+// SplitMix64, small and good enough for test data
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+const EDGE_CASE_NAMES: &[&str] = &[
+    "ボイス\\キャラ01.ogg",
+    "bgm～loop.OGG",
+    "dir\\sub dir\\file with spaces.txt",
+    "ｶﾀｶﾅ.dat",
+    "noext",
+    "UPPER.TXT",
+];
+
+fn entry_name(rng: &mut Rng, index: usize, edge_cases: bool) -> String {
+    if edge_cases {
+        match rng.below(4) {
+            0 => {
+                let base = EDGE_CASE_NAMES[rng.below(EDGE_CASE_NAMES.len())];
+                return format!("{index:04}_{base}");
+            }
+            1 => {
+                // Pad to exactly the longest name the length field allows
+                let name = format!("{index:04}_");
+                let padding = name::MAX_NAME_LEN - name.len() - ".bin".len();
+                return format!("{name}{}.bin", "x".repeat(padding));
+            }
+            _ => {}
+        }
+    }
+    format!("{index:04}_{:08x}.bin", rng.next() as u32)
+}
+
+fn entry_data(rng: &mut Rng, options: &SynthOptions) -> Vec<u8> {
+    let (min, max) = options.size_range;
+    let len = min + rng.below(max.saturating_sub(min) + 1);
+    let mut data = Vec::with_capacity(len);
+    for _ in 0..len {
+        let byte = match data.last() {
+            Some(&last) if rng.chance(options.compressibility) => last,
+            _ => rng.next() as u8,
+        };
+        data.push(byte);
+    }
+    data
+}
+
+/// Generates an archive according to `options`.
+pub fn generate_archive(options: &SynthOptions) -> Result<SynthArchive, ArcError> {
+    let mut rng = Rng(options.seed);
+    let mut entries = Vec::with_capacity(options.entries);
+    let mut records = Vec::with_capacity(options.entries);
+    let mut blocks = Vec::with_capacity(options.entries);
+    for index in 0..options.entries {
+        let name = entry_name(&mut rng, index, options.name_edge_cases);
+        let data = entry_data(&mut rng, options);
+        let block = match options.compress && !data.is_empty() {
+            true => Some(compress_data(&data)?).filter(|c| c.len() < data.len()),
+            false => None,
+        }
+        .unwrap_or_else(|| data.clone());
+        records.push(EntryRecord {
+            encrypted_name: encrypt_name(&name)?,
+            compressed_size: block.len() as u32,
+            original_size: data.len() as u32,
+            offset: 0,
+        });
+        blocks.push(block);
+        entries.push(SynthEntry { name, data });
+    }
+
+    let metadata_size: usize = records.iter().map(EntryRecord::encoded_len).sum();
+    let mut offset = HEADER_SIZE + metadata_size as u32;
+    for (record, block) in records.iter_mut().zip(&blocks) {
+        record.offset = offset;
+        offset += block.len() as u32;
+    }
+
+    let mut bytes = Vec::with_capacity(offset as usize);
+    format::write_header(&mut bytes, metadata_size as u32);
+    for record in &records {
+        format::write_entry(&mut bytes, record)?;
+    }
+    for block in blocks {
+        bytes.extend_from_slice(&block);
+    }
+    Ok(SynthArchive { bytes, entries })
+}
+
+/// Lays out entries with the given stored sizes the way packing does,
+/// without creating their data, and returns the metadata size. Lets tests
+/// reach the 4 GiB offset limit.
+pub fn layout_sizes(sizes: &[u32]) -> Result<u32, ArcError> {
+    let spill = Arc::new(SpillFile::new(None)?);
+    let mut files: Vec<PackFileInfo> = sizes
+        .iter()
+        .enumerate()
+        .map(|(index, &size)| {
+            let name = format!("{index}.bin");
+            Ok(PackFileInfo {
+                relative_path: PathBuf::from(&name),
+                encrypted_name: encrypt_name(&name)?,
+                name,
+                full_path: PathBuf::new(),
+                original_size: size,
+                compressed_data: None,
+                compressed_size: size,
+                offset: 0,
+                source_digest: None,
+                spilled: Some((spill.clone(), 0, size)),
+            })
+        })
+        .collect::<Result<_, ArcError>>()?;
+    let shared = vec![None; files.len()];
+    assign_offsets(&mut files, &shared)
+}
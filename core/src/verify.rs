@@ -0,0 +1,80 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use log::info;
+use rayon::prelude::*;
+
+use crate::{
+    HEADER_SIZE,
+    error::{ArcError, PathContext},
+    reader::{self, ArcEntry, ArcReader},
+};
+
+// --- Integrity Check ---
+// Checks every entry of a well-formed archive on its own and reports a
+// result per entry. Archives whose metadata cannot even be parsed are the
+// job of `doctor`.
+
+/// Result of checking one entry.
+#[derive(Debug, Clone)]
+pub struct EntryCheck {
+    pub entry: ArcEntry,
+    /// What is wrong with the entry, `None` if it passed.
+    pub problem: Option<String>,
+}
+
+impl EntryCheck {
+    pub fn passed(&self) -> bool {
+        self.problem.is_none()
+    }
+}
+
+/// Checks that every entry's data block lies inside the data section and
+/// decodes to exactly `original_size` bytes. Results are in metadata order.
+pub fn verify_archive(archive_path: impl AsRef<Path>) -> Result<Vec<EntryCheck>, ArcError> {
+    let archive_path = archive_path.as_ref();
+    info!("Verifying {:?}", archive_path);
+    let archive = ArcReader::open(archive_path)?;
+    let file_len = archive_path.metadata().with_path(archive_path)?.len();
+    let data_start = HEADER_SIZE as u64 + archive.metadata_size() as u64;
+
+    let checks = archive
+        .entries()
+        .par_iter()
+        .map(|entry| -> Result<EntryCheck, ArcError> {
+            let start = entry.offset() as u64;
+            let end = start + entry.compressed_size() as u64;
+            let problem = if start < data_start {
+                Some(format!(
+                    "data starts inside the metadata (before {data_start})"
+                ))
+            } else if end > file_len {
+                Some(format!(
+                    "data ends at {end}, past the end of the file ({file_len})"
+                ))
+            } else {
+                let mut task_reader =
+                    BufReader::new(File::open(archive_path).with_path(archive_path)?);
+                let raw_data = reader::read_raw_entry_data(&mut task_reader, entry)?;
+                match reader::decode_entry_data(entry, raw_data, None) {
+                    Ok(data) if data.len() == entry.original_size() as usize => None,
+                    Ok(data) => Some(format!(
+                        "decompresses to {} bytes, expected {}",
+                        data.len(),
+                        entry.original_size()
+                    )),
+                    Err(e) => Some(format!("fails to decompress: {e}")),
+                }
+            };
+            Ok(EntryCheck {
+                entry: entry.clone(),
+                problem,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    info!(
+        "{} of {} entries passed.",
+        checks.iter().filter(|check| check.passed()).count(),
+        checks.len()
+    );
+    Ok(checks)
+}
@@ -0,0 +1,75 @@
+use std::io::Cursor;
+
+use js_sys::{Array, Map, Uint8Array};
+use wasm_bindgen::{JsCast, JsError, prelude::wasm_bindgen};
+
+use crate::{PackOptions, error::ArcError, reader::ArcReader, writer::ArcWriter};
+
+// --- WebAssembly Bindings ---
+// `wasm-bindgen` exports for browsers, where there is no file system to
+// pack from or unpack to: archives and entries are passed as byte arrays.
+// The exports convert between JS values and the plain functions below,
+// which hold the actual work.
+
+/// Builds an archive in memory from `(name, data)` pairs, in that order.
+/// Entries are compressed with LZSS if `compress` is set and that makes
+/// them smaller.
+pub fn pack_entries(
+    entries: impl IntoIterator<Item = (String, Vec<u8>)>,
+    compress: bool,
+) -> Result<Vec<u8>, ArcError> {
+    let mut writer = ArcWriter::with_options(
+        "",
+        PackOptions {
+            compress,
+            ..Default::default()
+        },
+    );
+    for (name, data) in entries {
+        writer.add_file(&name, data.as_slice(), compress)?;
+    }
+    let mut archive = Cursor::new(Vec::new());
+    writer.finish_to(&mut archive)?;
+    Ok(archive.into_inner())
+}
+
+/// The decompressed entries of the archive `data`, in metadata order.
+pub fn unpack_entries(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, ArcError> {
+    let mut archive = ArcReader::new(Cursor::new(data))?;
+    let entries = archive.entries().to_vec();
+    entries
+        .iter()
+        .map(|entry| Ok((entry.name().to_string(), archive.read_entry(entry)?)))
+        .collect()
+}
+
+/// `pack(files: Map<string, Uint8Array>, compress: boolean): Uint8Array`
+#[wasm_bindgen(js_name = pack)]
+pub fn pack_js(files: &Map, compress: bool) -> Result<Vec<u8>, JsError> {
+    let mut entries = Vec::with_capacity(files.size() as usize);
+    for pair in files.entries() {
+        let pair: Array = pair
+            .map_err(|_| JsError::new("cannot iterate the files"))?
+            .unchecked_into();
+        let name = pair
+            .get(0)
+            .as_string()
+            .ok_or_else(|| JsError::new("file names must be strings"))?;
+        let data = pair
+            .get(1)
+            .dyn_into::<Uint8Array>()
+            .map_err(|_| JsError::new(&format!("{name:?} is not a Uint8Array")))?;
+        entries.push((name, data.to_vec()));
+    }
+    Ok(pack_entries(entries, compress)?)
+}
+
+/// `unpack(archive: Uint8Array): [string, Uint8Array][]`, which
+/// `new Map(...)` turns into a map.
+#[wasm_bindgen(js_name = unpack)]
+pub fn unpack_js(archive: &[u8]) -> Result<Array, JsError> {
+    Ok(unpack_entries(archive)?
+        .into_iter()
+        .map(|(name, data)| Array::of2(&name.into(), &Uint8Array::from(data.as_slice())))
+        .collect())
+}
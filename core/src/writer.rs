@@ -0,0 +1,121 @@
+use std::{
+    collections::HashSet,
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+
+use crate::{
+    PackFileInfo, PackOptions, PackSummary, add_provenance, error::ArcError, process_pack_files,
+    selection::normalize_entry_name, write_pack_files, write_pack_files_to,
+};
+
+// --- Archive Writer ---
+// Builds an archive from data supplied by the caller instead of a directory.
+// The metadata section comes first and needs every entry's final size, so
+// entries are compressed as they are added and kept in memory until
+// `finish` writes the archive (through a spool file, like packing).
+
+/// Creates an archive entry by entry.
+#[derive(Debug)]
+pub struct ArcWriter {
+    output_path: PathBuf,
+    options: PackOptions,
+    files: Vec<PackFileInfo>,
+    names: HashSet<String>,
+}
+
+impl ArcWriter {
+    /// A writer for an archive at `output_path`, which is only created by
+    /// [`ArcWriter::finish`].
+    pub fn create(output_path: impl AsRef<Path>) -> Self {
+        Self::with_options(output_path, PackOptions::default())
+    }
+
+    /// Like [`ArcWriter::create`]. `options` supply the compression
+    /// settings, name fallbacks, deduplication and spool directory;
+    /// `compress` is chosen per entry instead.
+    pub fn with_options(output_path: impl AsRef<Path>, options: PackOptions) -> Self {
+        Self {
+            output_path: output_path.as_ref().to_path_buf(),
+            options,
+            files: Vec::new(),
+            names: HashSet::new(),
+        }
+    }
+
+    /// Reads `reader` to the end and adds it as entry `name` (`\` or `/`
+    /// separated), compressed with LZSS if `compress` is set and that makes
+    /// it smaller. Entries are written in the order they are added.
+    pub fn add_file(
+        &mut self,
+        name: &str,
+        mut reader: impl Read,
+        compress: bool,
+    ) -> Result<(), ArcError> {
+        let (entry_name, encrypted_name) = self.options.encode_name(&normalize_entry_name(name))?;
+        if self.names.contains(&entry_name) {
+            return Err(ArcError::DuplicateEntry(entry_name));
+        }
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let original_size = u32::try_from(data.len()).map_err(|_| {
+            ArcError::InvalidFormat(format!(
+                "{entry_name:?} is {} bytes, entries are limited to 4 GiB",
+                data.len()
+            ))
+        })?;
+        debug!("Adding {:?} ({} bytes)", entry_name, original_size);
+        let mut file = [PackFileInfo {
+            relative_path: PathBuf::from(&entry_name),
+            name: entry_name.clone(),
+            full_path: PathBuf::new(),
+            encrypted_name,
+            original_size,
+            compressed_data: Some(data),
+            compressed_size: original_size,
+            offset: 0,
+            source_digest: None,
+            spilled: None,
+        }];
+        // The data is in memory already, and spilling it entry by entry
+        // would take a file each
+        let options = PackOptions {
+            compress,
+            memory_limit: None,
+            ..self.options.clone()
+        };
+        let metrics = self.options.metrics.clone().unwrap_or_default();
+        process_pack_files(&mut file, &options, &metrics)?;
+        let [file] = file;
+        self.names.insert(entry_name);
+        self.files.push(file);
+        Ok(())
+    }
+
+    /// Number of entries added so far.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Writes the archive, with a provenance entry if the options ask for
+    /// one.
+    pub fn finish(mut self) -> Result<PackSummary, ArcError> {
+        add_provenance(&mut self.files, &self.options)?;
+        let metrics = self.options.metrics.clone().unwrap_or_default();
+        write_pack_files(self.files, &self.output_path, &self.options, &metrics)
+    }
+
+    /// Like [`ArcWriter::finish`], but writes the archive to `writer`
+    /// instead of the output path, which is not touched.
+    pub fn finish_to<W: Write + Seek>(mut self, writer: W) -> Result<PackSummary, ArcError> {
+        add_provenance(&mut self.files, &self.options)?;
+        let metrics = self.options.metrics.clone().unwrap_or_default();
+        write_pack_files_to(self.files, writer, &self.options, &metrics)
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use log::{debug, info};
+use zip::ZipArchive;
+
+use crate::{
+    PackFileInfo, PackOptions, PackSummary, add_provenance,
+    error::{ArcError, PathContext, warn_or_fail},
+    name, process_pack_files, write_pack_files,
+};
+
+// --- Packing From Zip ---
+
+/// Packs the files of the zip at `zip_path` into an archive at
+/// `output_path`, keeping the zip's entry order. Entry data is read into
+/// memory instead of being extracted first; names get `\` separators and
+/// the usual CP932 checks.
+pub fn pack_zip(
+    zip_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    let zip_path = zip_path.as_ref();
+    let output_path = output_path.as_ref();
+    options
+        .parallelism
+        .install(|| pack_zip_impl(zip_path, output_path, options))
+}
+
+fn pack_zip_impl(
+    zip_path: &Path,
+    output_path: &Path,
+    options: &PackOptions,
+) -> Result<PackSummary, ArcError> {
+    info!("Starting pack of zip: {:?}", zip_path);
+    info!("Output archive: {:?}", output_path);
+    if !zip_path.is_file() {
+        return Err(ArcError::NotFound(zip_path.to_path_buf()));
+    }
+    let mut zip = ZipArchive::new(BufReader::new(File::open(zip_path).with_path(zip_path)?))?;
+
+    let mut files_to_pack = read_zip_files(&mut zip, options)?;
+    if files_to_pack.is_empty() && !options.allow_empty {
+        return Err(ArcError::EmptyInput(zip_path.to_path_buf()));
+    }
+
+    let metrics = options.metrics.clone().unwrap_or_default();
+    process_pack_files(&mut files_to_pack, options, &metrics)?;
+    add_provenance(&mut files_to_pack, options)?;
+    write_pack_files(files_to_pack, output_path, options, &metrics)
+}
+
+/// Reads every file entry of `zip` into memory, with directory entries kept
+/// as placeholders only if they are empty and `keep_empty_dirs` is set.
+fn read_zip_files<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    options: &PackOptions,
+) -> Result<Vec<PackFileInfo>, ArcError> {
+    let zip_names: Vec<String> = (0..zip.len())
+        .map(|index| Ok(zip_entry_name(&zip.by_index_raw(index)?.name()?)))
+        .collect::<Result<_, ArcError>>()?;
+    let parents: HashSet<&str> = zip_names
+        .iter()
+        .flat_map(|zip_name| {
+            zip_name
+                .trim_end_matches(name::SEPARATOR)
+                .rmatch_indices(name::SEPARATOR)
+                .map(|(end, _)| &zip_name[..end])
+        })
+        .collect();
+
+    let mut files = Vec::new();
+    for (index, zip_name) in zip_names.iter().enumerate() {
+        let mut file = zip.by_index(index)?;
+        if file.is_dir() {
+            let dir_name = zip_name.trim_end_matches(name::SEPARATOR);
+            if options.keep_empty_dirs && !dir_name.is_empty() && !parents.contains(dir_name) {
+                let (mut name, _) = options.encode_name(dir_name)?;
+                name.push(name::SEPARATOR);
+                debug!("Keeping empty directory {:?}", dir_name);
+                files.push(inline_file(name, Vec::new(), options)?);
+            }
+            continue;
+        }
+        let (name, _) = options.encode_name(zip_name)?;
+        if name != *zip_name {
+            warn_or_fail(
+                options.strict,
+                format!("{zip_name:?} contains ambiguous characters, storing it as {name:?}"),
+            )?;
+        }
+        if options.replaces_with_provenance(&name) {
+            debug!("Replacing {:?} with fresh provenance", zip_name);
+            continue;
+        }
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+        files.push(inline_file(name, data, options)?);
+    }
+    Ok(files)
+}
+
+/// A zip entry name with `\` separators and without a leading separator.
+fn zip_entry_name(raw: &str) -> String {
+    raw.replace('/', "\\")
+        .trim_start_matches(name::SEPARATOR)
+        .to_string()
+}
+
+/// An entry whose source data is already in memory.
+fn inline_file(
+    name: String,
+    data: Vec<u8>,
+    options: &PackOptions,
+) -> Result<PackFileInfo, ArcError> {
+    let encrypted_name = name::encrypt_with(&name, options.codec())?;
+    Ok(PackFileInfo {
+        relative_path: PathBuf::from(&name),
+        name,
+        full_path: PathBuf::new(),
+        encrypted_name,
+        original_size: data.len() as u32,
+        compressed_data: Some(data),
+        compressed_size: 0,
+        offset: 0,
+        source_digest: None,
+        spilled: None,
+    })
+}
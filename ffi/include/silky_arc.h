@@ -0,0 +1,34 @@
+/* C interface of silky-arc-core, built with `cargo build -p silky-arc-ffi
+ * --release`. All strings are NUL-terminated UTF-8. */
+#ifndef SILKY_ARC_H
+#define SILKY_ARC_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Packs the directory input_dir into the archive output, compressing
+ * entries with LZSS if compress is non-zero. Returns 0 on success, -1 on
+ * failure. */
+int silky_arc_pack(const char *input_dir, const char *output, int compress);
+
+/* Unpacks the archive input into the directory output_dir. Returns 0 on
+ * success, -1 on failure. */
+int silky_arc_unpack(const char *input, const char *output_dir);
+
+/* The entries of the archive input as JSON, or NULL on failure. Release the
+ * string with silky_arc_free_string. */
+char *silky_arc_list_json(const char *input);
+
+/* Releases a string returned by this library. NULL is ignored. */
+void silky_arc_free_string(char *s);
+
+/* Message of the last failure on the calling thread, or NULL. Owned by the
+ * library and valid until the next failing call on the same thread. */
+const char *silky_arc_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,169 @@
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    ptr,
+};
+
+use serde_json::json;
+use silky_arc_core::{error::ArcError, handle_pack, handle_unpack, reader::ArcReader};
+
+// --- C Interface ---
+// `extern "C"` entry points for tools written in other languages, built into
+// this crate's `cdylib` (see `include/silky_arc.h`). Strings
+// are NUL-terminated UTF-8. Functions report failure through their return
+// value; the message is kept per thread for `silky_arc_last_error`. Panics
+// are caught at the boundary and reported like errors.
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // Interior NULs would cut the message short, so drop them
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// Runs `f`, storing its error (or panic) as the last error.
+fn guard<T>(f: impl FnOnce() -> Result<T, ArcError>) -> Option<T> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            None
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("panic: {message}"));
+            None
+        }
+    }
+}
+
+/// The path in the C string `s`.
+///
+/// # Safety
+/// `s` must be null or point to a NUL-terminated string.
+unsafe fn path_arg(s: *const c_char, what: &str) -> Result<PathBuf, ArcError> {
+    if s.is_null() {
+        return Err(ArcError::InvalidFormat(format!("{what} is null")));
+    }
+    // SAFETY: guaranteed by the caller
+    let s = unsafe { CStr::from_ptr(s) };
+    s.to_str()
+        .map(PathBuf::from)
+        .map_err(|_| ArcError::InvalidFormat(format!("{what} is not valid UTF-8")))
+}
+
+/// Packs the directory `input_dir` into the archive `output`, compressing
+/// entries with LZSS if `compress` is non-zero. Returns 0 on success and -1
+/// on failure.
+///
+/// # Safety
+/// Both arguments must be NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn silky_arc_pack(
+    input_dir: *const c_char,
+    output: *const c_char,
+    compress: c_int,
+) -> c_int {
+    let result = guard(|| {
+        // SAFETY: guaranteed by the caller
+        let (input_dir, output) = unsafe {
+            (
+                path_arg(input_dir, "input_dir")?,
+                path_arg(output, "output")?,
+            )
+        };
+        handle_pack(input_dir, output, compress != 0).map(drop)
+    });
+    if result.is_some() { 0 } else { -1 }
+}
+
+/// Unpacks the archive `input` into the directory `output_dir`. Returns 0
+/// on success and -1 on failure.
+///
+/// # Safety
+/// Both arguments must be NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn silky_arc_unpack(
+    input: *const c_char,
+    output_dir: *const c_char,
+) -> c_int {
+    let result = guard(|| {
+        // SAFETY: guaranteed by the caller
+        let (input, output_dir) = unsafe {
+            (
+                path_arg(input, "input")?,
+                path_arg(output_dir, "output_dir")?,
+            )
+        };
+        handle_unpack(input, output_dir)
+    });
+    if result.is_some() { 0 } else { -1 }
+}
+
+/// The entries of the archive `input` as JSON, in the format of
+/// `list --json` (without the shared data sources). Returns null on
+/// failure; the string must be released with [`silky_arc_free_string`].
+///
+/// # Safety
+/// `input` must be a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn silky_arc_list_json(input: *const c_char) -> *mut c_char {
+    let result = guard(|| {
+        // SAFETY: guaranteed by the caller
+        let input = unsafe { path_arg(input, "input")? };
+        let archive = ArcReader::open(input)?;
+        let entries: Vec<_> = archive
+            .entries()
+            .iter()
+            .map(|entry| {
+                json!({
+                    "name": entry.name(),
+                    "offset": entry.offset(),
+                    "compressed_size": entry.compressed_size(),
+                    "original_size": entry.original_size(),
+                    "compressed": entry.is_compressed(),
+                })
+            })
+            .collect();
+        let document = json!({
+            "schema_version": 1,
+            "metadata_size": archive.metadata_size(),
+            "name_encoding": archive.name_encoding().to_string(),
+            "entries": entries,
+        });
+        // Names cannot contain NUL, and JSON escapes every other control
+        // character
+        Ok(CString::new(document.to_string()).expect("JSON without NUL"))
+    });
+    result.map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Releases a string returned by this library. Null is ignored.
+///
+/// # Safety
+/// `s` must be null or a string returned by this library that was not
+/// released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn silky_arc_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: `s` came from `CString::into_raw`, see above
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// The message of the last failure on this thread, or null if nothing
+/// failed yet. The string belongs to the library and stays valid until the
+/// next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn silky_arc_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
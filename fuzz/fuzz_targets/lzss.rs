@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use silky_arc_core::{compress_data, decompress_data};
+
+fuzz_target!(|input: (u32, &[u8])| {
+    let (original_size, data) = input;
+    _ = decompress_data(data, original_size);
+
+    let compressed = compress_data(data).unwrap();
+    assert_eq!(
+        decompress_data(&compressed, data.len() as u32).unwrap(),
+        data
+    );
+});
@@ -0,0 +1,24 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+use silky_arc_core::{format, reader::ArcReader};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(records) = format::parse_metadata(data) {
+        // Whatever parses must serialize back to the same bytes
+        let mut written = Vec::new();
+        for record in &records {
+            format::write_entry(&mut written, record).unwrap();
+        }
+        assert_eq!(written, data);
+    }
+
+    // The full reader must reject, not panic on, any header and layout
+    if let Ok(mut archive) = ArcReader::new(Cursor::new(data)) {
+        for entry in archive.entries().to_vec() {
+            _ = archive.read_entry(&entry);
+        }
+    }
+});
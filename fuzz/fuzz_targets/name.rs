@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use silky_arc_core::{
+    decrypt_name, encrypt_name,
+    name::{self, NameEncoding},
+};
+
+fuzz_target!(|input: (&[u8], NameEncoding)| {
+    let (encrypted, encoding) = input;
+    _ = name::decrypt_name_as(encrypted, encoding);
+    if let Ok(name) = decrypt_name(encrypted)
+        && let Ok(reencrypted) = encrypt_name(&name)
+    {
+        // CP932 has duplicate codes, so only the decoded name round trips
+        assert_eq!(decrypt_name(&reencrypted).unwrap(), name);
+    }
+    _ = name::sanitize_entry_name(&String::from_utf8_lossy(encrypted), 0);
+});
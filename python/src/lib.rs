@@ -0,0 +1,156 @@
+//! Python module `silky_arc`, built with `maturin build --release` in this
+//! directory. Paths are `str` or `os.PathLike`; failures raise
+//! `silky_arc.ArcError` (or `FileNotFoundError` for missing inputs).
+
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyFileNotFoundError, PyKeyError},
+    prelude::*,
+    types::PyBytes,
+};
+use silky_arc_core::{
+    error::{self, PathContext},
+    handle_pack, handle_unpack,
+    reader::{ArcEntry, ArcReader},
+};
+
+create_exception!(
+    silky_arc,
+    ArcError,
+    PyException,
+    "Failure reading or writing an archive."
+);
+
+fn to_py_err(e: error::ArcError) -> PyErr {
+    match e {
+        error::ArcError::NotFound(path) => PyFileNotFoundError::new_err(path.display().to_string()),
+        e => ArcError::new_err(e.to_string()),
+    }
+}
+
+/// A metadata entry of an archive.
+#[pyclass(frozen, get_all, module = "silky_arc")]
+#[derive(Clone)]
+struct Entry {
+    /// Entry name, using `\` as separator.
+    name: String,
+    offset: u32,
+    compressed_size: u32,
+    original_size: u32,
+}
+
+#[pymethods]
+impl Entry {
+    /// Whether the data is stored LZSS compressed.
+    #[getter]
+    fn compressed(&self) -> bool {
+        self.compressed_size != self.original_size
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Entry(name={:?}, offset={}, compressed_size={}, original_size={})",
+            self.name, self.offset, self.compressed_size, self.original_size
+        )
+    }
+}
+
+impl From<&ArcEntry> for Entry {
+    fn from(entry: &ArcEntry) -> Self {
+        Self {
+            name: entry.name().to_string(),
+            offset: entry.offset(),
+            compressed_size: entry.compressed_size(),
+            original_size: entry.original_size(),
+        }
+    }
+}
+
+/// An open archive, reading entries on demand.
+#[pyclass(module = "silky_arc")]
+struct Archive {
+    reader: ArcReader<BufReader<File>>,
+}
+
+#[pymethods]
+impl Archive {
+    #[new]
+    fn new(path: PathBuf) -> PyResult<Self> {
+        let reader = ArcReader::open(path).map_err(to_py_err)?;
+        Ok(Self { reader })
+    }
+
+    /// Entries in metadata order.
+    fn entries(&self) -> Vec<Entry> {
+        self.reader.entries().iter().map(Entry::from).collect()
+    }
+
+    /// The decompressed data of entry `name`. Raises `KeyError` if there is
+    /// no such entry.
+    fn read<'py>(&mut self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyBytes>> {
+        let entry = self
+            .reader
+            .get(name)
+            .cloned()
+            .ok_or_else(|| PyKeyError::new_err(name.to_string()))?;
+        let data = py
+            .allow_threads(|| self.reader.read_entry(&entry))
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Writes entry `name` to the file `path`, decompressing while copying,
+    /// and returns the number of bytes written.
+    fn extract(&mut self, py: Python<'_>, name: &str, path: PathBuf) -> PyResult<u64> {
+        py.allow_threads(|| {
+            let mut file = File::create(&path).with_path(&path)?;
+            self.reader.extract_entry_to(name, &mut file)
+        })
+        .map_err(to_py_err)
+    }
+
+    fn __len__(&self) -> usize {
+        self.reader.entries().len()
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.reader.contains(name)
+    }
+}
+
+/// Unpacks the archive `archive` into the directory `output_dir`.
+#[pyfunction]
+fn unpack(py: Python<'_>, archive: PathBuf, output_dir: PathBuf) -> PyResult<()> {
+    py.allow_threads(|| handle_unpack(archive, output_dir))
+        .map_err(to_py_err)
+}
+
+/// Packs the directory `input_dir` into the archive `output`, compressing
+/// entries with LZSS if `compress` is set. Returns the number of entries.
+#[pyfunction]
+#[pyo3(signature = (input_dir, output, compress = false))]
+fn pack(py: Python<'_>, input_dir: PathBuf, output: PathBuf, compress: bool) -> PyResult<usize> {
+    py.allow_threads(|| handle_pack(input_dir, output, compress))
+        .map(|summary| summary.entry_count())
+        .map_err(to_py_err)
+}
+
+/// The entries of the archive `archive`, without reading their data.
+#[pyfunction]
+fn list(archive: PathBuf) -> PyResult<Vec<Entry>> {
+    let reader = ArcReader::open(archive).map_err(to_py_err)?;
+    Ok(reader.entries().iter().map(Entry::from).collect())
+}
+
+#[pymodule]
+fn silky_arc(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("ArcError", m.py().get_type::<ArcError>())?;
+    m.add_class::<Archive>()?;
+    m.add_class::<Entry>()?;
+    m.add_function(wrap_pyfunction!(unpack, m)?)?;
+    m.add_function(wrap_pyfunction!(pack, m)?)?;
+    m.add_function(wrap_pyfunction!(list, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+
+use crate::error::{ArcError, PathContext};
+
+// --- Backup Logic ---
+// Archives are frequently the only copy of the original game data, so every
+// operation that overwrites an existing archive backs it up first. Existing
+// backups are never overwritten: the first backup is usually the pristine
+// original, so later ones get a numbered suffix instead.
+
+/// Copies `archive` to `<name>.bak` next to it (or into `backup_dir` when
+/// given) and returns the path of the created backup.
+pub fn backup_archive(
+    archive: impl AsRef<Path>,
+    backup_dir: Option<&Path>,
+) -> Result<PathBuf, ArcError> {
+    let archive = archive.as_ref();
+    if !archive.is_file() {
+        return Err(ArcError::NotFound(archive.to_path_buf()));
+    }
+    let file_name = archive
+        .file_name()
+        .ok_or_else(|| ArcError::NoFilename(archive.to_path_buf()))?;
+
+    let dir = match backup_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir).with_path(dir)?;
+            dir.to_path_buf()
+        }
+        None => archive.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+
+    let base = format!("{}.bak", file_name.to_string_lossy());
+    let mut backup_path = dir.join(&base);
+    let mut n = 1;
+    while backup_path.exists() {
+        backup_path = dir.join(format!("{base}.{n}"));
+        n += 1;
+    }
+
+    fs::copy(archive, &backup_path).with_path(&backup_path)?;
+    info!("Backed up {:?} to {:?}", archive, backup_path);
+    Ok(backup_path)
+}
@@ -0,0 +1,92 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    error::{ArcError, PathContext},
+    pattern::NamePatterns,
+};
+
+// --- Batch Input Discovery ---
+
+/// Immediate child directories of `parent`, sorted by name.
+pub fn subdirectories(parent: impl AsRef<Path>) -> Result<Vec<PathBuf>, ArcError> {
+    let parent = parent.as_ref();
+    if !parent.is_dir() {
+        return Err(ArcError::NotFound(parent.to_path_buf()));
+    }
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(parent).with_path(parent)? {
+        let entry = entry.with_path(parent)?;
+        if entry.file_type().with_path(entry.path())?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// `.arc` files (case-insensitive) in `dir`, optionally searching
+/// subdirectories too, sorted by path.
+pub fn find_archives(dir: impl AsRef<Path>, recursive: bool) -> Result<Vec<PathBuf>, ArcError> {
+    let dir = dir.as_ref();
+    if !dir.is_dir() {
+        return Err(ArcError::NotFound(dir.to_path_buf()));
+    }
+    let walker = WalkDir::new(dir).min_depth(1);
+    let walker = if recursive {
+        walker
+    } else {
+        walker.max_depth(1)
+    };
+    let mut archives = Vec::new();
+    for entry in walker {
+        let entry = entry?;
+        let is_arc = entry
+            .path()
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("arc"));
+        if entry.file_type().is_file() && is_arc {
+            archives.push(entry.into_path());
+        }
+    }
+    archives.sort();
+    Ok(archives)
+}
+
+/// Expands a file name wildcard (`*`, `?`, `[...]`) in the last component of
+/// `pattern` against the files in its directory, for shells that pass
+/// patterns through unexpanded. Other paths are returned as is.
+pub fn expand_file_pattern(pattern: impl AsRef<Path>) -> Result<Vec<PathBuf>, ArcError> {
+    let pattern = pattern.as_ref();
+    let Some(file_pattern) = pattern.file_name().and_then(|name| name.to_str()) else {
+        return Ok(vec![pattern.to_path_buf()]);
+    };
+    if !file_pattern.contains(['*', '?', '[']) || pattern.exists() {
+        return Ok(vec![pattern.to_path_buf()]);
+    }
+    let matcher = NamePatterns::new([file_pattern])?;
+    let dir = match pattern.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut matches = Vec::new();
+    for entry in fs::read_dir(dir).with_path(dir)? {
+        let entry = entry.with_path(dir)?;
+        let matched = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| matcher.is_match(name));
+        if matched && entry.file_type().with_path(entry.path())?.is_file() {
+            matches.push(pattern.with_file_name(entry.file_name()));
+        }
+    }
+    if matches.is_empty() {
+        return Err(ArcError::NotFound(pattern.to_path_buf()));
+    }
+    matches.sort();
+    Ok(matches)
+}
@@ -0,0 +1,385 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    path::Path,
+};
+
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+};
+use silky_arc_core::{
+    UnpackOptions,
+    error::ArcError,
+    handle_unpack_with,
+    reader::{ArcEntry, ArcReader, shared_data_sources},
+};
+
+use crate::progress::format_bytes;
+
+// --- Archive Browser ---
+// `browse` shows the entries of an archive as a folder tree. Keys:
+//
+//   up/down, j/k    move           space   select / deselect
+//   enter, right    open folder    x       extract selection
+//   left            close folder   q, esc  quit
+//
+// With nothing selected, `x` extracts the entry or folder under the cursor.
+
+/// A line of the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub depth: usize,
+    pub kind: RowKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowKind {
+    /// A folder, by its `\` separated path.
+    Directory(String),
+    /// An entry, by its index in the archive.
+    Entry(usize),
+}
+
+#[derive(Debug, Default)]
+struct Folder {
+    folders: BTreeMap<String, Folder>,
+    entries: Vec<usize>,
+}
+
+/// State of the browser, independent of the terminal.
+#[derive(Debug)]
+pub struct Browser {
+    entries: Vec<ArcEntry>,
+    shared: Vec<Option<usize>>,
+    root: Folder,
+    expanded: HashSet<String>,
+    selected: BTreeSet<usize>,
+    cursor: usize,
+}
+
+impl Browser {
+    /// A browser over `entries`, with every folder closed.
+    pub fn new(entries: Vec<ArcEntry>) -> Self {
+        let mut root = Folder::default();
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        order.sort_by(|&a, &b| entries[a].name().cmp(entries[b].name()));
+        for index in order {
+            let mut folder = &mut root;
+            let mut components: Vec<&str> = entries[index].name().split('\\').collect();
+            components.pop();
+            for component in components {
+                folder = folder.folders.entry(component.to_string()).or_default();
+            }
+            folder.entries.push(index);
+        }
+        Self {
+            shared: shared_data_sources(&entries),
+            entries,
+            root,
+            expanded: HashSet::new(),
+            selected: BTreeSet::new(),
+            cursor: 0,
+        }
+    }
+
+    /// The visible lines: folders first, then entries, by name.
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        self.push_rows(&self.root, "", 0, &mut rows);
+        rows
+    }
+
+    fn push_rows(&self, folder: &Folder, prefix: &str, depth: usize, rows: &mut Vec<Row>) {
+        for (name, child) in &folder.folders {
+            let path = format!("{prefix}{name}");
+            let open = self.expanded.contains(&path);
+            rows.push(Row {
+                depth,
+                kind: RowKind::Directory(path.clone()),
+            });
+            if open {
+                self.push_rows(child, &format!("{path}\\"), depth + 1, rows);
+            }
+        }
+        rows.extend(folder.entries.iter().map(|&index| Row {
+            depth,
+            kind: RowKind::Entry(index),
+        }));
+    }
+
+    fn folder(&self, path: &str) -> Option<&Folder> {
+        path.split('\\').try_fold(&self.root, |folder, component| {
+            folder.folders.get(component)
+        })
+    }
+
+    /// Indices of the entries in or below the folder at `path`.
+    fn entries_below(&self, path: &str) -> Vec<usize> {
+        fn collect(folder: &Folder, found: &mut Vec<usize>) {
+            folder
+                .folders
+                .values()
+                .for_each(|child| collect(child, found));
+            found.extend(&folder.entries);
+        }
+        let mut found = Vec::new();
+        if let Some(folder) = self.folder(path) {
+            collect(folder, &mut found);
+        }
+        found
+    }
+
+    pub fn entries(&self) -> &[ArcEntry] {
+        &self.entries
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn current(&self) -> Option<Row> {
+        self.rows().into_iter().nth(self.cursor)
+    }
+
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.selected.contains(&index)
+    }
+
+    /// Moves the cursor by `delta` rows, stopping at the ends.
+    pub fn move_cursor(&mut self, delta: isize) {
+        let last = self.rows().len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    /// Opens or closes the folder under the cursor.
+    pub fn toggle_folder(&mut self) {
+        if let Some(RowKind::Directory(path)) = self.current().map(|row| row.kind)
+            && !self.expanded.remove(&path)
+        {
+            self.expanded.insert(path);
+        }
+    }
+
+    /// Closes the folder under the cursor, or moves to the folder holding
+    /// the current line.
+    pub fn close_folder(&mut self) {
+        let Some(row) = self.current() else {
+            return;
+        };
+        if let RowKind::Directory(path) = &row.kind
+            && self.expanded.remove(path)
+        {
+            return;
+        }
+        if let Some(parent) = self.rows()[..self.cursor]
+            .iter()
+            .rposition(|other| other.depth < row.depth)
+        {
+            self.cursor = parent;
+        }
+    }
+
+    /// Selects the entry or the whole folder under the cursor, or deselects
+    /// it if it already is.
+    pub fn toggle_selected(&mut self) {
+        let indices = match self.current().map(|row| row.kind) {
+            Some(RowKind::Directory(path)) => self.entries_below(&path),
+            Some(RowKind::Entry(index)) => vec![index],
+            None => return,
+        };
+        if indices.iter().all(|index| self.selected.contains(index)) {
+            indices.iter().for_each(|index| {
+                self.selected.remove(index);
+            });
+        } else {
+            self.selected.extend(indices);
+        }
+    }
+
+    /// Names of the entries `x` extracts: the selection, or else what is
+    /// under the cursor.
+    pub fn targets(&self) -> Vec<String> {
+        let indices = if self.selected.is_empty() {
+            match self.current().map(|row| row.kind) {
+                Some(RowKind::Directory(path)) => self.entries_below(&path),
+                Some(RowKind::Entry(index)) => vec![index],
+                None => Vec::new(),
+            }
+        } else {
+            self.selected.iter().copied().collect()
+        };
+        indices
+            .into_iter()
+            .map(|index| self.entries[index].name().to_string())
+            .collect()
+    }
+
+    fn details(&self) -> Vec<Line<'static>> {
+        match self.current().map(|row| row.kind) {
+            Some(RowKind::Directory(path)) => {
+                let indices = self.entries_below(&path);
+                let size: u64 = indices
+                    .iter()
+                    .map(|&index| self.entries[index].original_size() as u64)
+                    .sum();
+                vec![
+                    Line::from(format!("{path}\\")),
+                    Line::from(format!("entries:    {}", indices.len())),
+                    Line::from(format!("size:       {}", format_bytes(size))),
+                ]
+            }
+            Some(RowKind::Entry(index)) => {
+                let entry = &self.entries[index];
+                let mut lines = vec![
+                    Line::from(entry.name().to_string()),
+                    Line::from(format!(
+                        "size:       {}",
+                        format_bytes(entry.original_size() as u64)
+                    )),
+                    Line::from(format!(
+                        "stored:     {}",
+                        format_bytes(entry.compressed_size() as u64)
+                    )),
+                    Line::from(format!(
+                        "compressed: {}",
+                        if entry.is_compressed() { "yes" } else { "no" }
+                    )),
+                    Line::from(format!("offset:     {:#x}", entry.offset())),
+                ];
+                if let Some(source) = self.shared[index] {
+                    lines.push(Line::from(format!(
+                        "shares data with {}",
+                        self.entries[source].name()
+                    )));
+                }
+                lines
+            }
+            None => vec![Line::from("(empty archive)")],
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame, status: &str) {
+        let [main, status_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [tree_area, details_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main);
+
+        let items: Vec<ListItem> = self
+            .rows()
+            .iter()
+            .map(|row| {
+                let indent = "  ".repeat(row.depth);
+                let label = match &row.kind {
+                    RowKind::Directory(path) => {
+                        let name = path.rsplit('\\').next().unwrap_or(path);
+                        let indices = self.entries_below(path);
+                        let mark = match indices.iter().filter(|i| self.is_selected(**i)).count() {
+                            0 => "[ ]",
+                            n if n == indices.len() => "[x]",
+                            _ => "[-]",
+                        };
+                        let arrow = if self.expanded.contains(path) {
+                            '▾'
+                        } else {
+                            '▸'
+                        };
+                        format!("{mark} {indent}{arrow} {name}\\")
+                    }
+                    RowKind::Entry(index) => {
+                        let name = self.entries[*index].name();
+                        let name = name.rsplit('\\').next().unwrap_or(name);
+                        let mark = if self.is_selected(*index) {
+                            "[x]"
+                        } else {
+                            "[ ]"
+                        };
+                        format!("{mark} {indent}  {name}")
+                    }
+                };
+                ListItem::new(label)
+            })
+            .collect();
+        let title = format!(
+            " {} entries, {} selected ",
+            self.entries.len(),
+            self.selected.len()
+        );
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.cursor));
+        frame.render_stateful_widget(list, tree_area, &mut state);
+        frame.render_widget(
+            Paragraph::new(self.details()).block(Block::bordered().title(" details ")),
+            details_area,
+        );
+        frame.render_widget(Paragraph::new(status.to_string()), status_area);
+    }
+}
+
+const HELP: &str = "space: select  enter: open  x: extract  q: quit";
+
+/// Browses `archive_path` in the terminal. `x` extracts into `output_dir`.
+pub fn browse(archive_path: &Path, output_dir: &Path) -> Result<(), ArcError> {
+    let archive = ArcReader::open(archive_path)?;
+    let mut browser = Browser::new(archive.entries().to_vec());
+
+    // Log lines would be drawn over the screen
+    let level = log::max_level();
+    log::set_max_level(log::LevelFilter::Off);
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut browser, archive_path, output_dir);
+    ratatui::restore();
+    log::set_max_level(level);
+    result
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    browser: &mut Browser,
+    archive_path: &Path,
+    output_dir: &Path,
+) -> Result<(), ArcError> {
+    let mut status = HELP.to_string();
+    loop {
+        terminal.draw(|frame| browser.draw(frame, &status))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => browser.move_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => browser.move_cursor(1),
+            KeyCode::PageUp => browser.move_cursor(-20),
+            KeyCode::PageDown => browser.move_cursor(20),
+            KeyCode::Enter | KeyCode::Right => browser.toggle_folder(),
+            KeyCode::Left => browser.close_folder(),
+            KeyCode::Char(' ') => browser.toggle_selected(),
+            KeyCode::Char('x') => {
+                let names = browser.targets();
+                let options = UnpackOptions {
+                    entries: Some(names.iter().cloned().collect()),
+                    require_entries: true,
+                    ..Default::default()
+                };
+                status = match handle_unpack_with(archive_path, output_dir, &options) {
+                    Ok(()) => format!(
+                        "Extracted {} entries to {}",
+                        names.len(),
+                        output_dir.display()
+                    ),
+                    Err(e) => format!("Extraction failed: {e}"),
+                };
+            }
+            _ => status = HELP.to_string(),
+        }
+    }
+}
@@ -2,37 +2,819 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+use crate::{
+    DEFAULT_MIN_COMPRESS_SIZE, EntryOrder, OverwritePolicy,
+    i18n::Lang,
+    merge::ConflictPolicy,
+    name::{NameEncoding, SeparatorStyle},
+    rename::RenameRule,
+    selection::SizeRange,
+};
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Number of worker threads (defaults to the number of CPUs)
+    #[arg(short = 'j', long, global = true, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Treat warnings (name mapping or sanitization, compression fallback)
+    /// as errors
+    #[arg(long, global = true, default_value_t = false)]
+    pub abort_on_warning: bool,
+
+    /// Show a byte progress line on stderr while packing or unpacking.
+    /// Per-file log messages are hidden unless `RUST_LOG` is set.
+    #[arg(long, global = true, default_value_t = false)]
+    pub progress: bool,
+
+    /// Write progress as newline-delimited JSON events to stderr, or to
+    /// `--progress-json=FILE` (e.g. `/dev/fd/3` for a file descriptor)
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "-",
+        conflicts_with = "progress"
+    )]
+    pub progress_json: Option<PathBuf>,
+
+    /// Language of messages (defaults to the system locale)
+    #[arg(long, global = true, value_enum)]
+    pub lang: Option<Lang>,
 }
 
 #[derive(clap::Subcommand, Debug)]
 pub enum Commands {
     /// Packs a directory into a .arc file
     Pack {
-        /// Input directory path
-        #[arg(required = true)]
-        input: PathBuf,
+        /// Input directory path. Several directories are packed in parallel,
+        /// each into its own archive.
+        #[arg(required_unless_present = "manifest", num_args = 1..)]
+        input: Vec<PathBuf>,
 
-        /// Output archive file path (optional)
+        /// Output archive file path (optional, single input only)
         #[arg(short, long)]
         output: Option<PathBuf>,
 
+        /// The inputs are zip files, packed without extracting them first
+        #[arg(long, default_value_t = false, conflicts_with_all = ["each", "verify"])]
+        from_zip: bool,
+
+        /// Pack the files listed in this JSON manifest, in order and with
+        /// the names and compression given there (see the README)
+        #[arg(
+            long,
+            value_name = "FILE",
+            conflicts_with_all = ["input", "each", "from_zip", "verify", "keep_empty_dirs"]
+        )]
+        manifest: Option<PathBuf>,
+
+        /// Pack each immediate subdirectory of the input into its own
+        /// archive
+        #[arg(long, default_value_t = false, conflicts_with = "output")]
+        each: bool,
+
+        /// Template for the derived output path, e.g. `{parent}/{stem}.arc`
+        #[arg(long, conflicts_with = "output", value_name = "TEMPLATE")]
+        output_template: Option<String>,
+
+        /// Put the derived archive(s) into this directory instead of next to
+        /// the input
+        #[arg(long, conflicts_with = "output", value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+
         /// Enable LZSS compression
         #[arg(short, long, default_value_t = false)]
         compress: bool,
+
+        /// With `--compress`, store files smaller than N bytes uncompressed
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_MIN_COMPRESS_SIZE, requires = "compress")]
+        min_compress_size: u32,
+
+        /// With `--compress`, comma separated patterns of files that are
+        /// always stored uncompressed (e.g. `*.ogg,*.mpg`)
+        #[arg(
+            long,
+            value_name = "PATTERNS",
+            value_delimiter = ',',
+            requires = "compress"
+        )]
+        store: Vec<String>,
+
+        /// Re-read the written archive and compare it against the sources
+        #[arg(long, default_value_t = false)]
+        verify: bool,
+
+        /// Directory for intermediate files (defaults to the output's
+        /// directory)
+        #[arg(long, value_name = "DIR")]
+        temp_dir: Option<PathBuf>,
+
+        /// Bound the file data held in memory per archive, e.g. `512M`,
+        /// keeping processed data in a temporary file instead
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        memory_limit: Option<u64>,
+
+        /// Add a `__silkyarctool__.json` entry recording the tool version,
+        /// time, a hash of the sources and the settings used
+        #[arg(long, default_value_t = false)]
+        provenance: bool,
+
+        /// Keep empty directories as zero-byte placeholder entries (named
+        /// with a trailing `\`), which `unpack` recreates
+        #[arg(long, default_value_t = false)]
+        keep_empty_dirs: bool,
+
+        /// Store files with identical content once, with all their entries
+        /// pointing at one data block
+        #[arg(long, default_value_t = false)]
+        dedup: bool,
+
+        /// Separator written into entry names: `backslash` (what the engine
+        /// expects) or `slash`
+        #[arg(long, value_name = "STYLE", default_value = "backslash")]
+        separator: SeparatorStyle,
+
+        /// Entry order: `walk` (as the directory is read) or `name`
+        /// (sorted, the same on every system)
+        #[arg(long, value_name = "ORDER", default_value = "walk")]
+        order: EntryOrder,
+
+        /// Create a header-only archive if the input contains no files
+        #[arg(long, default_value_t = false)]
+        allow_empty: bool,
+
+        /// Keep running and repack whenever a file in the input changes
+        #[arg(long, default_value_t = false, conflicts_with_all = ["manifest", "from_zip"])]
+        watch: bool,
+
+        /// With `--watch`, milliseconds without further changes before
+        /// repacking
+        #[arg(long, value_name = "MS", default_value_t = 300, requires = "watch")]
+        debounce: u64,
+
+        /// Replace a character in names before encoding, in addition to the
+        /// built-in CP932 fallbacks (e.g. `〜=～`). May be repeated.
+        #[arg(long = "map-char", value_name = "FROM=TO", value_parser = parse_char_mapping)]
+        map_chars: Vec<(char, char)>,
+
+        #[command(flatten)]
+        backup: BackupArgs,
     },
     /// Unpacks a .arc file into a directory
     Unpack {
+        /// Input archive file path. Several archives (or a pattern such as
+        /// `*.arc`) are unpacked in parallel, each into its own directory.
+        #[arg(required = true, num_args = 1..)]
+        input: Vec<PathBuf>,
+
+        /// Output directory path (optional). With several archives, each is
+        /// extracted into an archive-named folder inside it.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Template for the derived output path, e.g. `{stem}_extracted`
+        #[arg(long, conflicts_with = "output", value_name = "TEMPLATE")]
+        output_template: Option<String>,
+
+        /// Extract into an archive-named folder in the current directory
+        #[arg(long, default_value_t = false, conflicts_with_all = ["output", "output_template"])]
+        here: bool,
+
+        /// Unpack every .arc file in the input directory. With `--output`,
+        /// the directory layout is mirrored below the output directory.
+        #[arg(long, visible_alias = "batch", default_value_t = false)]
+        each: bool,
+
+        /// With `--each`, also search subdirectories for archives
+        #[arg(short, long, default_value_t = false, requires = "each")]
+        recursive: bool,
+
+        /// Skip entries already extracted with the expected size
+        #[arg(long, default_value_t = false)]
+        resume: bool,
+
+        /// With `--resume`, also compare existing files' content
+        #[arg(long, default_value_t = false, requires = "resume")]
+        verify_existing: bool,
+
+        /// Check each entry's decompressed size and refuse corrupt entries
+        #[arg(long, default_value_t = false)]
+        verify: bool,
+
+        /// `sha256sum`-style file with expected entry checksums (implies
+        /// `--verify`)
+        #[arg(long, value_name = "FILE")]
+        checksums: Option<PathBuf>,
+
+        /// Comma separated encodings to try for entry names, in order
+        /// (defaults to cp932,gbk,big5,utf8)
+        #[arg(long, value_delimiter = ',')]
+        encodings: Option<Vec<NameEncoding>>,
+
+        /// Hard link entries that share a data block instead of writing
+        /// each copy
+        #[arg(long, default_value_t = false)]
+        hardlink_shared: bool,
+
+        /// Bound the entry data held in memory per archive, e.g. `512M`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        memory_limit: Option<u64>,
+
+        /// Only extract the entries named in FILE (one per line, or NUL
+        /// separated)
+        #[arg(long, value_name = "FILE")]
+        entries_from: Option<PathBuf>,
+
+        /// Only extract this entry, failing if the archive has no such
+        /// entry. May be repeated.
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["entries_from", "each"])]
+        only: Vec<String>,
+
+        /// Comma separated patterns of entries to extract (e.g. `*.ogg`)
+        #[arg(long, value_name = "PATTERNS", value_delimiter = ',')]
+        include: Vec<String>,
+
+        /// Comma separated patterns of entries to skip (e.g. `SE_*`)
+        #[arg(long, value_name = "PATTERNS", value_delimiter = ',')]
+        exclude: Vec<String>,
+
+        #[command(flatten)]
+        size: SizeArgs,
+
+        /// sed-style rule applied to entry names before extraction, e.g.
+        /// `s/^voice\\//` to drop a leading `voice\`. May be repeated.
+        #[arg(long = "rename", value_name = "RULE")]
+        rename_rules: Vec<RenameRule>,
+
+        /// What to do with existing files: `replace`, `skip` or `fail`
+        #[arg(long, value_name = "POLICY", default_value = "replace")]
+        overwrite: OverwritePolicy,
+
+        /// Extract all entries directly into the output directory, dropping
+        /// their folders
+        #[arg(long, default_value_t = false)]
+        flatten: bool,
+
+        /// Report entries that fail to extract and continue with the rest
+        #[arg(long, default_value_t = false)]
+        keep_going: bool,
+    },
+    /// Adds files to an existing .arc file
+    Add {
+        /// Archive to add the files to
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Files to add, stored under their file name
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Folder inside the archive to store the files in, e.g. `script`
+        #[arg(long, value_name = "DIR")]
+        prefix: Option<String>,
+
+        /// Compress the new data with LZSS
+        #[arg(short, long, default_value_t = false)]
+        compress: bool,
+
+        #[command(flatten)]
+        edit: EditArgs,
+    },
+    /// Removes entries from a .arc file
+    Remove {
+        /// Archive to remove the entries from
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Names of the entries to remove (`\` or `/` separated)
+        #[arg(required = true)]
+        entries: Vec<String>,
+
+        #[command(flatten)]
+        edit: EditArgs,
+    },
+    /// Replaces the data of one entry of a .arc file
+    Update {
+        /// Archive containing the entry
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Name of the entry to replace (`\` or `/` separated)
+        #[arg(required = true)]
+        entry: String,
+
+        /// File with the new content
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Compress the new data with LZSS even if the entry was stored
+        /// uncompressed
+        #[arg(short, long, default_value_t = false)]
+        compress: bool,
+
+        #[command(flatten)]
+        edit: EditArgs,
+    },
+    /// Renames one entry of a .arc file in place
+    #[command(visible_alias = "mv")]
+    Move {
+        /// Archive containing the entry
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Current name of the entry (`\` or `/` separated)
+        #[arg(required = true)]
+        from: String,
+
+        /// New name of the entry
+        #[arg(required = true)]
+        to: String,
+
+        #[command(flatten)]
+        backup: BackupArgs,
+    },
+    /// Rewrites a .arc file with the files of an overlay directory and/or
+    /// after running a command on its extracted content
+    #[command(group = clap::ArgGroup::new("changes").required(true).multiple(true))]
+    Repack {
+        /// Archive to repack
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Directory of files replacing (or adding to) the archive's entries
+        #[arg(long, value_name = "DIR", group = "changes")]
+        overlay: Option<PathBuf>,
+
+        /// Extract the archive to a temporary directory and run this shell
+        /// command on it (the directory is passed as its first argument)
+        /// before packing it again
+        #[arg(long, value_name = "COMMAND", group = "changes")]
+        exec: Option<String>,
+
+        /// Compress new data (with `--exec`, all data) with LZSS
+        #[arg(short, long, default_value_t = false)]
+        compress: bool,
+
+        #[command(flatten)]
+        edit: EditArgs,
+    },
+    /// Combines the entries of several .arc files into one
+    Merge {
+        /// Archives to merge, in order
+        #[arg(required = true, num_args = 2..)]
+        archives: Vec<PathBuf>,
+
+        /// Output archive file path
+        #[arg(short, long, required = true)]
+        output: PathBuf,
+
+        /// What to do with entries found in several archives: `error`,
+        /// `keep-first` or `keep-last`
+        #[arg(long, value_name = "POLICY", default_value = "error")]
+        on_conflict: ConflictPolicy,
+
+        #[command(flatten)]
+        backup: BackupArgs,
+    },
+    /// Splits a .arc file into several self-contained archives of limited
+    /// size
+    Split {
+        /// Input archive file path
+        #[arg(required = true)]
+        input: PathBuf,
+
+        /// Maximum size of each part, e.g. `700M` (K, M and G are powers of
+        /// 1024)
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_size: u64,
+
+        /// Directory to write the parts to (defaults to the archive's
+        /// directory)
+        #[arg(short, long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+    },
+    /// Rewrites a .arc file with its data blocks back to back, dropping
+    /// gaps and stale data left by edits
+    Optimize {
+        /// Archive to optimize
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Try LZSS compression on entries that are stored uncompressed
+        #[arg(long, default_value_t = false)]
+        recompress: bool,
+
+        /// With `--recompress`, leave files smaller than N bytes
+        /// uncompressed
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_MIN_COMPRESS_SIZE, requires = "recompress")]
+        min_compress_size: u32,
+
+        /// With `--recompress`, comma separated patterns of entries that
+        /// stay uncompressed (e.g. `*.ogg`)
+        #[arg(
+            long,
+            value_name = "PATTERNS",
+            value_delimiter = ',',
+            requires = "recompress"
+        )]
+        store: Vec<String>,
+
+        /// Keep a separate copy of identical data blocks instead of storing
+        /// them once
+        #[arg(long, default_value_t = false)]
+        no_dedup: bool,
+
+        #[command(flatten)]
+        edit: EditArgs,
+    },
+    /// Rewrites the entry names of a .arc file to use `\` as separator,
+    /// leaving the data blocks as they are
+    Normalize {
+        /// Archive to normalize
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Also drop leading `.\` components
+        #[arg(long, default_value_t = false)]
+        trim_current_dir: bool,
+
+        #[command(flatten)]
+        edit: EditArgs,
+    },
+    /// Lists the entries of a .arc file without extracting them
+    List {
         /// Input archive file path
         #[arg(required = true)]
         input: PathBuf,
 
-        /// Output directory path (optional)
+        /// Only show the N largest entries
+        #[arg(long, value_name = "N")]
+        top: Option<usize>,
+
+        /// Size used to rank entries for `--top`
+        #[arg(long, value_enum, default_value_t = SizeKey::Original, requires = "top")]
+        by: SizeKey,
+
+        #[command(flatten)]
+        size: SizeArgs,
+
+        /// Print the provenance recorded by `pack --provenance` instead of
+        /// the entries
+        #[arg(long, default_value_t = false, conflicts_with = "top")]
+        provenance: bool,
+
+        /// Print the metadata as a JSON document (see the README for the
+        /// schema)
+        #[arg(long, default_value_t = false, conflicts_with = "provenance")]
+        json: bool,
+    },
+    /// Prints a summary of a .arc file's metadata
+    Info {
+        /// Input archive file path
+        #[arg(required = true)]
+        input: PathBuf,
+    },
+    /// Prints per-extension compression statistics
+    Stats {
+        /// Input archive file, or a directory to analyze as a pack dry-run
+        #[arg(required = true)]
+        input: PathBuf,
+    },
+    /// Reports groups of entries with identical content
+    Dupes {
+        /// Input archive file path
+        #[arg(required = true)]
+        input: PathBuf,
+    },
+    /// Searches the decompressed entries of a .arc file for a pattern
+    Grep {
+        /// Input archive file path
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Regular expression matched against each line, or with `--hex`
+        /// the bytes to search for (e.g. `8a 9c 0f`)
+        #[arg(required = true)]
+        pattern: String,
+
+        /// Search for the bytes given as hex instead of text
+        #[arg(long, default_value_t = false, conflicts_with_all = ["ignore_case", "encoding"])]
+        hex: bool,
+
+        /// Match letters case-insensitively
+        #[arg(short, long, default_value_t = false)]
+        ignore_case: bool,
+
+        /// Encoding of the entries' text
+        #[arg(long, default_value = "cp932")]
+        encoding: NameEncoding,
+
+        /// Only search entries matching these patterns (comma separated)
+        #[arg(long, value_name = "PATTERNS", value_delimiter = ',')]
+        include: Vec<String>,
+    },
+    /// Encrypts or decrypts single entry names
+    NameTool {
+        #[command(subcommand)]
+        action: NameAction,
+    },
+    /// Compresses or decompresses loose files with the Silky LZSS
+    /// parameters
+    Lzss {
+        #[command(subcommand)]
+        action: LzssAction,
+    },
+    /// Checks a directory for files the engine cannot handle before packing
+    Lint {
+        /// Input directory path
+        #[arg(required = true)]
+        input: PathBuf,
+
+        /// Comma separated list of expected extensions (e.g. `ogg,txt`)
+        #[arg(long, value_delimiter = ',')]
+        extensions: Option<Vec<String>>,
+
+        /// Print issues as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Compares this tool's extraction of a .arc file against a directory
+    /// extracted by another tool
+    CompatCheck {
+        /// Input archive file path
+        #[arg(required = true)]
+        input: PathBuf,
+
+        /// Directory extracted from the same archive by the reference tool
+        #[arg(required = true)]
+        reference: PathBuf,
+    },
+    /// Lists the files only in a .arc file or only in a directory, and the
+    /// ones whose content differs
+    Compare {
+        /// Archive file path
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Directory of files to compare against, e.g. the sources of a
+        /// repack
+        #[arg(required = true)]
+        dir: PathBuf,
+    },
+    /// Checks every entry of a .arc file and prints a pass/fail table
+    #[command(visible_alias = "test")]
+    Verify {
+        /// Input archive file path
+        #[arg(required = true)]
+        input: PathBuf,
+
+        /// Only print the entries that failed
+        #[arg(long, default_value_t = false)]
+        failed_only: bool,
+    },
+    /// Prints the SHA-256 of every decompressed entry of a .arc file, in
+    /// the format `unpack --checksums` reads, and of the whole content
+    Checksum {
+        /// Input archive file paths
+        #[arg(required = true, num_args = 1..)]
+        input: Vec<PathBuf>,
+
+        /// Only print the digest of each archive's content, which is the
+        /// same for archives with the same entries and data
+        #[arg(long, default_value_t = false)]
+        archive_only: bool,
+    },
+    /// Lists the entries added, removed or modified between two .arc files
+    Diff {
+        /// Old archive file path
+        #[arg(required = true)]
+        old: PathBuf,
+
+        /// New archive file path
+        #[arg(required = true)]
+        new: PathBuf,
+
+        /// Compare entry sizes only, without reading any data
+        #[arg(long, default_value_t = false)]
+        metadata_only: bool,
+    },
+    /// Writes a patch turning one version of a .arc file into another
+    MakePatch {
+        /// Archive the patch applies to
+        #[arg(required = true)]
+        old: PathBuf,
+
+        /// Archive the patch produces
+        #[arg(required = true)]
+        new: PathBuf,
+
+        /// Output patch file path
+        #[arg(short, long, required = true)]
+        output: PathBuf,
+    },
+    /// Rebuilds a .arc file from its base version and a patch
+    ApplyPatch {
+        /// Archive the patch was made against
+        #[arg(required = true)]
+        base: PathBuf,
+
+        /// Patch file path
+        #[arg(required = true)]
+        patch: PathBuf,
+
+        /// Output archive file path (may be the base archive itself)
+        #[arg(short, long, required = true)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        backup: BackupArgs,
+    },
+    /// Diagnoses common problems in a .arc file
+    Doctor {
+        /// Input archive file path
+        #[arg(required = true)]
+        input: PathBuf,
+    },
+    /// Asks what to do step by step and runs the matching command
+    Wizard,
+    /// Writes roff man pages for the tool and every subcommand, for
+    /// packagers
+    #[command(hide = true)]
+    Mangen {
+        /// Directory to write the pages to
+        #[arg(required = true)]
+        out_dir: PathBuf,
+    },
+    /// Browses a .arc file as a folder tree and extracts selected entries
+    #[cfg(feature = "tui")]
+    Browse {
+        /// Input archive file path
+        #[arg(required = true)]
+        archive: PathBuf,
+
+        /// Directory extracted entries are written to (defaults to where
+        /// `unpack` would put them)
         #[arg(short, long)]
         output: Option<PathBuf>,
     },
+    /// Runs a rhai script against the archive API
+    #[cfg(feature = "scripting")]
+    Script {
+        /// Script file path
+        #[arg(required = true)]
+        script: PathBuf,
+
+        /// Arguments passed to the script as `ARGS`
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum NameAction {
+    /// Prints the stored (encrypted) form of a name as hex
+    Encrypt {
+        /// Entry name, using `\` as separator
+        name: String,
+    },
+    /// Prints the name stored as the given bytes
+    Decrypt {
+        /// Encrypted name as hex, e.g. `8a 9c 0f` or `8a9c0f`
+        #[arg(long, required = true, value_parser = parse_hex)]
+        hex: HexBytes,
+
+        /// Encoding of the decrypted name
+        #[arg(long, default_value = "cp932")]
+        encoding: NameEncoding,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum LzssAction {
+    /// Compresses a file into a raw LZSS block, as entries are stored
+    Compress {
+        /// File to compress
+        input: PathBuf,
+        /// Path of the compressed output
+        output: PathBuf,
+    },
+    /// Decompresses a raw LZSS block, e.g. one cut from an archive
+    Decompress {
+        /// LZSS compressed file
+        input: PathBuf,
+        /// Path of the decompressed output
+        output: PathBuf,
+
+        /// Expected decompressed size; a mismatch is reported as an error
+        #[arg(long, value_name = "BYTES")]
+        size: Option<u32>,
+    },
+}
+
+/// Options shared by the commands that rewrite an existing archive
+#[derive(clap::Args, Debug)]
+pub struct EditArgs {
+    /// Write the result to this file instead of updating the archive in
+    /// place
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub backup: BackupArgs,
+}
+
+/// Options shared by every command that overwrites an existing archive
+#[derive(clap::Args, Debug)]
+pub struct BackupArgs {
+    /// Do not back up an existing archive before overwriting it
+    #[arg(long, default_value_t = false)]
+    pub no_backup: bool,
+
+    /// Directory to store backups in (defaults to the archive's directory)
+    #[arg(long, conflicts_with = "no_backup")]
+    pub backup_dir: Option<PathBuf>,
+}
+
+/// Filters on an entry's original size
+#[derive(clap::Args, Debug)]
+pub struct SizeArgs {
+    /// Only include entries of at least N bytes
+    #[arg(long, value_name = "N")]
+    pub min_size: Option<u64>,
+
+    /// Only include entries of at most N bytes
+    #[arg(long, value_name = "N")]
+    pub max_size: Option<u64>,
+}
+
+impl SizeArgs {
+    pub fn range(&self) -> SizeRange {
+        SizeRange {
+            min: self.min_size,
+            max: self.max_size,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeKey {
+    /// Size after decompression
+    Original,
+    /// Size of the stored data block
+    Compressed,
+}
+
+fn parse_char_mapping(s: &str) -> Result<(char, char), String> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some(from), Some('='), Some(to), None) => Ok((from, to)),
+        _ => Err("expected FROM=TO with a single character on each side".to_string()),
+    }
+}
+
+/// A byte count with an optional `K`, `M` or `G` suffix (powers of 1024).
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let multiplier: u64 = match s[digits.len()..].to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        suffix => return Err(format!("unknown size suffix {suffix:?}")),
+    };
+    let value: u64 = digits.trim().parse().map_err(|e| format!("{e}"))?;
+    value
+        .checked_mul(multiplier)
+        .filter(|&size| size > 0)
+        .ok_or_else(|| "expected a size greater than zero".to_string())
+}
+
+/// Bytes given on the command line as hex.
+#[derive(Debug, Clone)]
+pub struct HexBytes(pub Vec<u8>);
+
+pub fn parse_hex(s: &str) -> Result<HexBytes, String> {
+    let digits: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("{c:?} is not a hex digit"));
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err("expected an even number of hex digits".to_string());
+    }
+    // Only ASCII digits are left, so every pair is a whole `str`
+    Ok(HexBytes(
+        digits
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+            .collect(),
+    ))
 }
@@ -0,0 +1,275 @@
+//! Graphical front end: drop an archive onto the window to browse and extract
+//! it, or a folder to pack it. Everything runs through the same library calls
+//! and output path derivation as the command line tool.
+
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use eframe::egui;
+use silky_arc_tool::{
+    PackOptions, UnpackOptions,
+    cancel::CancelToken,
+    error::{ArcError, PathContext},
+    handle_pack_with, handle_unpack_with,
+    metrics::Metrics,
+    name::entry_name_to_path,
+    output::{UnpackOutput, derive_pack_output, resolve_unpack_output},
+    progress::format_bytes,
+    reader::{ArcEntry, ArcReader},
+};
+
+fn main() -> eframe::Result {
+    _ = pretty_env_logger::formatted_builder()
+        .filter_level(log::LevelFilter::Warn)
+        .parse_default_env()
+        .try_init();
+
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([720.0, 480.0])
+            .with_drag_and_drop(true),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "Silky Arc Tool",
+        options,
+        Box::new(|_| Ok(Box::<App>::default())),
+    )
+}
+
+/// What was dropped onto the window last.
+#[derive(Default)]
+enum Input {
+    #[default]
+    None,
+    Archive {
+        path: PathBuf,
+        entries: Vec<ArcEntry>,
+    },
+    Folder(PathBuf),
+}
+
+/// A pack or unpack running on a worker thread.
+struct Task {
+    metrics: Arc<Metrics>,
+    cancel: CancelToken,
+    handle: JoinHandle<Result<String, ArcError>>,
+}
+
+#[derive(Default)]
+struct App {
+    input: Input,
+    compress: bool,
+    task: Option<Task>,
+    status: String,
+}
+
+impl App {
+    fn open(&mut self, path: PathBuf) {
+        self.input = if path.is_dir() {
+            self.status = format!("Ready to pack {}", path.display());
+            Input::Folder(path)
+        } else {
+            match ArcReader::open(&path) {
+                Ok(archive) => {
+                    self.status = format!("{} entries", archive.entries().len());
+                    Input::Archive {
+                        entries: archive.entries().to_vec(),
+                        path,
+                    }
+                }
+                Err(e) => {
+                    self.status = format!("Cannot open {}: {e}", path.display());
+                    Input::None
+                }
+            }
+        };
+    }
+
+    fn spawn(
+        &mut self,
+        work: impl FnOnce(Arc<Metrics>, CancelToken) -> Result<String, ArcError> + Send + 'static,
+    ) {
+        let metrics = Arc::new(Metrics::new());
+        let cancel = CancelToken::new();
+        let handle = thread::spawn({
+            let (metrics, cancel) = (metrics.clone(), cancel.clone());
+            move || work(metrics, cancel)
+        });
+        self.task = Some(Task {
+            metrics,
+            cancel,
+            handle,
+        });
+    }
+
+    fn unpack(&mut self, archive: PathBuf) {
+        self.spawn(move |metrics, cancel| {
+            let output_dir = resolve_unpack_output(&archive, None, UnpackOutput::Derived(None))?;
+            let options = UnpackOptions {
+                metrics: Some(metrics),
+                cancel: Some(cancel),
+                ..Default::default()
+            };
+            handle_unpack_with(&archive, &output_dir, &options)?;
+            Ok(format!("Extracted to {}", output_dir.display()))
+        });
+    }
+
+    fn pack(&mut self, input_dir: PathBuf) {
+        let compress = self.compress;
+        self.spawn(move |metrics, cancel| {
+            let output = derive_pack_output(&input_dir, None)?;
+            let options = PackOptions {
+                compress,
+                metrics: Some(metrics),
+                cancel: Some(cancel),
+                ..Default::default()
+            };
+            let summary = handle_pack_with(&input_dir, &output, &options)?;
+            Ok(format!(
+                "Packed {} entries into {}",
+                summary.entry_count(),
+                output.display()
+            ))
+        });
+    }
+
+    /// Picks up the result of a finished task.
+    fn poll_task(&mut self) {
+        if self
+            .task
+            .as_ref()
+            .is_some_and(|task| task.handle.is_finished())
+        {
+            let task = self.task.take().expect("checked above");
+            self.status = match task.handle.join() {
+                Ok(Ok(message)) => message,
+                Ok(Err(ArcError::Cancelled)) => "Cancelled".to_string(),
+                Ok(Err(e)) => format!("Failed: {e}"),
+                Err(_) => "Failed: worker thread panicked".to_string(),
+            };
+        }
+    }
+}
+
+/// Extracts a single entry next to the archive, below the derived output
+/// directory.
+fn extract_entry(archive: &Path, entry: &ArcEntry) -> Result<PathBuf, ArcError> {
+    let output_dir = resolve_unpack_output(archive, None, UnpackOutput::Derived(None))?;
+    let output = output_dir.join(entry_name_to_path(entry.name()));
+    let data = ArcReader::open(archive)?.read_entry(entry)?;
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).with_path(parent)?;
+    }
+    fs::write(&output, data).with_path(&output)?;
+    Ok(output)
+}
+
+impl eframe::App for App {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        let dropped = ui.ctx().input(|input| {
+            input
+                .raw
+                .dropped_files
+                .first()
+                .map(|file| file.path().to_path_buf())
+        });
+        if let Some(path) = dropped
+            && self.task.is_none()
+        {
+            self.open(path);
+        }
+        self.poll_task();
+
+        egui::Panel::top("actions").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let idle = self.task.is_none();
+                match &self.input {
+                    Input::Archive { path, .. } => {
+                        ui.label(path.display().to_string());
+                        if ui
+                            .add_enabled(idle, egui::Button::new("Extract all"))
+                            .clicked()
+                        {
+                            self.unpack(path.clone());
+                        }
+                    }
+                    Input::Folder(path) => {
+                        ui.label(path.display().to_string());
+                        ui.checkbox(&mut self.compress, "Compress");
+                        if ui.add_enabled(idle, egui::Button::new("Pack")).clicked() {
+                            self.pack(path.clone());
+                        }
+                    }
+                    Input::None => {
+                        ui.label("Drop an .arc file to browse it, or a folder to pack it.");
+                    }
+                }
+            });
+            if let Some(task) = &self.task {
+                let snapshot = task.metrics.snapshot();
+                let fraction = match snapshot.bytes_total {
+                    0 => 0.0,
+                    total => snapshot.bytes_done as f32 / total as f32,
+                };
+                ui.horizontal(|ui| {
+                    let cancelling = task.cancel.is_cancelled();
+                    if ui
+                        .add_enabled(!cancelling, egui::Button::new("Cancel"))
+                        .clicked()
+                    {
+                        task.cancel.cancel();
+                    }
+                    ui.add(egui::ProgressBar::new(fraction).text(format!(
+                        "{} / {}",
+                        format_bytes(snapshot.bytes_done),
+                        format_bytes(snapshot.bytes_total)
+                    )));
+                });
+                ui.ctx().request_repaint();
+            } else {
+                ui.label(&self.status);
+            }
+        });
+
+        egui::CentralPanel::default_margins().show(ui, |ui| {
+            let Input::Archive { path, entries } = &self.input else {
+                return;
+            };
+            let mut extracted = None;
+            let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+            egui::ScrollArea::vertical().auto_shrink(false).show_rows(
+                ui,
+                row_height,
+                entries.len(),
+                |ui, rows| {
+                    for entry in &entries[rows] {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("Extract").clicked() {
+                                extracted = Some(extract_entry(path, entry));
+                            }
+                            ui.label(format!(
+                                "{}  ({}, stored {})",
+                                entry.name(),
+                                format_bytes(entry.original_size() as u64),
+                                format_bytes(entry.compressed_size() as u64)
+                            ));
+                        });
+                    }
+                },
+            );
+            match extracted {
+                Some(Ok(output)) => self.status = format!("Extracted {}", output.display()),
+                Some(Err(e)) => self.status = format!("Failed: {e}"),
+                None => {}
+            }
+        });
+    }
+}
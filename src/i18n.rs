@@ -0,0 +1,504 @@
+use std::{env, fmt::Display, sync::OnceLock};
+
+use clap::Command;
+use silky_arc_core::error::ArcError;
+
+// --- Localized Messages ---
+// Most users are Japanese and Chinese visual novel modders, so subcommand
+// descriptions, summaries and error messages are translated. Messages are
+// looked up by key; `{name}` placeholders are filled in by `trf`. Keys
+// missing a translation fall back to English.
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    Ja,
+    #[value(name = "zh-CN")]
+    ZhCn,
+}
+
+impl Lang {
+    /// Maps a POSIX locale such as `ja_JP.UTF-8` to a supported language.
+    pub fn from_locale(locale: &str) -> Option<Lang> {
+        let language = locale
+            .split(['.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+            .replace('-', "_");
+        match language.split('_').next() {
+            Some("ja") => Some(Lang::Ja),
+            Some("zh") => Some(Lang::ZhCn),
+            Some("en") | Some("c") | Some("posix") => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    /// Picks the language from a `--lang` argument, then from the locale
+    /// environment variables. Runs before clap, so that help output is
+    /// localized too.
+    pub fn detect(args: &[String]) -> Lang {
+        let from_args = args.iter().enumerate().find_map(|(i, arg)| {
+            let value = match arg.strip_prefix("--lang") {
+                Some("") => args.get(i + 1)?.as_str(),
+                Some(rest) => rest.strip_prefix('=')?,
+                None => return None,
+            };
+            <Lang as clap::ValueEnum>::from_str(value, true).ok()
+        });
+        from_args
+            .or_else(|| {
+                ["LC_ALL", "LC_MESSAGES", "LANG"]
+                    .iter()
+                    .filter_map(|var| env::var(var).ok())
+                    .find(|value| !value.is_empty())
+                    .and_then(|locale| Lang::from_locale(&locale))
+            })
+            .unwrap_or_default()
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Sets the language for the rest of the process. Only the first call has
+/// an effect.
+pub fn set_lang(lang: Lang) {
+    _ = LANG.set(lang);
+}
+
+pub fn lang() -> Lang {
+    LANG.get().copied().unwrap_or_default()
+}
+
+// key, English, Japanese, Simplified Chinese
+const MESSAGES: &[(&str, [&str; 3])] = &[
+    (
+        "about-pack",
+        [
+            "Packs a directory into a .arc file",
+            "ディレクトリを .arc ファイルにパックする",
+            "将目录打包为 .arc 文件",
+        ],
+    ),
+    (
+        "about-unpack",
+        [
+            "Unpacks a .arc file into a directory",
+            ".arc ファイルをディレクトリに展開する",
+            "将 .arc 文件解包到目录",
+        ],
+    ),
+    (
+        "about-add",
+        [
+            "Adds files to an existing .arc file",
+            "既存の .arc ファイルにファイルを追加する",
+            "向已有的 .arc 文件添加文件",
+        ],
+    ),
+    (
+        "about-remove",
+        [
+            "Removes entries from a .arc file",
+            ".arc ファイルからエントリを削除する",
+            "从 .arc 文件中删除条目",
+        ],
+    ),
+    (
+        "about-update",
+        [
+            "Replaces the data of one entry of a .arc file",
+            ".arc ファイルのエントリ 1 つのデータを差し替える",
+            "替换 .arc 文件中一个条目的数据",
+        ],
+    ),
+    (
+        "about-move",
+        [
+            "Renames one entry of a .arc file in place",
+            ".arc ファイルのエントリ 1 つの名前をその場で変更する",
+            "原地重命名 .arc 文件中的一个条目",
+        ],
+    ),
+    (
+        "about-repack",
+        [
+            "Rewrites a .arc file with the files of an overlay directory and/or after running a command on its extracted content",
+            "上書き用ディレクトリのファイルや、展開した内容に対して実行したコマンドの結果で .arc ファイルを作り直す",
+            "用覆盖目录中的文件，或在解包内容上运行命令后，重新生成 .arc 文件",
+        ],
+    ),
+    (
+        "about-merge",
+        [
+            "Combines the entries of several .arc files into one",
+            "複数の .arc ファイルのエントリを 1 つにまとめる",
+            "将多个 .arc 文件的条目合并为一个",
+        ],
+    ),
+    (
+        "about-split",
+        [
+            "Splits a .arc file into several self-contained archives of limited size",
+            ".arc ファイルをサイズ上限付きの独立した複数のアーカイブに分割する",
+            "将 .arc 文件拆分为多个大小受限且各自独立的归档",
+        ],
+    ),
+    (
+        "about-normalize",
+        [
+            "Rewrites the entry names of a .arc file to use `\\` as separator, leaving the data blocks as they are",
+            ".arc ファイルのエントリ名の区切り文字を `\\` に書き換える（データブロックはそのまま）",
+            "将 .arc 文件条目名的分隔符改写为 `\\`，数据块保持不变",
+        ],
+    ),
+    (
+        "about-optimize",
+        [
+            "Rewrites a .arc file with its data blocks back to back, dropping gaps and stale data left by edits",
+            ".arc ファイルのデータブロックを詰めて書き直し、編集で残った隙間や不要なデータを取り除く",
+            "紧凑地重写 .arc 文件的数据块，去除编辑留下的空隙和无用数据",
+        ],
+    ),
+    (
+        "about-grep",
+        [
+            "Searches the decompressed entries of a .arc file for a pattern",
+            ".arc ファイルの展開後のエントリからパターンを検索する",
+            "在 .arc 文件解压后的条目中搜索模式",
+        ],
+    ),
+    (
+        "about-list",
+        [
+            "Lists the entries of a .arc file without extracting them",
+            ".arc ファイルのエントリを展開せずに一覧表示する",
+            "列出 .arc 文件中的条目（不解包）",
+        ],
+    ),
+    (
+        "about-info",
+        [
+            "Prints a summary of a .arc file's metadata",
+            ".arc ファイルのメタデータの概要を表示する",
+            "显示 .arc 文件元数据的概要",
+        ],
+    ),
+    (
+        "about-stats",
+        [
+            "Prints per-extension compression statistics",
+            "拡張子ごとの圧縮統計を表示する",
+            "按扩展名显示压缩统计",
+        ],
+    ),
+    (
+        "about-name-tool",
+        [
+            "Encrypts or decrypts single entry names",
+            "エントリ名を個別に暗号化・復号する",
+            "加密或解密单个条目名",
+        ],
+    ),
+    (
+        "about-lzss",
+        [
+            "Compresses or decompresses loose files with the Silky LZSS parameters",
+            "Silky の LZSS パラメータで単体ファイルを圧縮・展開する",
+            "使用 Silky 的 LZSS 参数压缩或解压单个文件",
+        ],
+    ),
+    (
+        "about-dupes",
+        [
+            "Reports groups of entries with identical content",
+            "内容が同一のエントリのグループを報告する",
+            "报告内容相同的条目分组",
+        ],
+    ),
+    (
+        "about-lint",
+        [
+            "Checks a directory for files the engine cannot handle before packing",
+            "パック前に、エンジンが扱えないファイルがないか確認する",
+            "打包前检查目录中引擎无法处理的文件",
+        ],
+    ),
+    (
+        "about-compat-check",
+        [
+            "Compares this tool's extraction of a .arc file against a directory extracted by another tool",
+            "このツールによる .arc ファイルの展開結果を、別のツールで展開したディレクトリと比較する",
+            "将本工具解包 .arc 文件的结果与其他工具解包的目录进行比较",
+        ],
+    ),
+    (
+        "about-compare",
+        [
+            "Lists the files only in a .arc file or only in a directory, and the ones whose content differs",
+            ".arc ファイルとディレクトリの一方にしかないファイルと、内容が異なるファイルを一覧表示する",
+            "列出仅存在于 .arc 文件或目录一方的文件，以及内容不同的文件",
+        ],
+    ),
+    (
+        "about-verify",
+        [
+            "Checks every entry of a .arc file and prints a pass/fail table",
+            ".arc ファイルの全エントリを検査し、合否の一覧を表示する",
+            "检查 .arc 文件的每个条目并输出通过/失败列表",
+        ],
+    ),
+    (
+        "about-checksum",
+        [
+            "Prints the SHA-256 of every decompressed entry of a .arc file, in the format `unpack --checksums` reads, and of the whole content",
+            ".arc ファイルの展開後の各エントリと内容全体の SHA-256 を、`unpack --checksums` が読める形式で表示する",
+            "以 `unpack --checksums` 可读取的格式输出 .arc 文件每个解压后条目及整体内容的 SHA-256",
+        ],
+    ),
+    (
+        "about-diff",
+        [
+            "Lists the entries added, removed or modified between two .arc files",
+            "2 つの .arc ファイル間で追加・削除・変更されたエントリを一覧表示する",
+            "列出两个 .arc 文件之间新增、删除或修改的条目",
+        ],
+    ),
+    (
+        "about-make-patch",
+        [
+            "Writes a patch turning one version of a .arc file into another",
+            ".arc ファイルのある版を別の版に変換するパッチを作成する",
+            "生成将 .arc 文件的一个版本转换为另一个版本的补丁",
+        ],
+    ),
+    (
+        "about-apply-patch",
+        [
+            "Rebuilds a .arc file from its base version and a patch",
+            "元の版とパッチから .arc ファイルを再構築する",
+            "根据原版本和补丁重建 .arc 文件",
+        ],
+    ),
+    (
+        "about-doctor",
+        [
+            "Diagnoses common problems in a .arc file",
+            ".arc ファイルのよくある問題を診断する",
+            "诊断 .arc 文件的常见问题",
+        ],
+    ),
+    (
+        "about-wizard",
+        [
+            "Asks what to do step by step and runs the matching command",
+            "対話形式で操作を選び、対応するコマンドを実行する",
+            "以问答方式选择操作并运行相应的命令",
+        ],
+    ),
+    (
+        "about-browse",
+        [
+            "Browses a .arc file as a folder tree and extracts selected entries",
+            ".arc ファイルをフォルダツリーとして閲覧し、選択したエントリを展開する",
+            "以文件夹树浏览 .arc 文件并解包所选条目",
+        ],
+    ),
+    (
+        "about-script",
+        [
+            "Runs a rhai script against the archive API",
+            "アーカイブ API を使う rhai スクリプトを実行する",
+            "运行调用归档 API 的 rhai 脚本",
+        ],
+    ),
+    (
+        "packed-archives",
+        [
+            "Packed {ok} of {total} archives:",
+            "{total} 個中 {ok} 個のアーカイブをパックしました：",
+            "已打包 {ok}/{total} 个归档：",
+        ],
+    ),
+    (
+        "unpacked-archives",
+        [
+            "Unpacked {ok} of {total} archives:",
+            "{total} 個中 {ok} 個のアーカイブを展開しました：",
+            "已解包 {ok}/{total} 个归档：",
+        ],
+    ),
+    (
+        "entries-parsed",
+        [
+            "{count} entries parsed",
+            "{count} 個のエントリを解析しました",
+            "已解析 {count} 个条目",
+        ],
+    ),
+    (
+        "no-problems",
+        [
+            "No problems found.",
+            "問題は見つかりませんでした。",
+            "未发现问题。",
+        ],
+    ),
+    (
+        "no-provenance",
+        [
+            "The archive has no provenance entry.",
+            "このアーカイブには来歴エントリがありません。",
+            "该档案没有来源信息条目。",
+        ],
+    ),
+    (
+        "verify-summary",
+        [
+            "{passed} of {total} entries passed",
+            "{total} 件中 {passed} 件のエントリが合格",
+            "{total} 个条目中 {passed} 个通过",
+        ],
+    ),
+    (
+        "lzss-wasted",
+        [
+            "* LZSS saves less than {percent}% on these; consider packing them with --store \"{patterns}\"",
+            "* LZSS による削減が {percent}% 未満です。--store \"{patterns}\" を付けてのパックを検討してください",
+            "* LZSS 节省不足 {percent}%，可考虑使用 --store \"{patterns}\" 打包",
+        ],
+    ),
+    (
+        "patch-summary",
+        [
+            "Patch of {size}: {included} entries included, {copied} taken from the base archive",
+            "パッチ {size}：{included} 件のエントリを収録、{copied} 件は元のアーカイブから流用",
+            "补丁 {size}：包含 {included} 个条目，{copied} 个取自原归档",
+        ],
+    ),
+    (
+        "normalize-summary",
+        [
+            "{count} entries renamed",
+            "{count} 件のエントリ名を変更しました",
+            "已重命名 {count} 个条目",
+        ],
+    ),
+    (
+        "optimize-summary",
+        [
+            "{before} -> {after} ({saved} saved), {recompressed} entries compressed again",
+            "{before} -> {after}（{saved} 削減）、{recompressed} 件のエントリを再圧縮",
+            "{before} -> {after}（节省 {saved}），重新压缩了 {recompressed} 个条目",
+        ],
+    ),
+    (
+        "split-summary",
+        [
+            "Split into {count} archives:",
+            "{count} 個のアーカイブに分割しました：",
+            "已拆分为 {count} 个归档：",
+        ],
+    ),
+    (
+        "diff-summary",
+        [
+            "{count} entries differ",
+            "{count} 件のエントリが異なる",
+            "{count} 个条目不同",
+        ],
+    ),
+    (
+        "dupes-summary",
+        [
+            "{groups} group(s) of identical entries, {wasted} could be saved by deduplication",
+            "同一内容のエントリが {groups} グループ、重複排除で {wasted} 削減可能",
+            "{groups} 组内容相同的条目，去重可节省 {wasted}",
+        ],
+    ),
+    ("hint", ["hint", "ヒント", "提示"]),
+    (
+        "compat-summary",
+        [
+            "{matched} entries identical, {divergences} divergence(s)",
+            "{matched} 個のエントリが一致、相違 {divergences} 件",
+            "{matched} 个条目一致，{divergences} 处差异",
+        ],
+    ),
+    (
+        "error-empty-input",
+        [
+            "No files to pack in {path}. Pass --allow-empty to create an empty archive anyway.",
+            "{path} にパックするファイルがありません。空のアーカイブを作成するには --allow-empty を指定してください。",
+            "{path} 中没有可打包的文件。如需创建空归档，请使用 --allow-empty。",
+        ],
+    ),
+    (
+        "error-not-found",
+        [
+            "File not found: {path}",
+            "ファイルが見つかりません：{path}",
+            "找不到文件：{path}",
+        ],
+    ),
+    (
+        "error-verification",
+        [
+            "Verification failed for {name}: {reason}",
+            "{name} の検証に失敗しました：{reason}",
+            "{name} 校验失败：{reason}",
+        ],
+    ),
+];
+
+/// Message for `key` in the current language.
+pub fn tr(key: &str) -> &'static str {
+    let index = match lang() {
+        Lang::En => 0,
+        Lang::Ja => 1,
+        Lang::ZhCn => 2,
+    };
+    MESSAGES
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, messages)| messages[index])
+        .unwrap_or_else(|| panic!("missing message {key:?}"))
+}
+
+/// Like [`tr`], substituting `{name}` placeholders.
+pub fn trf(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut message = tr(key).to_string();
+    for (name, value) in args {
+        message = message.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    message
+}
+
+/// Localized top-level error message. Errors without a translation use
+/// their English description.
+pub fn error_message(error: &ArcError) -> String {
+    match error {
+        ArcError::NotFound(path) => trf("error-not-found", &[("path", &path.display())]),
+        ArcError::EmptyInput(path) => trf("error-empty-input", &[("path", &path.display())]),
+        ArcError::VerificationFailed(name, reason) => {
+            trf("error-verification", &[("name", name), ("reason", reason)])
+        }
+        error => error.to_string(),
+    }
+}
+
+/// Replaces the subcommand descriptions with their translations.
+pub fn localize_command(mut command: Command) -> Command {
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in names {
+        let key = format!("about-{name}");
+        if MESSAGES.iter().any(|(k, _)| *k == key) {
+            command = command.mut_subcommand(&name, |sub| sub.about(tr(&key)));
+        }
+    }
+    command
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    error::{ArcError, NameError},
+    name,
+};
+
+// --- Pre-pack Linting ---
+// Checks a directory for files the engine cannot handle before any archive
+// is written. Names are checked by `name::validate_entry_name`; sizes and
+// offsets are stored as u32, which gives the size limit below.
+
+/// Maximum size of a single entry in bytes.
+pub const MAX_FILE_SIZE: u64 = u32::MAX as u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    NameTooLong,
+    NotEncodable,
+    CaseCollision,
+    EmptyFile,
+    ForbiddenCharacter,
+    FileTooLarge,
+    UnexpectedExtension,
+    AmbiguousCharacter,
+}
+
+impl LintKind {
+    /// Stable identifier used in machine-readable output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LintKind::NameTooLong => "name-too-long",
+            LintKind::NotEncodable => "not-encodable",
+            LintKind::CaseCollision => "case-collision",
+            LintKind::EmptyFile => "empty-file",
+            LintKind::ForbiddenCharacter => "forbidden-character",
+            LintKind::FileTooLarge => "file-too-large",
+            LintKind::UnexpectedExtension => "unexpected-extension",
+            LintKind::AmbiguousCharacter => "ambiguous-character",
+        }
+    }
+
+    /// Whether packing would fail or produce an unusable archive, as opposed
+    /// to merely suspicious input.
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            LintKind::EmptyFile | LintKind::UnexpectedExtension | LintKind::AmbiguousCharacter
+        )
+    }
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    /// Path relative to the linted directory.
+    pub path: PathBuf,
+    pub kind: LintKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+    /// Lowercased extensions (without dot) that are expected in the archive.
+    /// `None` disables the extension check.
+    pub allowed_extensions: Option<Vec<String>>,
+}
+
+pub fn lint_directory(
+    input_dir: impl AsRef<Path>,
+    options: &LintOptions,
+) -> Result<Vec<LintIssue>, ArcError> {
+    let input_dir = input_dir.as_ref();
+    if !input_dir.is_dir() {
+        return Err(ArcError::NotFound(input_dir.to_path_buf()));
+    }
+
+    let mut issues = Vec::new();
+    let mut by_lowercase: HashMap<String, PathBuf> = HashMap::new();
+    for entry in WalkDir::new(input_dir).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path().strip_prefix(input_dir)?.to_path_buf();
+        let name = name::path_to_entry_name(&path)
+            .unwrap_or_else(|_| path.to_string_lossy().replace("/", "\\"));
+        let mut issue = |kind, message| {
+            issues.push(LintIssue {
+                path: path.clone(),
+                kind,
+                message,
+            })
+        };
+
+        let mapped = name::map_cp932_fallbacks(&name, name::CP932_FALLBACKS);
+        if mapped != name {
+            issue(
+                LintKind::AmbiguousCharacter,
+                format!("name will be stored as {mapped:?}"),
+            );
+        }
+
+        if let Err(e) = name::validate_entry_name(&mapped) {
+            let kind = match e {
+                NameError::NotEncodable => LintKind::NotEncodable,
+                NameError::TooLong(_) => LintKind::NameTooLong,
+                _ => LintKind::ForbiddenCharacter,
+            };
+            issue(kind, e.to_string());
+        }
+
+        let size = entry.metadata()?.len();
+        if size == 0 {
+            issue(LintKind::EmptyFile, "file is empty".to_string());
+        } else if size > MAX_FILE_SIZE {
+            issue(
+                LintKind::FileTooLarge,
+                format!("file is {size} bytes, the limit is {MAX_FILE_SIZE}"),
+            );
+        }
+
+        if let Some(allowed) = &options.allowed_extensions {
+            let extension = crate::stats::entry_extension(&name);
+            if !allowed.contains(&extension) {
+                issue(
+                    LintKind::UnexpectedExtension,
+                    format!("extension {extension:?} is not in the allowed list"),
+                );
+            }
+        }
+
+        if let Some(other) = by_lowercase.insert(mapped.to_lowercase(), path.clone()) {
+            issues.push(LintIssue {
+                message: format!("name differs only in case from {other:?}"),
+                path,
+                kind: LintKind::CaseCollision,
+            });
+        }
+    }
+    Ok(issues)
+}
@@ -1,74 +1,1288 @@
-pub mod cli;
-pub mod error;
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io, iter,
+    path::Path,
+    process::{self, ExitCode},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use std::path::PathBuf;
+use clap::{CommandFactory, FromArgMatches};
+use log::{error, info};
+use rayon::prelude::*;
+use silky_arc_tool::{
+    DEFAULT_MIN_COMPRESS_SIZE, PackOptions, ResumeMode, UnpackOptions,
+    backup::backup_archive,
+    batch::{expand_file_pattern, find_archives, subdirectories},
+    checksum::{archive_checksums, read_checksum_file},
+    cli::{BackupArgs, Cli, Commands, EditArgs, LzssAction, NameAction, SizeKey, parse_hex},
+    compat::{DivergenceKind, compat_check},
+    compress_data, decompress_data,
+    diff::{Change, diff_archives},
+    doctor::diagnose,
+    dupes::find_duplicates,
+    edit::{ArchiveEdit, edit_archive, overlay_edit},
+    editor::ArcEditor,
+    encrypt_name,
+    error::{ArcError, PathContext},
+    grep::{GrepOptions, GrepPattern, grep_archive},
+    handle_pack_with, handle_unpack_with,
+    i18n::{self, Lang, tr, trf},
+    lint::{LintOptions, lint_directory},
+    manifest::pack_manifest,
+    merge::merge_archives,
+    metrics::Metrics,
+    name,
+    normalize::{NormalizeOptions, normalize_archive},
+    optimize::optimize_archive,
+    output::{
+        DEFAULT_FILE_PACK_TEMPLATE, UnpackOutput, derive_pack_output, derive_pack_output_in,
+        resolve_unpack_output,
+    },
+    pack_dry_run,
+    patch::{apply_patch, make_patch},
+    pattern::NamePatterns,
+    progress::{ProgressBar, ProgressOutput, format_bytes},
+    provenance::{PROVENANCE_NAME, read_provenance},
+    reader::{ArcEntry, ArcReader, shared_data_sources},
+    selection::{normalize_entry_name, read_entry_list},
+    split::split_archive,
+    stats::{WASTED_LZSS_SAVING, archive_totals, extension_stats},
+    verify::verify_archive,
+    verify_pack_with, watch, wizard,
+    zip_source::pack_zip,
+};
+use walkdir::WalkDir;
 
-use clap::Parser as _;
-use cli::{Cli, Commands};
-use path_absolutize::Absolutize;
-use silky_arc_tool::{error::ArcError, handle_pack, handle_unpack};
-use tap::Tap;
+/// Backs up `archive` before it gets overwritten, unless disabled.
+fn backup_if_exists(archive: &Path, args: &BackupArgs) -> Result<(), ArcError> {
+    if !args.no_backup && archive.is_file() {
+        backup_archive(archive, args.backup_dir.as_deref())?;
+    }
+    Ok(())
+}
+
+/// Applies `archive_edit` to `archive`, in place unless `--output` is given.
+fn run_edit(
+    archive: &Path,
+    archive_edit: &ArchiveEdit,
+    args: &EditArgs,
+    options: &PackOptions,
+    progress: &Option<ProgressOutput>,
+) -> Result<(), ArcError> {
+    let output_path = args.output.as_deref().unwrap_or(archive);
+    backup_if_exists(output_path, &args.backup)?;
+    let summary = with_progress(progress, "edit", |metrics| {
+        edit_archive(
+            archive,
+            output_path,
+            archive_edit,
+            &PackOptions {
+                metrics,
+                ..options.clone()
+            },
+        )
+    })?;
+    info!(
+        "Wrote {} entries to {:?} ({} bytes)",
+        summary.entry_count(),
+        output_path,
+        summary.archive_size()
+    );
+    Ok(())
+}
+
+/// Copies the files below `from` into `to`, replacing existing ones.
+fn copy_tree(from: &Path, to: &Path) -> Result<(), ArcError> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target).with_path(&target)?;
+        } else if entry.file_type().is_file() {
+            fs::copy(entry.path(), &target).with_path(&target)?;
+        }
+    }
+    Ok(())
+}
 
-fn main() -> Result<(), ArcError> {
+/// Runs `command` through the shell with `dir` as its first argument and
+/// returns whether it succeeded.
+fn run_hook(command: &str, dir: &Path) -> Result<bool, ArcError> {
+    info!("Running {command:?} on {dir:?}");
+    let mut shell = if cfg!(windows) {
+        let mut shell = process::Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = process::Command::new("sh");
+        shell.arg("-c").arg(command).arg("sh");
+        shell
+    };
+    let status = shell.arg(dir).status()?;
+    Ok(status.success())
+}
+
+/// Exit code used when packing found no files and `--allow-empty` was not
+/// given. Distinct from clap's usage error code (2).
+const EXIT_EMPTY_INPUT: u8 = 3;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    i18n::set_lang(Lang::detect(&args));
+    let matches = i18n::localize_command(Cli::command()).get_matches_from(&args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // Per-file messages would scroll the progress line away
+    let level = match cli.progress || cli.progress_json.is_some() {
+        true => log::LevelFilter::Warn,
+        false => log::LevelFilter::Info,
+    };
     _ = pretty_env_logger::formatted_builder()
-        .filter_level(log::LevelFilter::Info)
+        .filter_level(level)
         .format_timestamp_secs()
         .parse_default_env()
         .try_init();
 
-    let cli = Cli::parse();
+    match run(cli) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("{}", i18n::error_message(&e));
+            match e {
+                ArcError::EmptyInput(_) => ExitCode::from(EXIT_EMPTY_INPUT),
+                _ => ExitCode::FAILURE,
+            }
+        }
+    }
+}
+
+/// Runs `op` with fresh metrics that are reported to `output`, if any.
+fn with_progress<T>(
+    output: &Option<ProgressOutput>,
+    phase: &'static str,
+    op: impl FnOnce(Option<Arc<Metrics>>) -> T,
+) -> T {
+    let Some(output) = output else {
+        return op(None);
+    };
+    let metrics = Arc::new(Metrics::new());
+    let bar = ProgressBar::start(metrics.clone(), output.clone(), phase);
+    let result = op(Some(metrics));
+    bar.finish();
+    result
+}
+
+fn run(cli: Cli) -> Result<ExitCode, ArcError> {
+    if let Some(jobs) = cli.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .map_err(|e| ArcError::ThreadPool(e.to_string()))?;
+    }
+
+    let progress = match &cli.progress_json {
+        Some(path) if path.as_os_str() == "-" => {
+            Some(ProgressOutput::Json(Arc::new(Mutex::new(io::stderr()))))
+        }
+        Some(path) => {
+            let file = File::create(path).with_path(path)?;
+            Some(ProgressOutput::Json(Arc::new(Mutex::new(file))))
+        }
+        None => cli.progress.then_some(ProgressOutput::Line),
+    };
 
     match cli.command {
         Commands::Pack {
             input,
             output,
+            from_zip,
+            manifest,
+            each,
+            output_template,
+            output_dir,
             compress,
+            min_compress_size,
+            store,
+            verify,
+            temp_dir,
+            memory_limit,
+            provenance,
+            keep_empty_dirs,
+            dedup,
+            separator,
+            order,
+            allow_empty,
+            watch,
+            debounce,
+            map_chars,
+            backup,
         } => {
-            let output_path = output.unwrap_or_else(|| {
-                // Default output: input + .arc in the same directory
-                PathBuf::from(
-                    input
-                        .absolutize()
-                        .expect("cannot absolutize input path")
-                        .as_os_str()
-                        .to_owned()
-                        .tap_mut(|x| x.push(".arc")),
-                )
+            let options = PackOptions {
+                compress,
+                min_compress_size: Some(min_compress_size),
+                store_patterns: (!store.is_empty())
+                    .then(|| NamePatterns::new(&store))
+                    .transpose()?,
+                temp_dir,
+                memory_limit,
+                allow_empty,
+                // User mappings take precedence over the built-in ones
+                name_fallbacks: (!map_chars.is_empty())
+                    .then(|| [map_chars.as_slice(), name::CP932_FALLBACKS].concat()),
+                strict: cli.abort_on_warning,
+                provenance,
+                keep_empty_dirs,
+                dedup,
+                separator,
+                order,
+                ..Default::default()
+            };
+            let output_template = output_template.or_else(|| {
+                (from_zip || manifest.is_some()).then(|| DEFAULT_FILE_PACK_TEMPLATE.to_string())
             });
-            // Check if derived path is same as input dir path, which is invalid
-            if output_path == input {
-                return Err(ArcError::CannotDeriveOutputPath(input));
-            }
-            handle_pack(&input, &output_path, compress)?;
-        }
-        Commands::Unpack { input, output } => {
-            let output_dir = output.unwrap_or_else(|| {
-                // Default output: input filename (no ext) in the same directory
-                let mut derived = input.with_extension("");
-                // If removing extension resulted in empty filename (e.g. ".arc"), use base name
-                if derived.file_name().is_none() || derived.file_name().unwrap().is_empty() {
-                    derived = input
-                        .file_name()
-                        .map(|name| input.with_file_name(name))
-                        .unwrap_or_else(|| PathBuf::from("output_dir")); // Fallback
+            let derive = |dir: &Path| match &output_dir {
+                Some(output_dir) => {
+                    derive_pack_output_in(dir, output_template.as_deref(), output_dir)
                 }
-                // Avoid unpacking into archive itself if names clash after removing ext
-                if derived == input {
-                    derived.set_file_name(format!(
-                        "{}_unpacked",
-                        derived.file_name().unwrap_or_default().to_string_lossy()
-                    ));
+                None => derive_pack_output(dir, output_template.as_deref()),
+            };
+            if input.len() > 1 && output.is_some() {
+                Cli::command()
+                    .error(
+                        clap::error::ErrorKind::ArgumentConflict,
+                        "--output cannot be used with several inputs",
+                    )
+                    .exit();
+            }
+            let input = match &manifest {
+                Some(manifest) => vec![manifest.clone()],
+                None => input,
+            };
+            let batch = each || input.len() > 1;
+            let mut jobs = Vec::new();
+            for input in input {
+                if each {
+                    for dir in subdirectories(&input)? {
+                        jobs.push((derive(&dir)?, dir));
+                    }
+                } else {
+                    let output_path = match &output {
+                        Some(output) => output.clone(),
+                        None => derive(&input)?,
+                    };
+                    jobs.push((output_path, input));
+                }
+            }
+            if let Some(output_dir) = &output_dir {
+                fs::create_dir_all(output_dir).with_path(output_dir)?;
+            }
+            let run_job = |output_path: &Path, input: &Path, metrics: Option<Arc<Metrics>>| {
+                backup_if_exists(output_path, &backup)?;
+                let job_options = PackOptions {
+                    metrics,
+                    ..options.clone()
+                };
+                let summary = if manifest.is_some() {
+                    pack_manifest(input, output_path, &job_options)?
+                } else if from_zip {
+                    pack_zip(input, output_path, &job_options)?
+                } else {
+                    handle_pack_with(input, output_path, &job_options)?
+                };
+                info!(
+                    "Packed {} entries into {:?} ({} bytes)",
+                    summary.entry_count(),
+                    output_path,
+                    summary.archive_size()
+                );
+                if verify {
+                    verify_pack_with(input, output_path, &options)?;
                 }
-                derived
+                Ok::<_, ArcError>(summary)
+            };
+            // Progress is reported for all jobs together
+            let results = with_progress(&progress, "pack", |metrics| {
+                jobs.par_iter()
+                    .map(|(output_path, input)| run_job(output_path, input, metrics.clone()))
+                    .collect::<Vec<_>>()
             });
-            // Prevent unpacking directly into the archive file itself
-            if output_dir == input {
-                return Err(ArcError::CannotDeriveOutputPath(input));
+            let rebuild_on_change = || {
+                let (outputs, inputs): (Vec<_>, Vec<_>) = jobs.iter().cloned().unzip();
+                watch::watch(
+                    &inputs,
+                    &outputs,
+                    Duration::from_millis(debounce),
+                    |changed| {
+                        for &index in changed {
+                            let (output_path, input) = &jobs[index];
+                            // A failed rebuild keeps the previous archive
+                            if let Err(e) = run_job(output_path, input, None) {
+                                error!("Failed to repack {:?}: {}", input, e);
+                            }
+                        }
+                        true
+                    },
+                )
+            };
+            if !batch {
+                results
+                    .into_iter()
+                    .try_for_each(|result| result.map(drop))?;
+                if watch {
+                    rebuild_on_change()?;
+                }
+                return Ok(ExitCode::SUCCESS);
             }
 
-            handle_unpack(&input, &output_dir)?;
+            let failed = results.iter().filter(|result| result.is_err()).count();
+            println!(
+                "{}",
+                trf(
+                    "packed-archives",
+                    &[("ok", &(jobs.len() - failed)), ("total", &jobs.len())]
+                )
+            );
+            for ((output_path, input), result) in jobs.iter().zip(results) {
+                let status = match result {
+                    Ok(summary) => format!(
+                        "{} entries, {}",
+                        summary.entry_count(),
+                        format_bytes(summary.archive_size())
+                    ),
+                    Err(e) => {
+                        error!("Failed to pack {:?}: {}", input, e);
+                        format!("failed: {e}")
+                    }
+                };
+                println!(
+                    "  {} -> {}: {status}",
+                    input.display(),
+                    output_path.display()
+                );
+            }
+            if watch {
+                rebuild_on_change()?;
+            }
+            if failed > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Unpack {
+            input,
+            output,
+            output_template,
+            here,
+            each,
+            recursive,
+            resume,
+            verify_existing,
+            verify,
+            checksums,
+            encodings,
+            hardlink_shared,
+            memory_limit,
+            entries_from,
+            only,
+            include,
+            exclude,
+            size,
+            rename_rules,
+            overwrite,
+            flatten,
+            keep_going,
+        } => {
+            let options = UnpackOptions {
+                resume: match (resume, verify_existing) {
+                    (false, _) => None,
+                    (true, false) => Some(ResumeMode::Size),
+                    (true, true) => Some(ResumeMode::Content),
+                },
+                verify: verify || checksums.is_some(),
+                checksums: checksums.map(read_checksum_file).transpose()?,
+                name_encodings: encodings,
+                hardlink_shared,
+                memory_limit,
+                strict: cli.abort_on_warning,
+                entries: if only.is_empty() {
+                    entries_from.map(read_entry_list).transpose()?
+                } else {
+                    Some(only.iter().map(|name| normalize_entry_name(name)).collect())
+                },
+                require_entries: !only.is_empty(),
+                include: (!include.is_empty())
+                    .then(|| NamePatterns::new(&include))
+                    .transpose()?,
+                exclude: (!exclude.is_empty())
+                    .then(|| NamePatterns::new(&exclude))
+                    .transpose()?,
+                size_range: size.range(),
+                rename_rules,
+                overwrite,
+                flatten,
+                keep_going,
+                ..Default::default()
+            };
+
+            let current_dir = env::current_dir()?;
+            let target = match (&output, here) {
+                (Some(output), _) => UnpackOutput::Explicit(output),
+                (None, true) => UnpackOutput::Below(&current_dir),
+                (None, false) => UnpackOutput::Derived(output_template.as_deref()),
+            };
+            let mut jobs = Vec::new();
+            if each {
+                for input in &input {
+                    for archive in find_archives(input, recursive)? {
+                        let output_dir = resolve_unpack_output(&archive, Some(input), target)?;
+                        jobs.push((archive, output_dir));
+                    }
+                }
+            } else {
+                let mut archives = Vec::new();
+                for pattern in &input {
+                    archives.extend(expand_file_pattern(pattern)?);
+                }
+                // Several archives cannot share one explicit output directory
+                let target = match target {
+                    UnpackOutput::Explicit(output) if archives.len() > 1 => {
+                        UnpackOutput::Below(output)
+                    }
+                    target => target,
+                };
+                for archive in archives {
+                    let output_dir = resolve_unpack_output(&archive, None, target)?;
+                    jobs.push((archive, output_dir));
+                }
+            }
+            let batch = each || jobs.len() > 1;
+            // Progress is reported for all jobs together
+            let results = with_progress(&progress, "unpack", |metrics| {
+                jobs.par_iter()
+                    .map(|(archive, output_dir)| {
+                        handle_unpack_with(
+                            archive,
+                            output_dir,
+                            &UnpackOptions {
+                                metrics: metrics.clone(),
+                                ..options.clone()
+                            },
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            });
+            if !batch {
+                results.into_iter().try_for_each(|result| result)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let failed = results.iter().filter(|result| result.is_err()).count();
+            println!(
+                "{}",
+                trf(
+                    "unpacked-archives",
+                    &[("ok", &(jobs.len() - failed)), ("total", &jobs.len())]
+                )
+            );
+            for ((archive, output_dir), result) in jobs.iter().zip(results) {
+                let status = match result {
+                    Ok(()) => "ok".to_string(),
+                    Err(e) => {
+                        error!("Failed to unpack {:?}: {}", archive, e);
+                        format!("failed: {e}")
+                    }
+                };
+                println!(
+                    "  {} -> {}: {status}",
+                    archive.display(),
+                    output_dir.display()
+                );
+            }
+            if failed > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Add {
+            archive,
+            files,
+            prefix,
+            compress,
+            edit,
+        } => {
+            let prefix = prefix
+                .map(|prefix| normalize_entry_name(&prefix))
+                .filter(|prefix| !prefix.is_empty());
+            let additions = files
+                .into_iter()
+                .map(|file| {
+                    let file_name = file
+                        .file_name()
+                        .ok_or_else(|| ArcError::NoFilename(file.clone()))?
+                        .to_string_lossy()
+                        .into_owned();
+                    let entry_name = match &prefix {
+                        Some(prefix) => format!(
+                            "{}{}{file_name}",
+                            prefix.trim_end_matches(name::SEPARATOR),
+                            name::SEPARATOR
+                        ),
+                        None => file_name,
+                    };
+                    Ok((entry_name, file))
+                })
+                .collect::<Result<_, ArcError>>()?;
+            let archive_edit = ArchiveEdit {
+                additions,
+                ..Default::default()
+            };
+            let options = PackOptions {
+                compress,
+                strict: cli.abort_on_warning,
+                ..Default::default()
+            };
+            run_edit(&archive, &archive_edit, &edit, &options, &progress)?;
+        }
+        Commands::Remove {
+            archive,
+            entries,
+            edit,
+        } => {
+            let archive_edit = ArchiveEdit {
+                removals: entries
+                    .iter()
+                    .map(|name| normalize_entry_name(name))
+                    .collect(),
+                ..Default::default()
+            };
+            let options = PackOptions {
+                strict: cli.abort_on_warning,
+                ..Default::default()
+            };
+            run_edit(&archive, &archive_edit, &edit, &options, &progress)?;
+        }
+        Commands::Update {
+            archive,
+            entry,
+            file,
+            compress,
+            edit,
+        } => {
+            let archive_edit = ArchiveEdit {
+                replacements: HashMap::from([(normalize_entry_name(&entry), file)]),
+                ..Default::default()
+            };
+            let options = PackOptions {
+                compress,
+                strict: cli.abort_on_warning,
+                ..Default::default()
+            };
+            run_edit(&archive, &archive_edit, &edit, &options, &progress)?;
+        }
+        Commands::Move {
+            archive,
+            from,
+            to,
+            backup,
+        } => {
+            backup_if_exists(&archive, &backup)?;
+            let mut editor = ArcEditor::open(&archive)?;
+            editor.rename(&normalize_entry_name(&from), &to)?;
+            let summary = editor.commit()?;
+            info!(
+                "Renamed {:?} to {:?} in {:?} ({} bytes written)",
+                from, to, archive, summary.written
+            );
+        }
+        Commands::Repack {
+            archive,
+            overlay,
+            exec,
+            compress,
+            edit,
+        } => {
+            let options = PackOptions {
+                compress,
+                strict: cli.abort_on_warning,
+                ..Default::default()
+            };
+            let Some(command) = exec else {
+                let overlay = overlay.expect("clap requires --overlay or --exec");
+                let archive_edit = overlay_edit(&archive, &overlay)?;
+                run_edit(&archive, &archive_edit, &edit, &options, &progress)?;
+                return Ok(ExitCode::SUCCESS);
+            };
+
+            let work_dir = tempfile::tempdir()?;
+            with_progress(&progress, "unpack", |metrics| {
+                handle_unpack_with(
+                    &archive,
+                    work_dir.path(),
+                    &UnpackOptions {
+                        metrics,
+                        strict: cli.abort_on_warning,
+                        ..Default::default()
+                    },
+                )
+            })?;
+            if let Some(overlay) = &overlay {
+                copy_tree(overlay, work_dir.path())?;
+            }
+            if !run_hook(&command, work_dir.path())? {
+                error!("{command:?} failed, the archive was left unchanged");
+                return Ok(ExitCode::FAILURE);
+            }
+            let output_path = edit.output.as_deref().unwrap_or(&archive);
+            backup_if_exists(output_path, &edit.backup)?;
+            with_progress(&progress, "pack", |metrics| {
+                handle_pack_with(
+                    work_dir.path(),
+                    output_path,
+                    &PackOptions {
+                        metrics,
+                        allow_empty: true,
+                        ..options.clone()
+                    },
+                )
+            })?;
+        }
+        Commands::Merge {
+            archives,
+            output,
+            on_conflict,
+            backup,
+        } => {
+            backup_if_exists(&output, &backup)?;
+            let summary = with_progress(&progress, "merge", |metrics| {
+                merge_archives(
+                    &archives,
+                    &output,
+                    on_conflict,
+                    &PackOptions {
+                        metrics,
+                        strict: cli.abort_on_warning,
+                        ..Default::default()
+                    },
+                )
+            })?;
+            info!(
+                "Merged {} entries into {:?} ({} bytes)",
+                summary.entry_count(),
+                output,
+                summary.archive_size()
+            );
+        }
+        Commands::Split {
+            input,
+            max_size,
+            output_dir,
+        } => {
+            let output_dir = output_dir
+                .unwrap_or_else(|| input.parent().map(Path::to_path_buf).unwrap_or_default());
+            fs::create_dir_all(&output_dir).with_path(&output_dir)?;
+            let parts = with_progress(&progress, "split", |metrics| {
+                split_archive(
+                    &input,
+                    &output_dir,
+                    max_size,
+                    &PackOptions {
+                        metrics,
+                        strict: cli.abort_on_warning,
+                        ..Default::default()
+                    },
+                )
+            })?;
+            println!("{}", trf("split-summary", &[("count", &parts.len())]));
+            for (path, summary) in parts {
+                println!(
+                    "  {}: {} entries, {}",
+                    path.display(),
+                    summary.entry_count(),
+                    format_bytes(summary.archive_size())
+                );
+            }
+        }
+        Commands::Optimize {
+            archive,
+            recompress,
+            min_compress_size,
+            store,
+            no_dedup,
+            edit,
+        } => {
+            let output_path = edit.output.as_deref().unwrap_or(&archive);
+            backup_if_exists(output_path, &edit.backup)?;
+            let options = PackOptions {
+                compress: recompress,
+                min_compress_size: Some(min_compress_size),
+                store_patterns: (!store.is_empty())
+                    .then(|| NamePatterns::new(&store))
+                    .transpose()?,
+                dedup: !no_dedup,
+                strict: cli.abort_on_warning,
+                ..Default::default()
+            };
+            let summary = with_progress(&progress, "optimize", |metrics| {
+                optimize_archive(
+                    &archive,
+                    output_path,
+                    &PackOptions {
+                        metrics,
+                        ..options.clone()
+                    },
+                )
+            })?;
+            println!(
+                "{}",
+                trf(
+                    "optimize-summary",
+                    &[
+                        ("before", &format_bytes(summary.original_size)),
+                        ("after", &format_bytes(summary.pack.archive_size())),
+                        ("saved", &format_bytes(summary.saved())),
+                        ("recompressed", &summary.recompressed)
+                    ]
+                )
+            );
+        }
+        Commands::Normalize {
+            archive,
+            trim_current_dir,
+            edit,
+        } => {
+            let output_path = edit.output.as_deref().unwrap_or(&archive);
+            backup_if_exists(output_path, &edit.backup)?;
+            let renamed = normalize_archive(
+                &archive,
+                output_path,
+                &NormalizeOptions { trim_current_dir },
+            )?;
+            for (old, new) in &renamed {
+                println!("{old} -> {new}");
+            }
+            println!("{}", trf("normalize-summary", &[("count", &renamed.len())]));
+        }
+        Commands::List {
+            input,
+            top,
+            by,
+            size,
+            provenance,
+            json,
+        } => {
+            let mut archive = ArcReader::open(&input)?;
+            if provenance {
+                match read_provenance(&mut archive)? {
+                    Some(document) => println!("{document}"),
+                    None => {
+                        println!("{}", tr("no-provenance"));
+                        return Ok(ExitCode::FAILURE);
+                    }
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+            let sources = shared_data_sources(archive.entries());
+            let size_range = size.range();
+            let mut entries: Vec<_> = archive
+                .entries()
+                .iter()
+                .zip(&sources)
+                .filter(|(entry, _)| size_range.contains(entry.original_size() as u64))
+                .collect();
+            if let Some(n) = top {
+                entries.sort_by_key(|(e, _)| {
+                    std::cmp::Reverse(match by {
+                        SizeKey::Original => e.original_size(),
+                        SizeKey::Compressed => e.compressed_size(),
+                    })
+                });
+                entries.truncate(n);
+            }
+            if json {
+                let entries: Vec<_> = entries
+                    .iter()
+                    .map(|(entry, source)| {
+                        let hex: Vec<String> = entry
+                            .encrypted_name()
+                            .iter()
+                            .map(|b| format!("{b:02x}"))
+                            .collect();
+                        serde_json::json!({
+                            "name": entry.name(),
+                            "encrypted_name": hex.concat(),
+                            "offset": entry.offset(),
+                            "compressed_size": entry.compressed_size(),
+                            "original_size": entry.original_size(),
+                            "compressed": entry.is_compressed(),
+                            "shares_data_with": source.map(|source| archive.entries()[source].name()),
+                        })
+                    })
+                    .collect();
+                let document = serde_json::json!({
+                    "schema_version": 1,
+                    "metadata_size": archive.metadata_size(),
+                    "name_encoding": archive.name_encoding().to_string(),
+                    "entries": entries,
+                });
+                println!("{document:#}");
+                return Ok(ExitCode::SUCCESS);
+            }
+            println!(
+                "{:>10} {:>10} {:>10}  name",
+                "offset", "compressed", "original"
+            );
+            for (entry, source) in entries {
+                let shared = match source {
+                    Some(source) => {
+                        format!(" (shares data with {})", archive.entries()[*source].name())
+                    }
+                    None => String::new(),
+                };
+                println!(
+                    "{:>10} {:>10} {:>10}  {}{shared}",
+                    entry.offset(),
+                    entry.compressed_size(),
+                    entry.original_size(),
+                    entry.name()
+                );
+            }
+        }
+        Commands::Info { input } => {
+            let archive = ArcReader::open(&input)?;
+            let totals = archive_totals(archive.entries());
+            println!("entries:       {}", totals.count);
+            println!(
+                "compressed:    {} ({} stored uncompressed)",
+                totals.compressed_count,
+                totals.stored_count()
+            );
+            println!(
+                "original size: {} ({} bytes)",
+                format_bytes(totals.original_size),
+                totals.original_size
+            );
+            println!(
+                "stored size:   {} ({} bytes, {:.1}%)",
+                format_bytes(totals.compressed_size),
+                totals.compressed_size,
+                totals.ratio() * 100.0
+            );
+            println!("metadata size: {} bytes", archive.metadata_size());
+            println!("name encoding: {}", archive.name_encoding());
+            if archive.contains(PROVENANCE_NAME) {
+                println!("provenance:    yes (see `list --provenance`)");
+            }
+        }
+        Commands::Stats { input } => {
+            let entries = if input.is_dir() {
+                pack_dry_run(&input, true)?
+            } else {
+                ArcReader::open(&input)?.entries().to_vec()
+            };
+            println!(
+                "{:<8} {:>7} {:>10} {:>12} {:>12} {:>7}",
+                "ext", "files", "compressed", "original", "stored", "ratio"
+            );
+            let mut wasted = Vec::new();
+            for stats in extension_stats(&entries) {
+                let extension = if stats.extension.is_empty() {
+                    "(none)"
+                } else {
+                    &stats.extension
+                };
+                // The dry run stores whatever LZSS does not shrink, so look
+                // at the overall saving there
+                let is_wasted = if input.is_dir() {
+                    stats.original_size >= DEFAULT_MIN_COMPRESS_SIZE as u64
+                        && 1.0 - stats.ratio() < WASTED_LZSS_SAVING
+                } else {
+                    stats.lzss_wasted()
+                };
+                println!(
+                    "{:<8} {:>7} {:>10} {:>12} {:>12} {:>6.1}%{}",
+                    extension,
+                    stats.count,
+                    stats.compressed_count,
+                    stats.original_size,
+                    stats.compressed_size,
+                    stats.ratio() * 100.0,
+                    if is_wasted { " *" } else { "" }
+                );
+                if is_wasted && !stats.extension.is_empty() {
+                    wasted.push(format!("*.{}", stats.extension));
+                }
+            }
+            if !wasted.is_empty() {
+                println!();
+                println!(
+                    "{}",
+                    trf(
+                        "lzss-wasted",
+                        &[
+                            ("percent", &(WASTED_LZSS_SAVING * 100.0)),
+                            ("patterns", &wasted.join(","))
+                        ]
+                    )
+                );
+            }
+        }
+        Commands::Grep {
+            archive,
+            pattern,
+            hex,
+            ignore_case,
+            encoding,
+            include,
+        } => {
+            let pattern = if hex {
+                GrepPattern::Bytes(parse_hex(&pattern).map_err(ArcError::InvalidFormat)?.0)
+            } else {
+                GrepPattern::text(&pattern, ignore_case)?
+            };
+            let options = GrepOptions {
+                encoding,
+                include: (!include.is_empty())
+                    .then(|| NamePatterns::new(&include))
+                    .transpose()?,
+            };
+            let matches = grep_archive(&archive, &pattern, &options)?;
+            for found in &matches {
+                match found.line_number {
+                    Some(line) => println!(
+                        "{}:{line} (0x{:x}): {}",
+                        found.entry, found.offset, found.text
+                    ),
+                    None => println!("{} (0x{:x}): {}", found.entry, found.offset, found.text),
+                }
+            }
+            if matches.is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::NameTool { action } => match action {
+            NameAction::Encrypt { name } => {
+                let encrypted = encrypt_name(&name)?;
+                let hex: Vec<String> = encrypted.iter().map(|b| format!("{b:02x}")).collect();
+                println!("{}", hex.join(" "));
+            }
+            NameAction::Decrypt { hex, encoding } => {
+                println!("{}", name::decrypt_name_as(&hex.0, encoding)?);
+            }
+        },
+        Commands::Lzss { action } => match action {
+            LzssAction::Compress { input, output } => {
+                let data = fs::read(&input).with_path(&input)?;
+                let compressed = compress_data(&data)?;
+                fs::write(&output, &compressed).with_path(&output)?;
+                info!("Compressed {} -> {} bytes", data.len(), compressed.len());
+            }
+            LzssAction::Decompress {
+                input,
+                output,
+                size,
+            } => {
+                let data = fs::read(&input).with_path(&input)?;
+                let decompressed = decompress_data(&data, size.unwrap_or(u32::MAX))?;
+                if let Some(size) = size
+                    && decompressed.len() != size as usize
+                {
+                    return Err(ArcError::LzssDecompressError(format!(
+                        "decompressed to {} bytes, expected {size}",
+                        decompressed.len()
+                    )));
+                }
+                fs::write(&output, &decompressed).with_path(&output)?;
+                info!(
+                    "Decompressed {} -> {} bytes",
+                    data.len(),
+                    decompressed.len()
+                );
+            }
+        },
+        Commands::Lint {
+            input,
+            extensions,
+            json,
+        } => {
+            let options = LintOptions {
+                allowed_extensions: extensions.map(|exts| {
+                    exts.iter()
+                        .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                        .collect()
+                }),
+            };
+            let issues = lint_directory(&input, &options)?;
+            if json {
+                let issues: Vec<_> = issues
+                    .iter()
+                    .map(|issue| {
+                        serde_json::json!({
+                            "path": issue.path,
+                            "kind": issue.kind.code(),
+                            "error": issue.kind.is_error(),
+                            "message": issue.message,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::Value::Array(issues));
+            } else {
+                for issue in &issues {
+                    let level = if issue.kind.is_error() {
+                        "error"
+                    } else {
+                        "warning"
+                    };
+                    println!(
+                        "[{level}] {:?}: {} ({})",
+                        issue.path, issue.message, issue.kind
+                    );
+                }
+            }
+            if issues.iter().any(|issue| issue.kind.is_error()) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Dupes { input } => {
+            let groups = find_duplicates(&input)?;
+            for group in &groups {
+                println!(
+                    "{} x {} bytes, {} wasted:",
+                    group.names.len(),
+                    group.size,
+                    group.wasted_bytes
+                );
+                for name in &group.names {
+                    println!("  {name}");
+                }
+            }
+            let wasted: u64 = groups.iter().map(|group| group.wasted_bytes).sum();
+            println!(
+                "{}",
+                trf(
+                    "dupes-summary",
+                    &[("groups", &groups.len()), ("wasted", &format_bytes(wasted))]
+                )
+            );
+        }
+        Commands::CompatCheck { input, reference } => {
+            let report = compat_check(&input, &reference)?;
+            for divergence in &report.divergences {
+                println!("{}: {}", divergence.path.display(), divergence.kind);
+            }
+            println!(
+                "{}",
+                trf(
+                    "compat-summary",
+                    &[
+                        ("matched", &report.matched),
+                        ("divergences", &report.divergences.len())
+                    ]
+                )
+            );
+            if !report.is_compatible() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Compare { archive, dir } => {
+            // Same pairing as compat-check, printed like `diff`
+            let report = compat_check(&archive, &dir)?;
+            for divergence in &report.divergences {
+                let path = divergence.path.display();
+                match &divergence.kind {
+                    DivergenceKind::MissingFromReference => println!("- {path}"),
+                    DivergenceKind::NotInArchive => println!("+ {path}"),
+                    DivergenceKind::NameDiffers { reference } => {
+                        println!("~ {path}  (named {} in the directory)", reference.display())
+                    }
+                    DivergenceKind::ContentDiffers {
+                        size,
+                        reference_size,
+                    } => println!("M {path}  ({size} -> {reference_size} bytes)"),
+                }
+            }
+            println!(
+                "{}",
+                trf(
+                    "compat-summary",
+                    &[
+                        ("matched", &report.matched),
+                        ("divergences", &report.divergences.len())
+                    ]
+                )
+            );
+            if !report.is_compatible() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Verify { input, failed_only } => {
+            let checks = verify_archive(&input)?;
+            println!(
+                "{:<6} {:>10} {:>10}  name",
+                "result", "compressed", "original"
+            );
+            for check in checks
+                .iter()
+                .filter(|check| !(failed_only && check.passed()))
+            {
+                let (result, problem) = match &check.problem {
+                    Some(problem) => ("FAIL", format!(": {problem}")),
+                    None => ("ok", String::new()),
+                };
+                println!(
+                    "{result:<6} {:>10} {:>10}  {}{problem}",
+                    check.entry.compressed_size(),
+                    check.entry.original_size(),
+                    check.entry.name()
+                );
+            }
+            let failed = checks.iter().filter(|check| !check.passed()).count();
+            println!(
+                "{}",
+                trf(
+                    "verify-summary",
+                    &[
+                        ("passed", &(checks.len() - failed)),
+                        ("total", &checks.len())
+                    ]
+                )
+            );
+            if failed > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Checksum {
+            input,
+            archive_only,
+        } => {
+            for archive in &input {
+                let checksums = archive_checksums(archive)?;
+                if archive_only {
+                    println!("{}  {}", checksums.archive_digest(), archive.display());
+                    continue;
+                }
+                if input.len() > 1 {
+                    println!("# {}", archive.display());
+                }
+                print!("{}", checksums.to_checksum_file());
+            }
+        }
+        Commands::MakePatch { old, new, output } => {
+            let summary = make_patch(&old, &new, &output)?;
+            println!(
+                "{}",
+                trf(
+                    "patch-summary",
+                    &[
+                        ("size", &format_bytes(summary.patch_size)),
+                        ("included", &summary.included),
+                        ("copied", &summary.copied)
+                    ]
+                )
+            );
+        }
+        Commands::ApplyPatch {
+            base,
+            patch,
+            output,
+            backup,
+        } => {
+            backup_if_exists(&output, &backup)?;
+            let summary = with_progress(&progress, "apply-patch", |metrics| {
+                apply_patch(
+                    &base,
+                    &patch,
+                    &output,
+                    &PackOptions {
+                        metrics,
+                        strict: cli.abort_on_warning,
+                        ..Default::default()
+                    },
+                )
+            })?;
+            info!(
+                "Wrote {} entries to {:?} ({} bytes)",
+                summary.entry_count(),
+                output,
+                summary.archive_size()
+            );
+        }
+        Commands::Diff {
+            old,
+            new,
+            metadata_only,
+        } => {
+            let changes = diff_archives(&old, &new, metadata_only)?;
+            for change in &changes {
+                let marker = match change.change {
+                    Change::Added => '+',
+                    Change::Removed => '-',
+                    Change::Modified => 'M',
+                };
+                let size = |entry: &Option<ArcEntry>| {
+                    entry
+                        .as_ref()
+                        .map(|entry| entry.original_size().to_string())
+                };
+                let sizes = match (size(&change.old), size(&change.new)) {
+                    (Some(old), Some(new)) => format!("{old} -> {new} bytes"),
+                    (Some(size), None) | (None, Some(size)) => format!("{size} bytes"),
+                    (None, None) => String::new(),
+                };
+                println!("{marker} {}  ({sizes})", change.name);
+            }
+            println!("{}", trf("diff-summary", &[("count", &changes.len())]));
+            if !changes.is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Doctor { input } => {
+            let diagnosis = diagnose(&input)?;
+            println!(
+                "{}",
+                trf("entries-parsed", &[("count", &diagnosis.entry_count)])
+            );
+            for finding in &diagnosis.findings {
+                println!("[{}] {}", finding.severity, finding.message);
+                if let Some(suggestion) = &finding.suggestion {
+                    println!("    {}: {suggestion}", tr("hint"));
+                }
+            }
+            if diagnosis.findings.is_empty() {
+                println!("{}", tr("no-problems"));
+            }
+            if diagnosis.has_errors() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Wizard => {
+            let args = wizard::ask(&mut io::stdin().lock(), &mut io::stdout())?;
+            println!("Running: {}", wizard::command_line(&args));
+            let matches = i18n::localize_command(Cli::command())
+                .try_get_matches_from(iter::once("silkyarctool".to_string()).chain(args))
+                .unwrap_or_else(|e| e.exit());
+            let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+            return run(cli);
+        }
+        Commands::Mangen { out_dir } => {
+            fs::create_dir_all(&out_dir).with_path(&out_dir)?;
+            // Pages are named after the installed binary, not the package
+            clap_mangen::generate_to(Cli::command().name("silkyarctool"), &out_dir)
+                .with_path(&out_dir)?;
+            info!("Wrote man pages to {:?}", out_dir);
+        }
+        #[cfg(feature = "tui")]
+        Commands::Browse { archive, output } => {
+            let output = match output {
+                Some(output) => output,
+                None => silky_arc_tool::output::derive_unpack_output(&archive, None)?,
+            };
+            silky_arc_tool::browse::browse(&archive, &output)?;
+        }
+        #[cfg(feature = "scripting")]
+        Commands::Script { script, args } => {
+            silky_arc_tool::script::run_script(&script, &args)?;
         }
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+
+use path_absolutize::Absolutize;
+
+use crate::error::{ArcError, PathContext};
+
+// --- Output Path Derivation ---
+// When no explicit output is given, it is derived from the input path using a
+// template. Supported placeholders:
+//   {parent} - absolute directory containing the input
+//   {name}   - input file or directory name, including any extension
+//   {stem}   - input name without its last extension
+//   {ext}    - last extension of the input (without the dot)
+
+/// Default template for `pack`: `<dir>` → `<dir>.arc` next to it.
+pub const DEFAULT_PACK_TEMPLATE: &str = "{parent}/{name}.arc";
+/// Default template for packing a file (`--from-zip`, `--manifest`):
+/// `<name>.zip` → `<name>.arc`.
+pub const DEFAULT_FILE_PACK_TEMPLATE: &str = "{parent}/{stem}.arc";
+/// Default template for `unpack`: `<name>.arc` → `<name>` next to it.
+pub const DEFAULT_UNPACK_TEMPLATE: &str = "{parent}/{stem}";
+
+/// Expands an output naming template for the given input path.
+pub fn expand_output_template(template: &str, input: &Path) -> Result<PathBuf, ArcError> {
+    let input = input.absolutize().with_path(input)?;
+    let name = input
+        .file_name()
+        .ok_or_else(|| ArcError::NoFilename(input.to_path_buf()))?;
+    let parent = input.parent().unwrap_or(Path::new(""));
+    let stem = Path::new(name).file_stem().unwrap_or(name);
+    let ext = Path::new(name).extension().unwrap_or_default();
+
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| ArcError::InvalidTemplate(template.to_string()))?;
+        let value = match &rest[start + 1..end] {
+            "parent" => parent.as_os_str(),
+            "name" => name,
+            "stem" => stem,
+            "ext" => ext,
+            _ => return Err(ArcError::InvalidTemplate(template.to_string())),
+        };
+        expanded.push_str(&value.to_string_lossy());
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    // Relative results are placed next to the input
+    Ok(parent.join(expanded))
+}
+
+/// Derives the archive path for packing `input_dir`.
+pub fn derive_pack_output(input_dir: &Path, template: Option<&str>) -> Result<PathBuf, ArcError> {
+    let output = expand_output_template(template.unwrap_or(DEFAULT_PACK_TEMPLATE), input_dir)?;
+    // Packing into the input directory itself is invalid
+    if output == input_dir.absolutize().with_path(input_dir)? {
+        return Err(ArcError::CannotDeriveOutputPath(input_dir.to_path_buf()));
+    }
+    Ok(output)
+}
+
+/// Like [`derive_pack_output`], but places the derived archive name in
+/// `output_dir` instead of where the template puts it.
+pub fn derive_pack_output_in(
+    input_dir: &Path,
+    template: Option<&str>,
+    output_dir: &Path,
+) -> Result<PathBuf, ArcError> {
+    let derived = derive_pack_output(input_dir, template)?;
+    let name = derived
+        .file_name()
+        .ok_or_else(|| ArcError::NoFilename(derived.clone()))?;
+    let output = output_dir.absolutize().with_path(output_dir)?.join(name);
+    if output == input_dir.absolutize().with_path(input_dir)? {
+        return Err(ArcError::CannotDeriveOutputPath(input_dir.to_path_buf()));
+    }
+    Ok(output)
+}
+
+/// Derives the output directory for unpacking `input`.
+pub fn derive_unpack_output(input: &Path, template: Option<&str>) -> Result<PathBuf, ArcError> {
+    let output = expand_output_template(template.unwrap_or(DEFAULT_UNPACK_TEMPLATE), input)?;
+    avoid_input_clash(output, input)
+}
+
+/// Where `unpack` puts the extracted files.
+#[derive(Debug, Clone, Copy)]
+pub enum UnpackOutput<'a> {
+    /// Exactly this directory (`--output`).
+    Explicit(&'a Path),
+    /// Derived from the archive path with a template, `None` for the default.
+    Derived(Option<&'a str>),
+    /// An archive-named folder inside this directory (`--here` passes the
+    /// current working directory).
+    Below(&'a Path),
+}
+
+/// Resolves the output directory for unpacking `archive`, exactly as the
+/// command line does.
+///
+/// `search_root` is the directory `--each` searched for `archive`; explicit
+/// and [`UnpackOutput::Below`] destinations then mirror the layout below it.
+pub fn resolve_unpack_output(
+    archive: &Path,
+    search_root: Option<&Path>,
+    target: UnpackOutput,
+) -> Result<PathBuf, ArcError> {
+    let mirrored = |dest: &Path| -> Result<PathBuf, ArcError> {
+        let relative = match search_root {
+            Some(root) => archive.strip_prefix(root)?,
+            None => Path::new(
+                archive
+                    .file_name()
+                    .ok_or_else(|| ArcError::NoFilename(archive.to_path_buf()))?,
+            ),
+        };
+        Ok(dest.join(relative.with_extension("")))
+    };
+    match (target, search_root) {
+        (UnpackOutput::Explicit(dest), None) => {
+            // Prevent unpacking directly into the archive file itself
+            if dest.absolutize().with_path(dest)? == archive.absolutize().with_path(archive)? {
+                return Err(ArcError::CannotDeriveOutputPath(archive.to_path_buf()));
+            }
+            Ok(dest.to_path_buf())
+        }
+        (UnpackOutput::Explicit(dest), Some(_)) => mirrored(dest),
+        (UnpackOutput::Derived(template), _) => derive_unpack_output(archive, template),
+        (UnpackOutput::Below(dir), _) => {
+            let output = mirrored(&dir.absolutize().with_path(dir)?)?;
+            avoid_input_clash(output, archive)
+        }
+    }
+}
+
+/// Avoids unpacking into the archive itself if names clash (e.g. no extension).
+fn avoid_input_clash(mut output: PathBuf, input: &Path) -> Result<PathBuf, ArcError> {
+    if output == input.absolutize().with_path(input)? {
+        let name = output.file_name().unwrap_or_default().to_string_lossy();
+        output.set_file_name(format!("{name}_unpacked"));
+    }
+    Ok(output)
+}
@@ -0,0 +1,142 @@
+use std::{
+    io::{self, Write},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+
+use crate::metrics::{Metrics, MetricsSnapshot};
+
+// --- Progress Display ---
+// A background thread samples the shared `Metrics` of a running pack or
+// unpack and either redraws a single status line on stderr, or emits one JSON
+// object per line for front ends to parse.
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Where progress is reported.
+#[derive(Clone)]
+pub enum ProgressOutput {
+    /// A human readable status line on stderr.
+    Line,
+    /// Newline-delimited JSON events, see [`json_event`].
+    Json(Arc<Mutex<dyn Write + Send>>),
+}
+
+/// Progress reporting until [`ProgressBar::finish`] is called.
+pub struct ProgressBar {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl ProgressBar {
+    /// Starts reporting `metrics` of the operation named `phase` (e.g.
+    /// `"pack"`).
+    pub fn start(metrics: Arc<Metrics>, output: ProgressOutput, phase: &'static str) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let stop = stop.clone();
+            move || {
+                let start = Instant::now();
+                let report = |finished: bool| {
+                    let snapshot = metrics.snapshot();
+                    match &output {
+                        ProgressOutput::Line => draw(&snapshot, finished),
+                        ProgressOutput::Json(writer) => {
+                            let event = json_event(phase, &snapshot, start.elapsed(), finished);
+                            let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+                            _ = writeln!(writer, "{event}");
+                            _ = writer.flush();
+                        }
+                    }
+                };
+                while !stop.load(Ordering::Relaxed) {
+                    report(false);
+                    thread::park_timeout(REFRESH_INTERVAL);
+                }
+                report(true);
+            }
+        });
+        Self { stop, handle }
+    }
+
+    /// Reports the final state.
+    pub fn finish(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.thread().unpark();
+        _ = self.handle.join();
+    }
+}
+
+/// Builds one progress event:
+///
+/// ```json
+/// {"event":"progress","phase":"unpack","entries_done":12,"bytes_done":1048576,
+///  "bytes_total":4194304,"elapsed_secs":0.8,"eta_secs":2.4}
+/// ```
+///
+/// `event` is `"finished"` for the last one. `eta_secs` is `null` until some
+/// bytes are done.
+pub fn json_event(
+    phase: &str,
+    snapshot: &MetricsSnapshot,
+    elapsed: Duration,
+    finished: bool,
+) -> serde_json::Value {
+    let elapsed_secs = elapsed.as_secs_f64();
+    let eta_secs = (snapshot.bytes_done > 0).then(|| {
+        let remaining = snapshot.bytes_total.saturating_sub(snapshot.bytes_done);
+        elapsed_secs * remaining as f64 / snapshot.bytes_done as f64
+    });
+    json!({
+        "event": if finished { "finished" } else { "progress" },
+        "phase": phase,
+        "entries_done": snapshot.entries_processed,
+        "bytes_done": snapshot.bytes_done,
+        "bytes_total": snapshot.bytes_total,
+        "elapsed_secs": elapsed_secs,
+        "eta_secs": eta_secs,
+    })
+}
+
+fn draw(snapshot: &MetricsSnapshot, finished: bool) {
+    let percent = match snapshot.bytes_total {
+        0 => 100.0,
+        total => snapshot.bytes_done as f64 * 100.0 / total as f64,
+    };
+    let mut stderr = io::stderr().lock();
+    _ = write!(
+        stderr,
+        "\r{} / {} ({percent:.1}%), {} entries",
+        format_bytes(snapshot.bytes_done),
+        format_bytes(snapshot.bytes_total),
+        snapshot.entries_processed
+    );
+    if finished {
+        _ = writeln!(stderr);
+    }
+    _ = stderr.flush();
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
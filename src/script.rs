@@ -0,0 +1,147 @@
+use std::{
+    cell::RefCell,
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+    rc::Rc,
+};
+
+use rhai::{Array, Blob, Dynamic, Engine, EvalAltResult, Map, Scope};
+use silky_arc_core::{
+    error::{ArcError, PathContext},
+    reader::{ArcEntry, ArcReader},
+    selection::normalize_entry_name,
+    validate_entry_name,
+    writer::ArcWriter,
+};
+
+// --- Scripting ---
+// Runs rhai scripts against the archive API for one-off batch migrations.
+// Scripts see:
+//
+//   open_archive(path)         -> Archive
+//   archive.entries()          -> [#{ name, size, stored_size, offset }]
+//   archive.contains(name)     -> bool
+//   archive.read(name)         -> Blob (decompressed)
+//   new_archive()              -> Writer
+//   writer.add(name, blob)     adds or replaces an entry
+//   writer.write(path[, compress])
+//   decode_cp932(blob)         -> String
+//   encode_cp932(string)       -> Blob
+//   ARGS                       extra command line arguments
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+fn script_error(e: impl ToString) -> Box<EvalAltResult> {
+    e.to_string().into()
+}
+
+#[derive(Clone)]
+struct ScriptArchive(Rc<RefCell<ArcReader<BufReader<File>>>>);
+
+impl ScriptArchive {
+    fn open(path: &str) -> ScriptResult<Self> {
+        let archive = ArcReader::open(path).map_err(script_error)?;
+        Ok(Self(Rc::new(RefCell::new(archive))))
+    }
+
+    fn entries(&mut self) -> Array {
+        self.0.borrow().entries().iter().map(entry_map).collect()
+    }
+
+    fn contains(&mut self, name: &str) -> bool {
+        self.0.borrow().contains(name)
+    }
+
+    fn read(&mut self, name: &str) -> ScriptResult<Blob> {
+        let mut archive = self.0.borrow_mut();
+        let entry = archive
+            .get(name)
+            .cloned()
+            .ok_or_else(|| script_error(format!("no entry named {name:?}")))?;
+        archive.read_entry(&entry).map_err(script_error)
+    }
+}
+
+fn entry_map(entry: &ArcEntry) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("name".into(), entry.name().into());
+    map.insert("size".into(), (entry.original_size() as i64).into());
+    map.insert(
+        "stored_size".into(),
+        (entry.compressed_size() as i64).into(),
+    );
+    map.insert("offset".into(), (entry.offset() as i64).into());
+    map.into()
+}
+
+/// Collects entries in memory, which are written on `write`.
+#[derive(Clone, Default)]
+struct ScriptWriter(Rc<RefCell<Vec<(String, Blob)>>>);
+
+impl ScriptWriter {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&mut self, name: &str, data: Blob) -> ScriptResult<()> {
+        validate_entry_name(&normalize_entry_name(name))
+            .map_err(|e| script_error(format!("invalid entry name {name:?}: {e}")))?;
+        let mut entries = self.0.borrow_mut();
+        match entries.iter_mut().find(|(existing, _)| existing == name) {
+            Some((_, existing)) => *existing = data,
+            None => entries.push((name.to_string(), data)),
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, output: &str, compress: bool) -> ScriptResult<()> {
+        let mut writer = ArcWriter::create(output);
+        for (name, data) in self.0.borrow().iter() {
+            writer
+                .add_file(name, data.as_slice(), compress)
+                .map_err(script_error)?;
+        }
+        writer.finish().map_err(script_error)?;
+        Ok(())
+    }
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .register_type_with_name::<ScriptArchive>("Archive")
+        .register_fn("open_archive", ScriptArchive::open)
+        .register_fn("entries", ScriptArchive::entries)
+        .register_fn("contains", ScriptArchive::contains)
+        .register_fn("read", ScriptArchive::read)
+        .register_type_with_name::<ScriptWriter>("Writer")
+        .register_fn("new_archive", ScriptWriter::new)
+        .register_fn("add", ScriptWriter::add)
+        .register_fn("write", ScriptWriter::write)
+        .register_fn("write", |writer: &mut ScriptWriter, output: &str| {
+            writer.write(output, false)
+        })
+        .register_fn("decode_cp932", |data: Blob| {
+            encoding_rs::SHIFT_JIS.decode(&data).0.into_owned()
+        })
+        .register_fn("encode_cp932", |text: &str| -> ScriptResult<Blob> {
+            let (encoded, _, had_errors) = encoding_rs::SHIFT_JIS.encode(text);
+            if had_errors {
+                return Err(script_error(format!("{text:?} is not CP932 encodable")));
+            }
+            Ok(encoded.into_owned())
+        });
+    engine
+}
+
+/// Runs the script at `path` with `args` available as `ARGS`.
+pub fn run_script(path: &Path, args: &[String]) -> Result<(), ArcError> {
+    let source = fs::read_to_string(path).with_path(path)?;
+    let mut scope = Scope::new();
+    let args: Array = args.iter().map(|arg| arg.clone().into()).collect();
+    scope.push_constant("ARGS", args);
+    engine()
+        .run_with_scope(&mut scope, &source)
+        .map_err(|e| ArcError::Script(format!("{}: {e}", path.display())))
+}
@@ -0,0 +1,90 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    path::PathBuf,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+use log::{debug, info};
+use notify::{EventKind, RecursiveMode, Watcher};
+use silky_arc_core::error::ArcError;
+
+// --- Watch Mode ---
+// `pack --watch` rebuilds archives when their sources change. Changes are
+// collected until none arrive for the debounce interval, so saving several
+// files (or an editor's write-rename dance) triggers a single rebuild.
+
+/// Default quiet interval before rebuilding.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Prefix of the spool files written next to the output while packing.
+const SPOOL_PREFIX: &str = ".silkyarc";
+
+/// Indices of the `roots` containing one of `paths`, skipping the `ignored`
+/// paths (the archives being written) and spool files.
+pub fn changed_roots(roots: &[PathBuf], ignored: &[PathBuf], paths: &[PathBuf]) -> BTreeSet<usize> {
+    paths
+        .iter()
+        .filter(|path| !ignored.iter().any(|ignored| *path == ignored))
+        .filter(|path| {
+            !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(SPOOL_PREFIX))
+        })
+        .filter_map(|path| roots.iter().position(|root| path.starts_with(root)))
+        .collect()
+}
+
+/// Watches `roots` recursively and calls `rebuild` with the indices of the
+/// roots that changed, once changes have settled for `debounce`. Returns
+/// when `rebuild` returns `false`.
+pub fn watch(
+    roots: &[PathBuf],
+    ignored: &[PathBuf],
+    debounce: Duration,
+    mut rebuild: impl FnMut(&BTreeSet<usize>) -> bool,
+) -> Result<(), ArcError> {
+    // Events carry absolute paths
+    let absolute = |paths: &[PathBuf]| -> Vec<PathBuf> {
+        paths
+            .iter()
+            .map(|path| std::path::absolute(path).unwrap_or_else(|_| path.clone()))
+            .collect()
+    };
+    let (roots, ignored) = (absolute(roots), absolute(ignored));
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+    for root in &roots {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(io::Error::other)?;
+    }
+    info!("Watching {} inputs for changes", roots.len());
+
+    let received = |event: notify::Result<notify::Event>| -> Result<Vec<PathBuf>, ArcError> {
+        let event = event.map_err(io::Error::other)?;
+        debug!("{:?}", event);
+        Ok(match event.kind {
+            EventKind::Access(_) => Vec::new(),
+            _ => event.paths,
+        })
+    };
+    loop {
+        let Ok(first) = receiver.recv() else {
+            return Ok(());
+        };
+        let mut paths = received(first)?;
+        loop {
+            match receiver.recv_timeout(debounce) {
+                Ok(event) => paths.extend(received(event)?),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+        let changed = changed_roots(&roots, &ignored, &paths);
+        if !changed.is_empty() && !rebuild(&changed) {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::output::{derive_pack_output, derive_unpack_output};
+
+// --- Wizard ---
+// Asks what to do, one question at a time, and turns the answers into the
+// arguments of the equivalent command, so users learn the real invocation.
+
+/// Asks the questions on `output`, reading answers from `input`, and returns
+/// the command line arguments (without the program name) of the chosen task.
+pub fn ask(input: &mut impl BufRead, output: &mut impl Write) -> io::Result<Vec<String>> {
+    let mut prompter = Prompter { input, output };
+    let task = prompter.choose(
+        "What do you want to do?",
+        &[
+            "Unpack an archive",
+            "Repack a (translated) folder into an archive",
+            "Verify an archive",
+        ],
+    )?;
+    let args = match task {
+        0 => {
+            let archive = prompter.path("Archive to unpack", None, Path::is_file)?;
+            let default = derive_unpack_output(&archive, None).ok();
+            let output_dir = prompter.path("Output directory", default, |_| true)?;
+            vec![
+                "unpack".to_string(),
+                display(&archive),
+                "--output".to_string(),
+                display(&output_dir),
+            ]
+        }
+        1 => {
+            let input_dir = prompter.path("Folder to pack", None, Path::is_dir)?;
+            let default = derive_pack_output(&input_dir, None).ok();
+            let archive = prompter.path("Output archive", default, |path| !path.is_dir())?;
+            let mut args = vec![
+                "pack".to_string(),
+                display(&input_dir),
+                "--output".to_string(),
+                display(&archive),
+            ];
+            if prompter.confirm("Compress with LZSS? Not recommended for voice archives")? {
+                args.push("--compress".to_string());
+            }
+            if prompter.confirm("Re-read the archive afterwards to verify it?")? {
+                args.push("--verify".to_string());
+            }
+            args
+        }
+        _ => {
+            let archive = prompter.path("Archive to verify", None, Path::is_file)?;
+            vec!["verify".to_string(), display(&archive)]
+        }
+    };
+    Ok(args)
+}
+
+/// Formats arguments as a shell command line, quoting where needed.
+pub fn command_line(args: &[String]) -> String {
+    let mut line = "silkyarctool".to_string();
+    for arg in args {
+        line.push(' ');
+        if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "\"'$`\\".contains(c)) {
+            line.push('"');
+            line.push_str(&arg.replace('"', "\\\""));
+            line.push('"');
+        } else {
+            line.push_str(arg);
+        }
+    }
+    line
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+struct Prompter<'a, R, W> {
+    input: &'a mut R,
+    output: &'a mut W,
+}
+
+impl<R: BufRead, W: Write> Prompter<'_, R, W> {
+    /// Prints `question` and returns the trimmed answer. Fails at end of
+    /// input so a closed stdin does not loop forever.
+    fn line(&mut self, question: &str) -> io::Result<String> {
+        write!(self.output, "{question}: ")?;
+        self.output.flush()?;
+        let mut answer = String::new();
+        if self.input.read_line(&mut answer)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "no answer given",
+            ));
+        }
+        Ok(answer.trim().to_string())
+    }
+
+    fn choose(&mut self, question: &str, options: &[&str]) -> io::Result<usize> {
+        writeln!(self.output, "{question}")?;
+        for (index, option) in options.iter().enumerate() {
+            writeln!(self.output, "  {}) {option}", index + 1)?;
+        }
+        loop {
+            let answer = self.line("Choice")?;
+            match answer.parse::<usize>() {
+                Ok(choice) if (1..=options.len()).contains(&choice) => return Ok(choice - 1),
+                _ => writeln!(self.output, "Please enter 1 to {}.", options.len())?,
+            }
+        }
+    }
+
+    /// Asks for a path until `valid` accepts it. An empty answer takes
+    /// `default`, if there is one.
+    fn path(
+        &mut self,
+        question: &str,
+        default: Option<PathBuf>,
+        valid: impl Fn(&Path) -> bool,
+    ) -> io::Result<PathBuf> {
+        let question = match &default {
+            Some(default) => format!("{question} [{}]", default.display()),
+            None => question.to_string(),
+        };
+        loop {
+            let answer = self.line(&question)?;
+            // Paths dragged into a terminal are often quoted
+            let answer = answer.trim_matches(['"', '\'']);
+            let path = match (answer.is_empty(), &default) {
+                (true, Some(default)) => default.clone(),
+                (true, None) => continue,
+                (false, _) => PathBuf::from(answer),
+            };
+            if valid(&path) {
+                return Ok(path);
+            }
+            writeln!(self.output, "{} cannot be used here.", path.display())?;
+        }
+    }
+
+    fn confirm(&mut self, question: &str) -> io::Result<bool> {
+        let answer = self.line(&format!("{question} [y/N]"))?;
+        Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+    }
+}
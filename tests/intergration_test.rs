@@ -1,6 +1,81 @@
-use std::fs;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    ffi::{CStr, CString},
+    fs,
+    io::{Cursor, Read},
+    path::Path,
+    ptr,
+    sync::Arc,
+    time::Duration,
+};
 
-use silky_arc_tool::{handle_pack, handle_unpack};
+use silky_arc_ffi::{
+    silky_arc_free_string, silky_arc_last_error, silky_arc_list_json, silky_arc_pack,
+    silky_arc_unpack,
+};
+use silky_arc_tool::{
+    EntryOrder, OverwritePolicy, PackOptions, PackSummary, ResumeMode, UnpackOptions,
+    async_api::{pack_async, unpack_async},
+    backup::backup_archive,
+    batch::{expand_file_pattern, find_archives, subdirectories},
+    cancel::CancelToken,
+    checksum::{archive_checksums, read_checksum_file},
+    cli::parse_hex,
+    compat::{DivergenceKind, compat_check},
+    decompress_data, decrypt_name,
+    diff::{Change, diff_archives},
+    doctor::diagnose,
+    dupes::find_duplicates,
+    edit::{ArchiveEdit, edit_archive, overlay_edit},
+    editor::ArcEditor,
+    encrypt_name,
+    error::{ArcError, NameError},
+    format::{
+        EntryRecord, effective_metadata_size, parse_entry, parse_header, parse_metadata,
+        write_entry, write_header,
+    },
+    grep::{GrepMatch, GrepOptions, GrepPattern, grep_archive},
+    handle_pack, handle_pack_to_writer, handle_pack_with, handle_unpack, handle_unpack_from_reader,
+    handle_unpack_lenient, handle_unpack_with,
+    i18n::Lang,
+    lint::{LintKind, LintOptions, lint_directory},
+    manifest::{pack_manifest, pack_manifest_entries, parse_manifest, read_manifest},
+    merge::{ConflictPolicy, merge_archives},
+    metrics::{Metrics, MetricsSnapshot},
+    mmap::MappedArchive,
+    name::{
+        CP932_FALLBACKS, DEFAULT_NAME_ENCODINGS, NameCodec, NameEncoding, SeparatorStyle,
+        SilkyCodec, detect_name_encoding, encode_path, encode_path_with, entry_name_to_path,
+        map_cp932_fallbacks, path_to_entry_name, sanitize_entry_name, validate_entry_name_with,
+    },
+    normalize::{NormalizeOptions, normalize_archive},
+    optimize::optimize_archive,
+    output::{
+        UnpackOutput, derive_pack_output, derive_pack_output_in, derive_unpack_output,
+        expand_output_template, resolve_unpack_output,
+    },
+    pack_dry_run,
+    parallel::Parallelism,
+    patch::{PATCH_MAGIC, apply_patch, make_patch},
+    pattern::NamePatterns,
+    progress::{format_bytes, json_event},
+    provenance::{PROVENANCE_NAME, read_provenance},
+    reader::{ArcReader, shared_data_sources},
+    rename::RenameRule,
+    selection::{PackFilter, SizeRange, parse_entry_list},
+    split::{plan_split, split_archive},
+    stats::{ExtensionStats, archive_totals, extension_stats},
+    synth::{SynthOptions, generate_archive, layout_sizes},
+    validate_entry_name,
+    verify::verify_archive,
+    verify_pack, verify_pack_with,
+    wasm::{pack_entries, unpack_entries},
+    watch::{changed_roots, watch},
+    wizard,
+    writer::ArcWriter,
+    zip_source::pack_zip,
+};
 use tempfile::tempdir;
 
 #[test]
@@ -11,6 +86,30 @@ fn test_unpack() {
     assert!(temp_dir.path().join("KT_A0000.OGG").exists());
 }
 
+#[test]
+fn test_unpack_from_reader() {
+    let temp_dir = tempdir().unwrap();
+    let from_file = temp_dir.path().join("file");
+    let from_memory = temp_dir.path().join("memory");
+    handle_unpack("./test_assets/test.arc", &from_file).unwrap();
+    let data = fs::read("./test_assets/test.arc").unwrap();
+    let options = UnpackOptions {
+        verify: true,
+        ..Default::default()
+    };
+    handle_unpack_from_reader(Cursor::new(data), &from_memory, &options).unwrap();
+    for entry in ArcReader::open("./test_assets/test.arc").unwrap().entries() {
+        let path = entry_name_to_path(entry.name());
+        assert_eq!(
+            fs::read(from_memory.join(&path)).unwrap(),
+            fs::read(from_file.join(&path)).unwrap()
+        );
+    }
+    assert!(
+        handle_unpack_from_reader(Cursor::new(b"not an archive"), &from_memory, &options).is_err()
+    );
+}
+
 #[test]
 fn test_pack() {
     let temp_dir = tempdir().unwrap();
@@ -24,3 +123,2650 @@ fn test_pack() {
     handle_pack(&input_dir, &output_path, true).unwrap();
     assert!(output_path.exists());
 }
+
+#[test]
+fn test_pack_to_writer() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("test");
+    fs::create_dir_all(input_dir.join("sub")).unwrap();
+    fs::write(input_dir.join("test.txt"), "test ".repeat(50)).unwrap();
+    fs::write(input_dir.join("sub").join("a.bin"), [1, 2, 3]).unwrap();
+    let options = PackOptions {
+        compress: true,
+        ..Default::default()
+    };
+    let output_path = temp_dir.path().join("test.arc");
+    let on_disk = handle_pack_with(&input_dir, &output_path, &options).unwrap();
+    let mut buffer = Cursor::new(Vec::new());
+    let in_memory = handle_pack_to_writer(&input_dir, &mut buffer, &options).unwrap();
+    assert_eq!(in_memory.archive_size(), on_disk.archive_size());
+    assert_eq!(buffer.into_inner(), fs::read(&output_path).unwrap());
+}
+
+#[test]
+fn test_pack_filter() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("test");
+    fs::create_dir_all(input_dir.join("work")).unwrap();
+    fs::write(input_dir.join("a.txt"), "a").unwrap();
+    fs::write(input_dir.join("notes.psd"), "psd").unwrap();
+    fs::write(input_dir.join("work").join("b.txt"), "b").unwrap();
+    let options = PackOptions {
+        filter: Some(PackFilter::new(|path| {
+            !path.starts_with("work") && path.extension().is_none_or(|ext| ext != "psd")
+        })),
+        ..Default::default()
+    };
+    let output_path = temp_dir.path().join("test.arc");
+    let summary = handle_pack_with(&input_dir, &output_path, &options).unwrap();
+    let names: Vec<_> = summary.entries.iter().map(|e| e.name()).collect();
+    assert_eq!(names, ["a.txt"]);
+}
+
+#[tokio::test]
+async fn test_async_api() {
+    let temp_dir = tempdir().unwrap();
+    let output_dir = temp_dir.path().join("unpacked");
+    unpack_async(
+        "./test_assets/test.arc",
+        &output_dir,
+        UnpackOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert!(output_dir.join("test.txt").is_file());
+    let output_path = temp_dir.path().join("repacked.arc");
+    let summary = pack_async(&output_dir, &output_path, PackOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(summary.entry_count(), 2);
+    assert!(matches!(
+        unpack_async(
+            temp_dir.path().join("missing.arc"),
+            &output_dir,
+            UnpackOptions::default()
+        )
+        .await,
+        Err(ArcError::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_ffi() {
+    let temp_dir = tempdir().unwrap();
+    let c_path = |path: &Path| CString::new(path.to_str().unwrap()).unwrap();
+    let archive = c_path(Path::new("./test_assets/test.arc"));
+    let output_dir = c_path(&temp_dir.path().join("unpacked"));
+    let repacked = c_path(&temp_dir.path().join("repacked.arc"));
+    unsafe {
+        assert_eq!(silky_arc_unpack(archive.as_ptr(), output_dir.as_ptr()), 0);
+        assert_eq!(silky_arc_pack(output_dir.as_ptr(), repacked.as_ptr(), 1), 0);
+
+        let listing = silky_arc_list_json(repacked.as_ptr());
+        assert!(!listing.is_null());
+        let document: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(listing).to_str().unwrap()).unwrap();
+        silky_arc_free_string(listing);
+        let names: Vec<_> = document["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["KT_A0000.OGG", "test.txt"]);
+
+        let missing = c_path(&temp_dir.path().join("missing.arc"));
+        assert!(silky_arc_list_json(missing.as_ptr()).is_null());
+        let error = CStr::from_ptr(silky_arc_last_error()).to_str().unwrap();
+        assert!(error.contains("missing.arc"), "{error}");
+        assert_eq!(silky_arc_unpack(ptr::null(), output_dir.as_ptr()), -1);
+    }
+}
+
+#[test]
+fn test_wasm_entries() {
+    let entries = vec![
+        (
+            r"script\main.txt".to_string(),
+            "m".repeat(1000).into_bytes(),
+        ),
+        ("se/ok.ogg".to_string(), vec![1, 2, 3]),
+    ];
+    let archive = pack_entries(entries, true).unwrap();
+    let reader = ArcReader::new(Cursor::new(&archive)).unwrap();
+    assert!(reader.entries()[0].is_compressed());
+    assert_eq!(
+        unpack_entries(&archive).unwrap(),
+        [
+            (
+                r"script\main.txt".to_string(),
+                "m".repeat(1000).into_bytes()
+            ),
+            (r"se\ok.ogg".to_string(), vec![1, 2, 3]),
+        ]
+    );
+    assert!(matches!(
+        pack_entries(
+            [("a".to_string(), vec![]), ("a".to_string(), vec![])],
+            false
+        ),
+        Err(ArcError::DuplicateEntry(_))
+    ));
+    assert!(unpack_entries(&archive[..10]).is_err());
+}
+
+#[test]
+fn test_serde_entries() {
+    let temp_dir = tempdir().unwrap();
+    let unpacked = temp_dir.path().join("unpacked");
+    handle_unpack("./test_assets/test.arc", &unpacked).unwrap();
+    let output = temp_dir.path().join("out.arc");
+    let summary = handle_pack(&unpacked, &output, true).unwrap();
+    let json = serde_json::to_string(&summary).unwrap();
+    let restored: PackSummary = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.metadata_size, summary.metadata_size);
+    assert_eq!(restored.entries.len(), summary.entries.len());
+    for (restored, entry) in restored.entries.iter().zip(&summary.entries) {
+        assert_eq!(restored.name(), entry.name());
+        assert_eq!(restored.encrypted_name(), entry.encrypted_name());
+        assert_eq!(restored.offset(), entry.offset());
+        assert_eq!(restored.compressed_size(), entry.compressed_size());
+    }
+
+    let archive = ArcReader::open(&output).unwrap();
+    assert_eq!(
+        serde_json::to_value(archive.name_encoding()).unwrap(),
+        "cp932"
+    );
+    let record = EntryRecord {
+        encrypted_name: vec![1, 2],
+        compressed_size: 3,
+        original_size: 4,
+        offset: 5,
+    };
+    let value = serde_json::to_value(&record).unwrap();
+    assert_eq!(value["offset"], 5);
+    assert_eq!(
+        serde_json::from_value::<EntryRecord>(value).unwrap(),
+        record
+    );
+}
+
+/// Plain UTF-8 bytes, each XORed with 0x5a.
+#[derive(Debug)]
+struct XorCodec;
+
+impl NameCodec for XorCodec {
+    fn encrypt(&self, name: &str) -> Result<Vec<u8>, ArcError> {
+        Ok(name.bytes().map(|b| b ^ 0x5a).collect())
+    }
+
+    fn decrypt(&self, encrypted: &[u8]) -> Result<String, ArcError> {
+        let bytes = encrypted.iter().map(|b| b ^ 0x5a).collect();
+        String::from_utf8(bytes).map_err(|_| ArcError::NameDecodeError(encrypted.to_vec()))
+    }
+}
+
+#[test]
+fn test_name_codec() {
+    let silky = SilkyCodec::default();
+    let encrypted = silky.encrypt(r"script\テスト.txt").unwrap();
+    assert_eq!(encrypted, encrypt_name(r"script\テスト.txt").unwrap());
+    assert_eq!(silky.decrypt(&encrypted).unwrap(), r"script\テスト.txt");
+
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("sub")).unwrap();
+    fs::write(input_dir.join("sub").join("a.txt"), "a").unwrap();
+    let archive = temp_dir.path().join("xor.arc");
+    let codec: Arc<dyn NameCodec> = Arc::new(XorCodec);
+    let options = PackOptions {
+        name_codec: Some(codec.clone()),
+        ..Default::default()
+    };
+    let summary = handle_pack_with(&input_dir, &archive, &options).unwrap();
+    assert_eq!(
+        summary.entries[0].encrypted_name(),
+        XorCodec.encrypt(r"sub\a.txt").unwrap()
+    );
+
+    let reader = ArcReader::open_with_codec(&archive, &XorCodec).unwrap();
+    assert_eq!(reader.entries()[0].name(), r"sub\a.txt");
+    let output_dir = temp_dir.path().join("output");
+    let options = UnpackOptions {
+        name_codec: Some(codec),
+        ..Default::default()
+    };
+    handle_unpack_with(&archive, &output_dir, &options).unwrap();
+    assert_eq!(
+        fs::read(output_dir.join("sub").join("a.txt")).unwrap(),
+        b"a"
+    );
+}
+
+#[test]
+fn test_name_codec_encodability() {
+    // Not CP932 encodable, which only the Silky codec cares about
+    assert!(matches!(
+        encrypt_name("😀.txt"),
+        Err(ArcError::NameEncodeError(_))
+    ));
+    assert_eq!(validate_entry_name_with("😀.txt", &XorCodec), Ok(()));
+
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("😀.txt"), "smile").unwrap();
+    let archive = temp_dir.path().join("xor.arc");
+    assert!(matches!(
+        handle_pack(&input_dir, &archive, false),
+        Err(ArcError::InvalidEntryName(_, NameError::NotEncodable))
+    ));
+    let codec: Arc<dyn NameCodec> = Arc::new(XorCodec);
+    let options = PackOptions {
+        name_codec: Some(codec.clone()),
+        ..Default::default()
+    };
+    handle_pack_with(&input_dir, &archive, &options).unwrap();
+    let reader = ArcReader::open_with_codec(&archive, &XorCodec).unwrap();
+    assert_eq!(reader.entries()[0].name(), "😀.txt");
+    let output_dir = temp_dir.path().join("output");
+    let options = UnpackOptions {
+        name_codec: Some(codec),
+        ..Default::default()
+    };
+    handle_unpack_with(&archive, &output_dir, &options).unwrap();
+    assert_eq!(fs::read(output_dir.join("😀.txt")).unwrap(), b"smile");
+}
+
+#[test]
+fn test_memory_limit() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("sub")).unwrap();
+    let large: Vec<u8> = (0..20_000u32).map(|i| (i * i / 7 % 251) as u8).collect();
+    fs::write(input_dir.join("large.bin"), &large).unwrap();
+    fs::write(input_dir.join("sub").join("copy.bin"), &large).unwrap();
+    for i in 0..8 {
+        fs::write(
+            input_dir.join(format!("{i}.txt")),
+            format!("{i} ").repeat(500),
+        )
+        .unwrap();
+    }
+    fs::write(input_dir.join("empty.txt"), "").unwrap();
+
+    let unlimited = temp_dir.path().join("unlimited.arc");
+    let limited = temp_dir.path().join("limited.arc");
+    let options = PackOptions {
+        compress: true,
+        dedup: true,
+        ..Default::default()
+    };
+    handle_pack_with(&input_dir, &unlimited, &options).unwrap();
+    // Smaller than the large file, which is then processed alone
+    let options = PackOptions {
+        memory_limit: Some(4096),
+        temp_dir: Some(temp_dir.path().to_path_buf()),
+        ..options
+    };
+    handle_pack_with(&input_dir, &limited, &options).unwrap();
+    assert_eq!(fs::read(&limited).unwrap(), fs::read(&unlimited).unwrap());
+    // The spill file is gone with the pack
+    let leftovers: Vec<_> = fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(leftovers.len(), 3, "{leftovers:?}");
+    // Spilled data still passes the layout's length check
+    let cli_archive = temp_dir.path().join("cli.arc");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_silkyarctool"))
+        .arg("pack")
+        .arg(&input_dir)
+        .arg("-o")
+        .arg(&cli_archive)
+        .args([
+            "--memory-limit",
+            "4K",
+            "--compress",
+            "--dedup",
+            "--no-backup",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("Internal inconsistency"), "{stderr}");
+    assert_eq!(
+        fs::read(&cli_archive).unwrap(),
+        fs::read(&unlimited).unwrap()
+    );
+    fs::remove_file(&cli_archive).unwrap();
+
+    let output_dir = temp_dir.path().join("output");
+    let options = UnpackOptions {
+        memory_limit: Some(4096),
+        ..Default::default()
+    };
+    handle_unpack_with(&limited, &output_dir, &options).unwrap();
+    assert_eq!(
+        fs::read(output_dir.join("sub").join("copy.bin")).unwrap(),
+        large
+    );
+    assert_eq!(
+        fs::read_to_string(output_dir.join("7.txt")).unwrap(),
+        "7 ".repeat(500)
+    );
+    assert!(fs::read(output_dir.join("empty.txt")).unwrap().is_empty());
+
+    // Edits write the new data from the spill file as well
+    let options = PackOptions {
+        memory_limit: Some(4096),
+        compress: true,
+        ..Default::default()
+    };
+    let mut editor = ArcEditor::with_options(&limited, options).unwrap();
+    editor.add("added.txt", input_dir.join("0.txt")).unwrap();
+    editor.commit().unwrap();
+    let mut reader = ArcReader::open(&limited).unwrap();
+    let entry = reader.get("added.txt").cloned().unwrap();
+    assert_eq!(
+        reader.read_entry(&entry).unwrap(),
+        "0 ".repeat(500).as_bytes()
+    );
+}
+
+#[test]
+fn test_parse_hex() {
+    assert_eq!(parse_hex("8a 9C\t0f").unwrap().0, [0x8a, 0x9c, 0x0f]);
+    assert!(parse_hex("").unwrap().0.is_empty());
+    assert!(parse_hex("abc").is_err());
+    // Rejected before pairing digits, so multi-byte characters cannot split
+    assert!(parse_hex("aé1").is_err());
+    assert!(parse_hex("+1").is_err());
+    assert!(parse_hex("こんにちは").is_err());
+}
+
+#[test]
+fn test_pack_order_and_separator() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("script").join("sub")).unwrap();
+    fs::create_dir_all(input_dir.join("empty")).unwrap();
+    for name in ["b.txt", "a.txt", r"script/z.txt", r"script/sub/m.txt"] {
+        fs::write(input_dir.join(name), name).unwrap();
+    }
+    let archive = temp_dir.path().join("sorted.arc");
+    let options = PackOptions {
+        order: EntryOrder::Name,
+        separator: SeparatorStyle::Slash,
+        keep_empty_dirs: true,
+        ..Default::default()
+    };
+    let summary = handle_pack_with(&input_dir, &archive, &options).unwrap();
+    let names: Vec<_> = summary.entries.iter().map(|entry| entry.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "a.txt",
+            "b.txt",
+            "empty/",
+            "script/sub/m.txt",
+            "script/z.txt"
+        ]
+    );
+    verify_pack_with(&input_dir, &archive, &options).unwrap();
+
+    let output_dir = temp_dir.path().join("output");
+    handle_unpack(&archive, &output_dir).unwrap();
+    assert_eq!(
+        fs::read_to_string(output_dir.join("script").join("sub").join("m.txt")).unwrap(),
+        "script/sub/m.txt"
+    );
+    assert!(output_dir.join("empty").is_dir());
+
+    // The default keeps `\`
+    let options = PackOptions {
+        order: EntryOrder::Name,
+        ..Default::default()
+    };
+    let summary = handle_pack_with(&input_dir, &archive, &options).unwrap();
+    assert_eq!(summary.entries[2].name(), r"script\sub\m.txt");
+    assert_eq!("slash".parse(), Ok(SeparatorStyle::Slash));
+    assert!("colon".parse::<SeparatorStyle>().is_err());
+    assert_eq!("NAME".parse(), Ok(EntryOrder::Name));
+}
+
+#[test]
+fn test_cancellation() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(&input_dir).unwrap();
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(input_dir.join(name), name).unwrap();
+    }
+    let out_dir = temp_dir.path().join("out");
+    fs::create_dir_all(&out_dir).unwrap();
+    let output_path = out_dir.join("test.arc");
+
+    // Cancelled while the input is walked
+    let cancel = CancelToken::new();
+    let options = PackOptions {
+        filter: Some(PackFilter::new({
+            let cancel = cancel.clone();
+            move |path| {
+                if path.ends_with("b.txt") {
+                    cancel.cancel();
+                }
+                true
+            }
+        })),
+        cancel: Some(cancel.clone()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        handle_pack_with(&input_dir, &output_path, &options),
+        Err(ArcError::Cancelled)
+    ));
+    assert!(cancel.is_cancelled());
+    // Neither the archive nor its spool file is left behind
+    assert_eq!(fs::read_dir(&out_dir).unwrap().count(), 0);
+
+    let unpack_dir = temp_dir.path().join("unpacked");
+    fs::create_dir_all(&unpack_dir).unwrap();
+    fs::write(unpack_dir.join("test.txt"), "kept").unwrap();
+    let options = UnpackOptions {
+        cancel: Some(cancel),
+        ..Default::default()
+    };
+    assert!(matches!(
+        handle_unpack_with("./test_assets/test.arc", &unpack_dir, &options),
+        Err(ArcError::Cancelled)
+    ));
+    assert!(!unpack_dir.join("KT_A0000.OGG").exists());
+    assert_eq!(fs::read(unpack_dir.join("test.txt")).unwrap(), b"kept");
+}
+
+#[test]
+fn test_backup_archive() {
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("test.arc");
+    fs::write(&archive, "original").unwrap();
+    let first = backup_archive(&archive, None).unwrap();
+    assert_eq!(first, temp_dir.path().join("test.arc.bak"));
+    fs::write(&archive, "modified").unwrap();
+    let second = backup_archive(&archive, None).unwrap();
+    assert_eq!(second, temp_dir.path().join("test.arc.bak.1"));
+    // The first backup keeps the pristine original
+    assert_eq!(fs::read_to_string(first).unwrap(), "original");
+}
+
+#[test]
+fn test_doctor() {
+    assert!(!diagnose("./test_assets/test.arc").unwrap().has_errors());
+
+    let temp_dir = tempdir().unwrap();
+    let truncated = temp_dir.path().join("truncated.arc");
+    let data = fs::read("./test_assets/test.arc").unwrap();
+    fs::write(&truncated, &data[..data.len() - 100]).unwrap();
+    let diagnosis = diagnose(&truncated).unwrap();
+    assert!(diagnosis.has_errors());
+    assert!(
+        diagnosis
+            .findings
+            .iter()
+            .any(|f| f.message.contains("truncated"))
+    );
+}
+
+#[test]
+fn test_read_entries() {
+    let archive = ArcReader::open("./test_assets/test.arc").unwrap();
+    let names: Vec<_> = archive.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(names, ["KT_A0000.OGG", "test.txt"]);
+    assert_eq!(archive.entries()[0].offset(), 4 + archive.metadata_size());
+    assert_eq!(
+        archive.get("test.txt").unwrap().offset(),
+        archive.entries()[1].offset()
+    );
+    assert!(archive.contains("KT_A0000.OGG"));
+    assert!(!archive.contains("kt_a0000.ogg"));
+    assert_eq!(
+        archive.entries()[1].encrypted_name(),
+        encrypt_name("test.txt").unwrap()
+    );
+}
+
+#[test]
+fn test_extension_stats() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("a.TXT"), "a".repeat(1000)).unwrap();
+    fs::write(temp_dir.path().join("b.txt"), "b".repeat(1000)).unwrap();
+    fs::write(temp_dir.path().join("noext"), "c").unwrap();
+    let entries = pack_dry_run(temp_dir.path(), true).unwrap();
+    let stats = extension_stats(&entries);
+    let totals = archive_totals(&entries);
+    assert_eq!(totals.count, 3);
+    assert_eq!(totals.stored_count(), 1);
+    assert_eq!(totals.original_size, 2001);
+    assert!(totals.ratio() < 1.0);
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0].extension, "txt");
+    assert_eq!(stats[0].count, 2);
+    assert_eq!(stats[0].compressed_count, 2);
+    assert!(stats[0].ratio() < 0.5);
+    assert!(stats[0].lzss_saving().unwrap() > 0.5 && !stats[0].lzss_wasted());
+    assert_eq!(stats[1].extension, "");
+    assert_eq!(stats[1].lzss_saving(), None);
+
+    let poor = ExtensionStats {
+        compressed_count: 1,
+        lzss_original_size: 1000,
+        lzss_compressed_size: 950,
+        ..Default::default()
+    };
+    assert!(poor.lzss_wasted());
+}
+
+#[test]
+fn test_unpack_resume() {
+    let temp_dir = tempdir().unwrap();
+    handle_unpack("./test_assets/test.arc", temp_dir.path()).unwrap();
+    let txt = temp_dir.path().join("test.txt");
+    let original = fs::read(&txt).unwrap();
+    let tampered = vec![b'x'; original.len()];
+    fs::write(&txt, &tampered).unwrap();
+
+    let mut options = UnpackOptions {
+        resume: Some(ResumeMode::Size),
+        ..Default::default()
+    };
+    handle_unpack_with("./test_assets/test.arc", temp_dir.path(), &options).unwrap();
+    assert_eq!(fs::read(&txt).unwrap(), tampered);
+
+    options.resume = Some(ResumeMode::Content);
+    handle_unpack_with("./test_assets/test.arc", temp_dir.path(), &options).unwrap();
+    assert_eq!(fs::read(&txt).unwrap(), original);
+}
+
+#[test]
+fn test_unpack_policies() {
+    let temp_dir = tempdir().unwrap();
+    let txt = temp_dir.path().join("test.txt");
+    fs::write(&txt, "old").unwrap();
+    let mut options = UnpackOptions {
+        overwrite: OverwritePolicy::Skip,
+        ..Default::default()
+    };
+    handle_unpack_with("./test_assets/test.arc", temp_dir.path(), &options).unwrap();
+    assert_eq!(fs::read(&txt).unwrap(), b"old");
+    assert!(temp_dir.path().join("KT_A0000.OGG").exists());
+    options.overwrite = OverwritePolicy::Fail;
+    assert!(handle_unpack_with("./test_assets/test.arc", temp_dir.path(), &options).is_err());
+    assert_eq!(fs::read(&txt).unwrap(), b"old");
+    assert_eq!("skip".parse(), Ok(OverwritePolicy::Skip));
+    assert!("never".parse::<OverwritePolicy>().is_err());
+
+    // A directory in the way of an entry
+    let blocked = temp_dir.path().join("blocked");
+    fs::create_dir_all(blocked.join("test.txt")).unwrap();
+    let mut options = UnpackOptions::default();
+    assert!(matches!(
+        handle_unpack_with("./test_assets/test.arc", &blocked, &options),
+        Err(ArcError::PathIo { .. })
+    ));
+    options.keep_going = true;
+    assert!(matches!(
+        handle_unpack_with("./test_assets/test.arc", &blocked, &options),
+        Err(ArcError::ExtractFailed(_, 1))
+    ));
+    assert!(blocked.join("KT_A0000.OGG").is_file());
+
+    let archive = temp_dir.path().join("nested.arc");
+    let mut writer = ArcWriter::create(&archive);
+    writer.add_file(r"a\x.txt", &b"a"[..], false).unwrap();
+    writer.add_file(r"b\x.txt", &b"b"[..], false).unwrap();
+    writer.add_file(r"c\d\y.txt", &b"y"[..], false).unwrap();
+    writer.finish().unwrap();
+    let flat = temp_dir.path().join("flat");
+    let options = UnpackOptions {
+        flatten: true,
+        ..Default::default()
+    };
+    handle_unpack_with(&archive, &flat, &options).unwrap();
+    let mut files: Vec<_> = fs::read_dir(&flat)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["x.txt", "x.txt_1", "y.txt"]);
+    assert_eq!(fs::read(flat.join("x.txt_1")).unwrap(), b"b");
+}
+
+#[test]
+fn test_unpack_lenient() {
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("broken.arc");
+    let mut writer = ArcWriter::create(&archive);
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        writer.add_file(name, name.as_bytes(), false).unwrap();
+    }
+    writer.finish().unwrap();
+    let mut data = fs::read(&archive).unwrap();
+    // The name of the second entry starts after the header, the first
+    // record (1 + 5 + 12 bytes) and its length byte; make it decode to 0xFF
+    data[4 + 18 + 1] = 0xFF - 5;
+    // Cut off the data of the last entry
+    data.truncate(data.len() - 2);
+    fs::write(&archive, &data).unwrap();
+
+    let output_dir = temp_dir.path().join("out");
+    assert!(matches!(
+        handle_unpack_with(&archive, &output_dir, &UnpackOptions::default()),
+        Err(ArcError::NameDecodeError(_))
+    ));
+    let failures = handle_unpack_lenient(&archive, &output_dir, &UnpackOptions::default()).unwrap();
+    let indices: Vec<_> = failures.iter().map(|failure| failure.index).collect();
+    assert_eq!(indices, [1, 2]);
+    assert!(matches!(failures[0].error, ArcError::NameDecodeError(_)));
+    assert!(failures[0].name.ends_with(".txt"));
+    assert_eq!(failures[1].name, "c.txt");
+    assert_eq!(fs::read(output_dir.join("a.txt")).unwrap(), b"a.txt");
+    assert!(output_dir.join(&failures[0].name).is_file());
+    assert!(!output_dir.join("c.txt").exists());
+
+    // Metadata cut in the middle of the second record
+    let (reader, failures) =
+        ArcReader::with_encodings_lenient(Cursor::new(&data[..30]), DEFAULT_NAME_ENCODINGS)
+            .unwrap();
+    assert_eq!(reader.entries().len(), 1);
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].index, 1);
+    assert!(ArcReader::new(Cursor::new(&data[..30])).is_err());
+}
+
+#[test]
+fn test_verify_pack() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("test");
+    let output_path = temp_dir.path().join("test.arc");
+    fs::create_dir_all(input_dir.join("sub")).unwrap();
+    fs::write(input_dir.join("a.txt"), "a".repeat(1000)).unwrap();
+    fs::write(input_dir.join("sub").join("b.txt"), "b").unwrap();
+    handle_pack(&input_dir, &output_path, true).unwrap();
+    verify_pack(&input_dir, &output_path).unwrap();
+
+    fs::write(input_dir.join("a.txt"), "changed").unwrap();
+    assert!(verify_pack(&input_dir, &output_path).is_err());
+}
+
+#[test]
+fn test_unpack_verify_checksums() {
+    let temp_dir = tempdir().unwrap();
+    let sidecar = temp_dir.path().join("test.sha256");
+    fs::write(&sidecar, format!("{}  test.txt\n", "0".repeat(64))).unwrap();
+    let options = UnpackOptions {
+        verify: true,
+        checksums: Some(read_checksum_file(&sidecar).unwrap()),
+        ..Default::default()
+    };
+    let output_dir = temp_dir.path().join("out");
+    assert!(handle_unpack_with("./test_assets/test.arc", &output_dir, &options).is_err());
+    assert!(!output_dir.join("test.txt").exists());
+    assert!(output_dir.join("KT_A0000.OGG").exists());
+}
+
+#[test]
+fn test_archive_checksums() {
+    let checksums = archive_checksums("./test_assets/test.arc").unwrap();
+    let names: Vec<_> = checksums
+        .entries
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(names, ["KT_A0000.OGG", "test.txt"]);
+
+    // The checksum file is accepted by `unpack --checksums`
+    let temp_dir = tempdir().unwrap();
+    let sidecar = temp_dir.path().join("test.sha256");
+    fs::write(&sidecar, checksums.to_checksum_file()).unwrap();
+    let options = UnpackOptions {
+        verify: true,
+        checksums: Some(read_checksum_file(&sidecar).unwrap()),
+        ..Default::default()
+    };
+    handle_unpack_with(
+        "./test_assets/test.arc",
+        temp_dir.path().join("out"),
+        &options,
+    )
+    .unwrap();
+
+    // Repacking with compression and in another order keeps the digest
+    let repacked = temp_dir.path().join("repacked.arc");
+    handle_pack(temp_dir.path().join("out"), &repacked, true).unwrap();
+    let mut reversed = archive_checksums(&repacked).unwrap();
+    reversed.entries.reverse();
+    assert_eq!(reversed.archive_digest(), checksums.archive_digest());
+    reversed.entries[0].1 = "0".repeat(64);
+    assert_ne!(reversed.archive_digest(), checksums.archive_digest());
+}
+
+#[test]
+fn test_lint_directory() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("A.txt"), "a").unwrap();
+    fs::write(temp_dir.path().join("a.TXT"), "a").unwrap();
+    fs::write(temp_dir.path().join("empty.txt"), "").unwrap();
+    fs::write(temp_dir.path().join("ok.txt"), "ok").unwrap();
+    let issues = lint_directory(temp_dir.path(), &LintOptions::default()).unwrap();
+    let kinds: Vec<_> = issues.iter().map(|issue| issue.kind).collect();
+    assert_eq!(kinds, [LintKind::CaseCollision, LintKind::EmptyFile]);
+}
+
+#[test]
+fn test_output_templates() {
+    let temp_dir = tempdir().unwrap();
+    let base = temp_dir.path();
+    assert_eq!(
+        derive_pack_output(&base.join("voice"), None).unwrap(),
+        base.join("voice.arc")
+    );
+    assert_eq!(
+        derive_unpack_output(&base.join("voice.arc"), None).unwrap(),
+        base.join("voice")
+    );
+    assert_eq!(
+        derive_unpack_output(&base.join("voice"), None).unwrap(),
+        base.join("voice_unpacked")
+    );
+    assert_eq!(
+        derive_unpack_output(&base.join("voice.arc"), Some("out/{stem}_{ext}")).unwrap(),
+        base.join("out").join("voice_arc")
+    );
+    assert!(expand_output_template("{unknown}", &base.join("voice")).is_err());
+    assert_eq!(
+        derive_pack_output_in(&base.join("voice"), None, &base.join("build")).unwrap(),
+        base.join("build").join("voice.arc")
+    );
+
+    let archive = base.join("data").join("voice.arc");
+    let cwd = base.join("cwd");
+    assert_eq!(
+        resolve_unpack_output(&archive, None, UnpackOutput::Below(&cwd)).unwrap(),
+        cwd.join("voice")
+    );
+    assert_eq!(
+        resolve_unpack_output(&archive, Some(base), UnpackOutput::Below(&cwd)).unwrap(),
+        cwd.join("data").join("voice")
+    );
+    assert_eq!(
+        resolve_unpack_output(&archive, Some(base), UnpackOutput::Explicit(&cwd)).unwrap(),
+        cwd.join("data").join("voice")
+    );
+    assert_eq!(
+        resolve_unpack_output(&archive, None, UnpackOutput::Derived(None)).unwrap(),
+        base.join("data").join("voice")
+    );
+    assert_eq!(
+        resolve_unpack_output(&base.join("voice"), None, UnpackOutput::Below(base)).unwrap(),
+        base.join("voice_unpacked")
+    );
+    assert!(resolve_unpack_output(&archive, None, UnpackOutput::Explicit(&archive)).is_err());
+}
+
+#[test]
+fn test_batch_discovery() {
+    let temp_dir = tempdir().unwrap();
+    let base = temp_dir.path();
+    fs::create_dir_all(base.join("voice").join("nested")).unwrap();
+    fs::create_dir_all(base.join("data")).unwrap();
+    fs::write(base.join("data.arc"), "").unwrap();
+    fs::write(base.join("voice").join("nested").join("se.ARC"), "").unwrap();
+    fs::write(base.join("readme.txt"), "").unwrap();
+
+    assert_eq!(
+        subdirectories(base).unwrap(),
+        [base.join("data"), base.join("voice")]
+    );
+    assert_eq!(find_archives(base, false).unwrap(), [base.join("data.arc")]);
+    assert_eq!(
+        find_archives(base, true).unwrap(),
+        [
+            base.join("data.arc"),
+            base.join("voice").join("nested").join("se.ARC")
+        ]
+    );
+
+    fs::write(base.join("bgm.arc"), "").unwrap();
+    assert_eq!(
+        expand_file_pattern(base.join("*.ARC")).unwrap(),
+        [base.join("bgm.arc"), base.join("data.arc")]
+    );
+    assert_eq!(
+        expand_file_pattern(base.join("readme.txt")).unwrap(),
+        [base.join("readme.txt")]
+    );
+    assert!(expand_file_pattern(base.join("*.pak")).is_err());
+}
+
+#[test]
+fn test_pack_temp_dir() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("test");
+    let spool_dir = temp_dir.path().join("spool");
+    let output_path = temp_dir.path().join("test.arc");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("test.txt"), "test").unwrap();
+    let options = PackOptions {
+        temp_dir: Some(spool_dir.clone()),
+        ..Default::default()
+    };
+    handle_pack_with(&input_dir, &output_path, &options).unwrap();
+    verify_pack(&input_dir, &output_path).unwrap();
+    assert_eq!(fs::read_dir(&spool_dir).unwrap().count(), 0);
+}
+
+#[test]
+fn test_pack_empty_input() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("empty");
+    let output_path = temp_dir.path().join("empty.arc");
+    fs::create_dir_all(&input_dir).unwrap();
+    let result = handle_pack_with(&input_dir, &output_path, &PackOptions::default());
+    assert!(matches!(result, Err(ArcError::EmptyInput(_))));
+    assert!(!output_path.exists());
+
+    let options = PackOptions {
+        allow_empty: true,
+        ..Default::default()
+    };
+    handle_pack_with(&input_dir, &output_path, &options).unwrap();
+    assert!(ArcReader::open(&output_path).unwrap().entries().is_empty());
+}
+
+#[test]
+fn test_custom_thread_pool() {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .build()
+        .unwrap();
+    let temp_dir = tempdir().unwrap();
+    let options = UnpackOptions {
+        parallelism: Parallelism::Pool(Arc::new(pool)),
+        ..Default::default()
+    };
+    handle_unpack_with("./test_assets/test.arc", temp_dir.path(), &options).unwrap();
+    assert!(temp_dir.path().join("test.txt").exists());
+
+    let output_path = temp_dir.path().join("repacked.arc");
+    let options = PackOptions {
+        parallelism: Parallelism::Threads(1),
+        ..Default::default()
+    };
+    handle_pack_with(temp_dir.path(), &output_path, &options).unwrap();
+    assert_eq!(ArcReader::open(&output_path).unwrap().entries().len(), 2);
+}
+
+#[test]
+fn test_name_path_conversion() {
+    let path = Path::new("voice").join("0001.ogg");
+    assert_eq!(path_to_entry_name(&path).unwrap(), "voice\\0001.ogg");
+    assert_eq!(entry_name_to_path("voice\\0001.ogg"), path);
+    assert_eq!(
+        entry_name_to_path("..\\..\\evil.txt"),
+        Path::new("evil.txt")
+    );
+    let (name, encrypted) = encode_path(&path).unwrap();
+    assert_eq!(decrypt_name(&encrypted).unwrap(), name);
+    assert!(encode_path(Path::new(&"a".repeat(300))).is_err());
+
+    // Nested directories survive a pack/unpack round trip
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("voice")).unwrap();
+    fs::write(input_dir.join(&path), "ogg").unwrap();
+    let archive = temp_dir.path().join("input.arc");
+    handle_pack(&input_dir, &archive, false).unwrap();
+    let output_dir = temp_dir.path().join("output");
+    handle_unpack(&archive, &output_dir).unwrap();
+    assert_eq!(fs::read(output_dir.join(&path)).unwrap(), b"ogg");
+}
+
+#[test]
+fn test_validate_entry_name() {
+    assert_eq!(validate_entry_name("voice\\0001.ogg"), Ok(()));
+    assert_eq!(validate_entry_name(""), Err(NameError::Empty));
+    assert_eq!(
+        validate_entry_name("voice/0001.ogg"),
+        Err(NameError::ForwardSlash)
+    );
+    assert_eq!(
+        validate_entry_name("voice\\\\0001.ogg"),
+        Err(NameError::InvalidComponent)
+    );
+    assert_eq!(
+        validate_entry_name("what?.txt"),
+        Err(NameError::ForbiddenCharacter('?'))
+    );
+    assert_eq!(validate_entry_name("😀.txt"), Err(NameError::NotEncodable));
+    assert_eq!(
+        validate_entry_name(&"あ".repeat(200)),
+        Err(NameError::TooLong(400))
+    );
+}
+
+#[test]
+fn test_pack_summary() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("test");
+    let output_path = temp_dir.path().join("test.arc");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("a.txt"), "a".repeat(1000)).unwrap();
+    fs::write(input_dir.join("b.txt"), "b").unwrap();
+    let summary = handle_pack(&input_dir, &output_path, true).unwrap();
+    assert_eq!(summary.entry_count(), 2);
+    assert_eq!(
+        summary.archive_size(),
+        fs::metadata(&output_path).unwrap().len()
+    );
+
+    let archive = ArcReader::open(&output_path).unwrap();
+    assert_eq!(summary.metadata_size, archive.metadata_size());
+    for (written, read) in summary.entries.iter().zip(archive.entries()) {
+        assert_eq!(written.name(), read.name());
+        assert_eq!(written.offset(), read.offset());
+        assert_eq!(written.compressed_size(), read.compressed_size());
+    }
+}
+
+#[test]
+fn test_io_error_has_path() {
+    let temp_dir = tempdir().unwrap();
+    let blocker = temp_dir.path().join("blocker");
+    fs::write(&blocker, "not a directory").unwrap();
+    let output_dir = blocker.join("out");
+    match handle_unpack("./test_assets/test.arc", &output_dir) {
+        Err(ArcError::PathIo { path, .. }) => assert_eq!(path, output_dir),
+        other => panic!("expected PathIo error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_metrics() {
+    let temp_dir = tempdir().unwrap();
+    let output_dir = temp_dir.path().join("out");
+    let metrics = Arc::new(Metrics::new());
+    let options = UnpackOptions {
+        metrics: Some(metrics.clone()),
+        ..Default::default()
+    };
+    handle_unpack_with("./test_assets/test.arc", &output_dir, &options).unwrap();
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.entries_processed, 2);
+    assert_eq!(snapshot.bytes_written, 6807 + 251);
+    assert_eq!(snapshot.bytes_total, 6807 + 251);
+    assert_eq!(snapshot.bytes_done, snapshot.bytes_total);
+
+    let metrics = Arc::new(Metrics::new());
+    let options = PackOptions {
+        compress: true,
+        metrics: Some(metrics.clone()),
+        ..Default::default()
+    };
+    let summary = handle_pack_with(&output_dir, temp_dir.path().join("re.arc"), &options).unwrap();
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.entries_processed, 2);
+    assert_eq!(snapshot.bytes_read, 6807 + 251);
+    assert_eq!(snapshot.bytes_written, summary.data_size);
+    assert_eq!(snapshot.bytes_total, 6807 + 251);
+    assert_eq!(snapshot.bytes_done, snapshot.bytes_total);
+}
+
+#[test]
+fn test_cp932_fallbacks() {
+    assert_eq!(map_cp932_fallbacks("a〜b", CP932_FALLBACKS), "a～b");
+    assert!(matches!(
+        map_cp932_fallbacks("plain.txt", CP932_FALLBACKS),
+        Cow::Borrowed(_)
+    ));
+
+    // The wave dash is not encodable as is, but is stored as the fullwidth
+    // tilde CP932 uses for the same bytes
+    let path = Path::new("bgm〜1.ogg");
+    assert!(encode_path_with(path, &[]).is_err());
+    let (name, encrypted) = encode_path(path).unwrap();
+    assert_eq!(name, "bgm～1.ogg");
+    assert_eq!(decrypt_name(&encrypted).unwrap(), name);
+
+    let (name, _) = encode_path_with(Path::new("a_b.txt"), &[('_', '-')]).unwrap();
+    assert_eq!(name, "a-b.txt");
+}
+
+#[test]
+fn test_detect_name_encoding() {
+    let shift = |bytes: &[u8]| {
+        let mut shifted = bytes.to_vec();
+        for (k, byte) in shifted.iter_mut().rev().enumerate() {
+            *byte = byte.wrapping_sub((k + 1) as u8);
+        }
+        shifted
+    };
+    let cp932 = encrypt_name("テスト.txt").unwrap();
+    assert_eq!(
+        detect_name_encoding(&[&cp932], DEFAULT_NAME_ENCODINGS),
+        Some((NameEncoding::Cp932, vec!["テスト.txt".to_string()]))
+    );
+
+    // "测试.txt" in GBK; CP932 decodes this without errors, but as
+    // half-width katakana
+    let gbk = shift(&[0xB2, 0xE2, 0xCA, 0xD4, b'.', b't', b'x', b't']);
+    assert_eq!(
+        detect_name_encoding(&[&gbk], DEFAULT_NAME_ENCODINGS),
+        Some((NameEncoding::Gbk, vec!["测试.txt".to_string()]))
+    );
+    assert!(detect_name_encoding(&[&gbk], &[NameEncoding::Utf8]).is_none());
+}
+
+#[test]
+fn test_out_of_order_layout() {
+    // Swap the two metadata entries of the test archive, leaving the data
+    // where it is, and append some padding
+    let data = fs::read("./test_assets/test.arc").unwrap();
+    let metadata_end = 4 + u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+    let first_len = 1 + data[4] as usize + 12;
+    let mut shuffled = data[..4].to_vec();
+    shuffled.extend_from_slice(&data[4 + first_len..metadata_end]);
+    shuffled.extend_from_slice(&data[4..4 + first_len]);
+    shuffled.extend_from_slice(&data[metadata_end..]);
+    shuffled.extend_from_slice(&[0; 16]);
+
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("shuffled.arc");
+    fs::write(&archive, shuffled).unwrap();
+
+    let diagnosis = diagnose(&archive).unwrap();
+    assert!(!diagnosis.has_errors());
+    let messages: Vec<_> = diagnosis.findings.iter().map(|f| &f.message).collect();
+    assert!(
+        messages
+            .iter()
+            .any(|m| m.contains("not listed in data order"))
+    );
+    assert!(messages.iter().any(|m| m.contains("16 trailing bytes")));
+
+    let (expected_dir, output_dir) = (
+        temp_dir.path().join("expected"),
+        temp_dir.path().join("out"),
+    );
+    handle_unpack("./test_assets/test.arc", &expected_dir).unwrap();
+    handle_unpack(&archive, &output_dir).unwrap();
+    for name in ["KT_A0000.OGG", "test.txt"] {
+        assert_eq!(
+            fs::read(output_dir.join(name)).unwrap(),
+            fs::read(expected_dir.join(name)).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_shared_data_blocks() {
+    // Point the second entry of the test archive at the first one's data
+    let mut data = fs::read("./test_assets/test.arc").unwrap();
+    let first_fields = 4 + 1 + data[4] as usize;
+    let second_fields = first_fields + 12 + 1 + data[first_fields + 12] as usize;
+    let fields = data[first_fields..first_fields + 12].to_vec();
+    data[second_fields..second_fields + 12].copy_from_slice(&fields);
+
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("shared.arc");
+    fs::write(&archive, data).unwrap();
+
+    let entries = ArcReader::open(&archive).unwrap().entries().to_vec();
+    assert_eq!(shared_data_sources(&entries), [None, Some(0)]);
+    let stats = extension_stats(&entries);
+    assert_eq!(
+        stats.iter().map(|s| s.compressed_size).sum::<u64>(),
+        entries[0].compressed_size() as u64
+    );
+
+    let output_dir = temp_dir.path().join("out");
+    let options = UnpackOptions {
+        hardlink_shared: true,
+        ..Default::default()
+    };
+    handle_unpack_with(&archive, &output_dir, &options).unwrap();
+    let (ogg, txt) = (output_dir.join("KT_A0000.OGG"), output_dir.join("test.txt"));
+    assert_eq!(fs::read(&ogg).unwrap(), fs::read(&txt).unwrap());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        assert_eq!(
+            fs::metadata(&ogg).unwrap().ino(),
+            fs::metadata(&txt).unwrap().ino()
+        );
+    }
+}
+
+/// Writes an uncompressed archive with the given raw (encrypted) names.
+fn write_raw_archive(path: &Path, entries: &[(Vec<u8>, &[u8])]) {
+    let metadata_size: usize = entries.iter().map(|(name, _)| 1 + name.len() + 12).sum();
+    let mut data = (metadata_size as u32).to_le_bytes().to_vec();
+    let mut offset = 4 + metadata_size as u32;
+    for (name, content) in entries {
+        data.push(name.len() as u8);
+        data.extend_from_slice(name);
+        for field in [content.len() as u32, content.len() as u32, offset] {
+            data.extend_from_slice(&field.to_be_bytes());
+        }
+        offset += content.len() as u32;
+    }
+    for (_, content) in entries {
+        data.extend_from_slice(content);
+    }
+    fs::write(path, data).unwrap();
+}
+
+#[test]
+fn test_sanitize_entry_names() {
+    assert_eq!(sanitize_entry_name("a\0b.txt", 0), "a_b.txt");
+    assert_eq!(sanitize_entry_name("", 3), "_unnamed_3");
+    assert_eq!(sanitize_entry_name("..\\.", 1), "_unnamed_1");
+    assert!(matches!(sanitize_entry_name("ok.txt", 0), Cow::Borrowed(_)));
+
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("names.arc");
+    write_raw_archive(
+        &archive,
+        &[
+            (Vec::new(), b"empty name"),
+            (encrypt_name("a\u{1}b.txt").unwrap(), b"control"),
+            (encrypt_name("a_b.txt").unwrap(), b"taken"),
+        ],
+    );
+    let output_dir = temp_dir.path().join("out");
+    handle_unpack(&archive, &output_dir).unwrap();
+    let read = |name: &str| fs::read_to_string(output_dir.join(name)).unwrap();
+    assert_eq!(read("_unnamed_0"), "empty name");
+    assert_eq!(read("a_b.txt"), "taken");
+    assert_eq!(read("a_b.txt_1"), "control");
+}
+
+#[test]
+fn test_undecodable_names() {
+    let mut writer = ArcWriter::create("");
+    writer.add_file("readme.txt", &b"a"[..], false).unwrap();
+    writer.add_file("テスト.txt", &b"b"[..], false).unwrap();
+    let mut archive = Cursor::new(Vec::new());
+    writer.finish_to(&mut archive).unwrap();
+    let data = archive.into_inner();
+
+    // The error names the entry that failed, not just that one did
+    let error = ArcReader::with_encodings(Cursor::new(&data), &[NameEncoding::Utf8]).unwrap_err();
+    let expected = encrypt_name("テスト.txt").unwrap();
+    assert!(matches!(&error, ArcError::NameDecodeError(bytes) if *bytes == expected));
+    let error = ArcReader::with_encodings(Cursor::new(&data), &[]).unwrap_err();
+    let expected = encrypt_name("readme.txt").unwrap();
+    assert!(matches!(&error, ArcError::NameDecodeError(bytes) if *bytes == expected));
+}
+
+#[test]
+fn test_legacy_empty_header() {
+    assert_eq!(effective_metadata_size(4, 4), 0);
+    assert_eq!(effective_metadata_size(4, 40), 4);
+    assert_eq!(effective_metadata_size(0, 4), 0);
+
+    // Older versions wrote empty archives as `04 00 00 00`
+    let temp_dir = tempdir().unwrap();
+    let legacy = temp_dir.path().join("legacy.arc");
+    fs::write(&legacy, 4u32.to_le_bytes()).unwrap();
+    let reader = ArcReader::open(&legacy).unwrap();
+    assert!(reader.entries().is_empty());
+    assert_eq!(reader.metadata_size(), 0);
+    assert!(!diagnose(&legacy).unwrap().has_errors());
+    let output_dir = temp_dir.path().join("output");
+    handle_unpack(&legacy, &output_dir).unwrap();
+    assert_eq!(fs::read_dir(&output_dir).unwrap().count(), 0);
+
+    // Any other file claiming 4 metadata bytes must hold them
+    fs::write(&legacy, [4, 0, 0, 0, 1]).unwrap();
+    assert!(ArcReader::open(&legacy).is_err());
+}
+
+#[test]
+fn test_format_round_trip() {
+    let data = fs::read("./test_assets/test.arc").unwrap();
+    let metadata_size = parse_header(&data).unwrap();
+    let metadata = &data[4..4 + metadata_size as usize];
+    let records = parse_metadata(metadata).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].offset, 4 + metadata_size);
+
+    let mut written = Vec::new();
+    write_header(&mut written, metadata_size);
+    for record in &records {
+        write_entry(&mut written, record).unwrap();
+    }
+    assert_eq!(written, data[..4 + metadata_size as usize]);
+
+    assert!(parse_header(&data[..3]).is_err());
+    assert!(parse_entry(&metadata[..10]).is_err());
+    let too_long = EntryRecord {
+        encrypted_name: vec![0; 256],
+        ..Default::default()
+    };
+    assert!(write_entry(&mut Vec::new(), &too_long).is_err());
+}
+
+#[test]
+fn test_untrusted_sizes() {
+    // A huge original size must not turn into a huge allocation
+    assert!(decompress_data(&[0x00, 1, 2, 3], u32::MAX).is_ok());
+
+    // An entry claiming more data than the file holds fails cleanly
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("bad.arc");
+    let mut data = fs::read("./test_assets/test.arc").unwrap();
+    let fields = 4 + 1 + data[4] as usize;
+    data[fields..fields + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+    fs::write(&archive, data).unwrap();
+    let mut reader = ArcReader::open(&archive).unwrap();
+    let entry = reader.entries()[0].clone();
+    assert!(matches!(
+        reader.read_entry(&entry),
+        Err(ArcError::InvalidFormat(_))
+    ));
+}
+
+#[test]
+fn test_synthetic_archive_round_trip() {
+    let options = SynthOptions {
+        seed: 42,
+        entries: 24,
+        size_range: (0, 1024),
+        ..Default::default()
+    };
+    let archive = generate_archive(&options).unwrap();
+    assert_eq!(archive.bytes, generate_archive(&options).unwrap().bytes);
+
+    let temp_dir = tempdir().unwrap();
+    let archive_path = temp_dir.path().join("synth.arc");
+    archive.write_to(&archive_path).unwrap();
+    assert!(!diagnose(&archive_path).unwrap().has_errors());
+
+    let output_dir = temp_dir.path().join("out");
+    handle_unpack(&archive_path, &output_dir).unwrap();
+    for entry in &archive.entries {
+        assert_eq!(
+            fs::read(output_dir.join(entry_name_to_path(&entry.name))).unwrap(),
+            entry.data,
+            "{}",
+            entry.name
+        );
+    }
+    let repacked = temp_dir.path().join("repacked.arc");
+    handle_pack(&output_dir, &repacked, true).unwrap();
+    verify_pack(&output_dir, &repacked).unwrap();
+}
+
+#[test]
+fn test_compat_check() {
+    let temp_dir = tempdir().unwrap();
+    let reference = temp_dir.path().join("reference");
+    handle_unpack("./test_assets/test.arc", &reference).unwrap();
+    let report = compat_check("./test_assets/test.arc", &reference).unwrap();
+    assert!(report.is_compatible());
+    assert_eq!(report.matched, 2);
+
+    fs::write(reference.join("test.txt"), "changed").unwrap();
+    fs::rename(
+        reference.join("KT_A0000.OGG"),
+        reference.join("kt_a0000.ogg"),
+    )
+    .unwrap();
+    fs::write(reference.join("extra.txt"), "").unwrap();
+    let report = compat_check("./test_assets/test.arc", &reference).unwrap();
+    let kinds: Vec<_> = report.divergences.iter().map(|d| &d.kind).collect();
+    assert!(matches!(kinds[0], DivergenceKind::NameDiffers { .. }));
+    assert_eq!(kinds[1], &DivergenceKind::NotInArchive);
+    assert!(matches!(kinds[2], DivergenceKind::ContentDiffers { .. }));
+    assert_eq!(report.matched, 1);
+}
+
+#[test]
+fn test_locale_detection() {
+    assert_eq!(Lang::from_locale("ja_JP.UTF-8"), Some(Lang::Ja));
+    assert_eq!(Lang::from_locale("zh_CN.GB18030"), Some(Lang::ZhCn));
+    assert_eq!(Lang::from_locale("en_US"), Some(Lang::En));
+    assert_eq!(Lang::from_locale("de_DE"), None);
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        Lang::detect(&args(&["tool", "--lang", "ja", "list"])),
+        Lang::Ja
+    );
+    assert_eq!(Lang::detect(&args(&["tool", "--lang=zh-CN"])), Lang::ZhCn);
+}
+
+#[test]
+fn test_strict_mode() {
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("names.arc");
+    write_raw_archive(&archive, &[(Vec::new(), b"empty name")]);
+    let options = UnpackOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let output_dir = temp_dir.path().join("out");
+    assert!(matches!(
+        handle_unpack_with(&archive, &output_dir, &options),
+        Err(ArcError::StrictWarning(_))
+    ));
+    assert!(!output_dir.join("_unnamed_0").exists());
+
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("bgm〜1.ogg"), "").unwrap();
+    let options = PackOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let output = temp_dir.path().join("out.arc");
+    assert!(handle_pack_with(&input_dir, &output, &options).is_err());
+    assert!(!output.exists());
+}
+
+#[test]
+fn test_compression_thresholds() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("bgm")).unwrap();
+    fs::write(input_dir.join("large.txt"), "a".repeat(3000)).unwrap();
+    fs::write(input_dir.join("small.txt"), "b".repeat(1000)).unwrap();
+    fs::write(input_dir.join("bgm").join("01.OGG"), "c".repeat(3000)).unwrap();
+    let output = temp_dir.path().join("out.arc");
+    let options = PackOptions {
+        compress: true,
+        min_compress_size: Some(2000),
+        store_patterns: Some(NamePatterns::new(["*.ogg"]).unwrap()),
+        ..Default::default()
+    };
+    handle_pack_with(&input_dir, &output, &options).unwrap();
+
+    let archive = ArcReader::open(&output).unwrap();
+    let mut compressed: Vec<_> = archive
+        .entries()
+        .iter()
+        .map(|entry| {
+            (
+                entry.name(),
+                entry.compressed_size() != entry.original_size(),
+            )
+        })
+        .collect();
+    compressed.sort();
+    assert_eq!(
+        compressed,
+        [
+            ("bgm\\01.OGG", false),
+            ("large.txt", true),
+            ("small.txt", false)
+        ]
+    );
+    assert!(NamePatterns::new(["[z-a"]).is_err());
+}
+
+#[test]
+fn test_progress_events() {
+    let snapshot = MetricsSnapshot {
+        entries_processed: 3,
+        bytes_done: 1024,
+        bytes_total: 4096,
+        ..Default::default()
+    };
+    let event = json_event("unpack", &snapshot, Duration::from_secs(2), false);
+    assert_eq!(event["event"], "progress");
+    assert_eq!(event["phase"], "unpack");
+    assert_eq!(event["entries_done"], 3);
+    assert_eq!(event["bytes_total"], 4096);
+    assert_eq!(event["eta_secs"], 6.0);
+
+    let event = json_event("pack", &MetricsSnapshot::default(), Duration::ZERO, true);
+    assert_eq!(event["event"], "finished");
+    assert!(event["eta_secs"].is_null());
+    assert_eq!(format_bytes(1536), "1.5 KiB");
+}
+
+#[test]
+fn test_wizard() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("translated");
+    fs::create_dir_all(&input_dir).unwrap();
+
+    // An invalid choice and a missing folder are asked again, the output
+    // takes the default
+    let answers = format!(
+        "4\n2\n{}\n{}\n\ny\nn\n",
+        temp_dir.path().join("missing").display(),
+        input_dir.display()
+    );
+    let mut prompts = Vec::new();
+    let args = wizard::ask(&mut answers.as_bytes(), &mut prompts).unwrap();
+    let output = temp_dir.path().join("translated.arc");
+    assert_eq!(
+        args,
+        [
+            "pack",
+            input_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--compress"
+        ]
+    );
+    assert!(
+        String::from_utf8(prompts)
+            .unwrap()
+            .contains("cannot be used here")
+    );
+
+    // Running out of answers fails instead of asking forever
+    assert!(wizard::ask(&mut "1\n".as_bytes(), &mut Vec::new()).is_err());
+    assert_eq!(
+        wizard::command_line(&["unpack".into(), "my game/data.arc".into()]),
+        "silkyarctool unpack \"my game/data.arc\""
+    );
+}
+
+#[test]
+fn test_provenance() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("a.txt"), "a").unwrap();
+    // A stale copy from unpacking an earlier build gets replaced
+    fs::write(input_dir.join(PROVENANCE_NAME), "stale").unwrap();
+    let output = temp_dir.path().join("out.arc");
+    let options = PackOptions {
+        provenance: true,
+        ..Default::default()
+    };
+    handle_pack_with(&input_dir, &output, &options).unwrap();
+    verify_pack_with(&input_dir, &output, &options).unwrap();
+
+    let mut archive = ArcReader::open(&output).unwrap();
+    assert_eq!(archive.entries().len(), 2);
+    let document = read_provenance(&mut archive).unwrap().unwrap();
+    let document: serde_json::Value = serde_json::from_str(&document).unwrap();
+    assert_eq!(document["tool"], "silky-arc-core");
+    assert_eq!(document["source_files"], 1);
+    assert_eq!(document["settings"]["compress"], false);
+
+    handle_pack(&input_dir, &output, false).unwrap();
+    let mut archive = ArcReader::open(&output).unwrap();
+    assert_eq!(read_provenance(&mut archive).unwrap().unwrap(), "stale");
+}
+
+#[test]
+fn test_entry_selection() {
+    assert_eq!(
+        parse_entry_list("a.txt\r\nsub/b.txt\n\n"),
+        HashSet::from(["a.txt".to_string(), "sub\\b.txt".to_string()])
+    );
+    assert_eq!(
+        parse_entry_list("with space.txt\0b.txt\0"),
+        HashSet::from(["with space.txt".to_string(), "b.txt".to_string()])
+    );
+
+    let temp_dir = tempdir().unwrap();
+    let output_dir = temp_dir.path().join("out");
+    let options = UnpackOptions {
+        entries: Some(HashSet::from(["test.txt".to_string()])),
+        ..Default::default()
+    };
+    handle_unpack_with("./test_assets/test.arc", &output_dir, &options).unwrap();
+    assert!(output_dir.join("test.txt").is_file());
+    assert!(!output_dir.join("KT_A0000.OGG").exists());
+
+    let options = UnpackOptions {
+        entries: Some(HashSet::from(["missing.txt".to_string()])),
+        strict: true,
+        ..Default::default()
+    };
+    assert!(handle_unpack_with("./test_assets/test.arc", &output_dir, &options).is_err());
+
+    let output_dir = temp_dir.path().join("patterns");
+    let options = UnpackOptions {
+        include: Some(NamePatterns::new(["*.ogg", "*.txt"]).unwrap()),
+        exclude: Some(NamePatterns::new(["test.*"]).unwrap()),
+        ..Default::default()
+    };
+    handle_unpack_with("./test_assets/test.arc", &output_dir, &options).unwrap();
+    assert!(output_dir.join("KT_A0000.OGG").is_file());
+    assert!(!output_dir.join("test.txt").exists());
+
+    let output_dir = temp_dir.path().join("only");
+    let options = UnpackOptions {
+        entries: Some(HashSet::from([
+            "test.txt".to_string(),
+            "missing.txt".to_string(),
+        ])),
+        require_entries: true,
+        ..Default::default()
+    };
+    let result = handle_unpack_with("./test_assets/test.arc", &output_dir, &options);
+    assert!(matches!(result, Err(ArcError::MissingEntries(names)) if names == ["missing.txt"]));
+    assert!(!output_dir.join("test.txt").exists());
+}
+
+#[test]
+fn test_rename_rules() {
+    let rule: RenameRule = r"s/^voice\\//".parse().unwrap();
+    assert_eq!(rule.apply(r"voice\a.ogg"), "a.ogg");
+    let rule: RenameRule = r"s|(\w+)\.OGG$|\1.ogg|".parse().unwrap();
+    assert_eq!(rule.apply(r"bgm\01.OGG"), r"bgm\01.ogg");
+    let rule: RenameRule = "s/a/[&]/g".parse().unwrap();
+    assert_eq!(rule.apply("banana"), "b[a]n[a]n[a]");
+    assert!("s/a/b".parse::<RenameRule>().is_err());
+    assert!("s/a/b/x".parse::<RenameRule>().is_err());
+    assert!("s/(/b/".parse::<RenameRule>().is_err());
+
+    let temp_dir = tempdir().unwrap();
+    let output_dir = temp_dir.path().join("out");
+    let options = UnpackOptions {
+        rename_rules: vec![
+            r"s/^/data\\/".parse().unwrap(),
+            r"s/\.OGG$/.ogg/".parse().unwrap(),
+        ],
+        ..Default::default()
+    };
+    handle_unpack_with("./test_assets/test.arc", &output_dir, &options).unwrap();
+    assert!(output_dir.join("data").join("KT_A0000.ogg").is_file());
+    assert!(output_dir.join("data").join("test.txt").is_file());
+}
+
+#[test]
+fn test_find_duplicates() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("sub")).unwrap();
+    fs::write(input_dir.join("a.txt"), "same content").unwrap();
+    fs::write(input_dir.join("sub").join("b.txt"), "same content").unwrap();
+    fs::write(input_dir.join("c.txt"), "other conten").unwrap();
+    let output = temp_dir.path().join("out.arc");
+    handle_pack(&input_dir, &output, false).unwrap();
+
+    let groups = find_duplicates(&output).unwrap();
+    assert_eq!(groups.len(), 1);
+    let mut names = groups[0].names.clone();
+    names.sort();
+    assert_eq!(names, ["a.txt", r"sub\b.txt"]);
+    assert_eq!(groups[0].size, 12);
+    assert_eq!(groups[0].wasted_bytes, 12);
+
+    // Entries already sharing a data block waste nothing
+    let mut data = fs::read("./test_assets/test.arc").unwrap();
+    let first_fields = 4 + 1 + data[4] as usize;
+    let second_fields = first_fields + 12 + 1 + data[first_fields + 12] as usize;
+    let fields = data[first_fields..first_fields + 12].to_vec();
+    data[second_fields..second_fields + 12].copy_from_slice(&fields);
+    fs::write(&output, data).unwrap();
+    let groups = find_duplicates(&output).unwrap();
+    assert_eq!(groups[0].names.len(), 2);
+    assert_eq!(groups[0].wasted_bytes, 0);
+}
+
+#[test]
+fn test_keep_empty_dirs() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("save")).unwrap();
+    fs::create_dir_all(input_dir.join("data").join("empty")).unwrap();
+    fs::write(input_dir.join("data").join("a.txt"), "a").unwrap();
+    let output = temp_dir.path().join("out.arc");
+
+    handle_pack(&input_dir, &output, false).unwrap();
+    assert_eq!(ArcReader::open(&output).unwrap().entries().len(), 1);
+
+    let options = PackOptions {
+        keep_empty_dirs: true,
+        ..Default::default()
+    };
+    handle_pack_with(&input_dir, &output, &options).unwrap();
+    verify_pack_with(&input_dir, &output, &options).unwrap();
+    let archive = ArcReader::open(&output).unwrap();
+    let mut names: Vec<&str> = archive.entries().iter().map(|e| e.name()).collect();
+    names.sort();
+    assert_eq!(names, [r"data\a.txt", r"data\empty\", r"save\"]);
+
+    let output_dir = temp_dir.path().join("unpacked");
+    handle_unpack(&output, &output_dir).unwrap();
+    assert!(output_dir.join("save").is_dir());
+    assert!(output_dir.join("data").join("empty").is_dir());
+    assert!(output_dir.join("data").join("a.txt").is_file());
+}
+
+#[test]
+fn test_size_range() {
+    let range = SizeRange {
+        min: Some(10),
+        max: Some(20),
+    };
+    assert!(!range.contains(9));
+    assert!(range.contains(10) && range.contains(20));
+    assert!(!range.contains(21));
+    assert!(SizeRange::default().contains(0));
+
+    let entries = ArcReader::open("./test_assets/test.arc")
+        .unwrap()
+        .entries()
+        .to_vec();
+    let smallest = entries.iter().map(|e| e.original_size()).min().unwrap();
+    let temp_dir = tempdir().unwrap();
+    let output_dir = temp_dir.path().join("out");
+    let options = UnpackOptions {
+        size_range: SizeRange {
+            max: Some(smallest as u64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    handle_unpack_with("./test_assets/test.arc", &output_dir, &options).unwrap();
+    let extracted = fs::read_dir(&output_dir).unwrap().count();
+    assert!(extracted >= 1 && extracted < entries.len());
+}
+
+#[test]
+fn test_mapped_archive() {
+    let mut archive = ArcReader::open("./test_assets/test.arc").unwrap();
+    let mapped = MappedArchive::open("./test_assets/test.arc").unwrap();
+    assert_eq!(mapped.entries().len(), archive.entries().len());
+    for entry in archive.entries().to_vec() {
+        let data = mapped.entry_bytes(&entry).unwrap();
+        assert!(matches!(data, Cow::Borrowed(_)));
+        assert_eq!(data, archive.read_entry(&entry).unwrap());
+    }
+
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("a.txt"), "a".repeat(1000)).unwrap();
+    let output = temp_dir.path().join("out.arc");
+    handle_pack(&input_dir, &output, true).unwrap();
+    let mapped = MappedArchive::open(&output).unwrap();
+    let entry = mapped.get("a.txt").unwrap();
+    assert!(mapped.stored_bytes(entry).unwrap().len() < 1000);
+    assert_eq!(
+        mapped.entry_bytes(entry).unwrap(),
+        "a".repeat(1000).as_bytes()
+    );
+}
+
+#[cfg(feature = "scripting")]
+#[test]
+fn test_script() {
+    let temp_dir = tempdir().unwrap();
+    let output = temp_dir.path().join("out.arc");
+    let script = temp_dir.path().join("rename.rhai");
+    fs::write(
+        &script,
+        r#"
+            let archive = open_archive(ARGS[0]);
+            let out = new_archive();
+            for entry in archive.entries() {
+                if entry.name.ends_with(".txt") {
+                    let text = decode_cp932(archive.read(entry.name));
+                    out.add("scripts\\" + entry.name, encode_cp932(text + "!"));
+                }
+            }
+            out.write(ARGS[1], true);
+        "#,
+    )
+    .unwrap();
+    let args = [
+        "./test_assets/test.arc".to_string(),
+        output.to_string_lossy().into_owned(),
+    ];
+    silky_arc_tool::script::run_script(&script, &args).unwrap();
+
+    let mut archive = ArcReader::open(&output).unwrap();
+    let entry = archive.get(r"scripts\test.txt").unwrap().clone();
+    assert!(archive.read_entry(&entry).unwrap().ends_with(b"!"));
+    assert_eq!(archive.entries().len(), 1);
+
+    fs::write(&script, "open_archive(\"missing.arc\");").unwrap();
+    assert!(matches!(
+        silky_arc_tool::script::run_script(&script, &[]),
+        Err(ArcError::Script(_))
+    ));
+}
+
+#[test]
+fn test_edit_archive() {
+    let temp_dir = tempdir().unwrap();
+    let new_file = temp_dir.path().join("new.txt");
+    fs::write(&new_file, "n".repeat(1000)).unwrap();
+    let output = temp_dir.path().join("out.arc");
+    let edit = ArchiveEdit {
+        additions: vec![(r"script\new.txt".to_string(), new_file.clone())],
+        ..Default::default()
+    };
+    let options = PackOptions {
+        compress: true,
+        ..Default::default()
+    };
+    edit_archive("./test_assets/test.arc", &output, &edit, &options).unwrap();
+
+    let mut original = ArcReader::open("./test_assets/test.arc").unwrap();
+    let mut edited = ArcReader::open(&output).unwrap();
+    let names: Vec<&str> = edited.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(names, ["KT_A0000.OGG", "test.txt", r"script\new.txt"]);
+    for entry in original.entries().to_vec() {
+        let copy = edited.get(entry.name()).unwrap().clone();
+        assert_eq!(
+            edited.read_entry(&copy).unwrap(),
+            original.read_entry(&entry).unwrap()
+        );
+    }
+    let added = edited.get(r"script\new.txt").unwrap().clone();
+    assert!(added.compressed_size() < added.original_size());
+    assert_eq!(
+        edited.read_entry(&added).unwrap(),
+        "n".repeat(1000).as_bytes()
+    );
+
+    // Adding an entry that already exists fails, in place too
+    let edit = ArchiveEdit {
+        additions: vec![("test.txt".to_string(), new_file)],
+        ..Default::default()
+    };
+    assert!(matches!(
+        edit_archive(&output, &output, &edit, &options),
+        Err(ArcError::DuplicateEntry(_))
+    ));
+
+    let edit = ArchiveEdit {
+        removals: HashSet::from(["KT_A0000.OGG".to_string()]),
+        ..Default::default()
+    };
+    edit_archive(&output, &output, &edit, &PackOptions::default()).unwrap();
+    let mut edited = ArcReader::open(&output).unwrap();
+    let names: Vec<&str> = edited.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(names, ["test.txt", r"script\new.txt"]);
+    assert_eq!(edited.entries()[0].offset(), 4 + edited.metadata_size());
+    let added = edited.get(r"script\new.txt").unwrap().clone();
+    assert_eq!(
+        edited.read_entry(&added).unwrap(),
+        "n".repeat(1000).as_bytes()
+    );
+
+    assert!(matches!(
+        edit_archive(&output, &output, &edit, &PackOptions::default()),
+        Err(ArcError::MissingEntries(_))
+    ));
+
+    // A compressed entry stays compressed when its data is replaced
+    let replacement = temp_dir.path().join("replacement.txt");
+    fs::write(&replacement, "r".repeat(2000)).unwrap();
+    let edit = ArchiveEdit {
+        replacements: HashMap::from([
+            (r"script\new.txt".to_string(), replacement.clone()),
+            ("test.txt".to_string(), replacement),
+        ]),
+        ..Default::default()
+    };
+    edit_archive(&output, &output, &edit, &PackOptions::default()).unwrap();
+    let mut edited = ArcReader::open(&output).unwrap();
+    let names: Vec<&str> = edited.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(names, ["test.txt", r"script\new.txt"]);
+    let (text, script) = (edited.entries()[0].clone(), edited.entries()[1].clone());
+    assert_eq!(text.compressed_size(), 2000);
+    assert!(script.compressed_size() < 2000);
+    assert_eq!(
+        edited.read_entry(&script).unwrap(),
+        "r".repeat(2000).as_bytes()
+    );
+}
+
+#[test]
+fn test_arc_editor() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("edit.arc");
+    let mut writer = ArcWriter::create(&path);
+    writer
+        .add_file("a.txt", "a".repeat(3000).as_bytes(), true)
+        .unwrap();
+    writer.add_file("b.bin", &[7u8; 500][..], false).unwrap();
+    writer
+        .add_file("c.txt", "c".repeat(800).as_bytes(), false)
+        .unwrap();
+    writer.finish().unwrap();
+    let before = ArcReader::open(&path).unwrap().entries().to_vec();
+    let read = |name: &str| {
+        let mut data = Vec::new();
+        let mut archive = ArcReader::open(&path).unwrap();
+        archive.extract_entry_to(name, &mut data).unwrap();
+        data
+    };
+
+    // A longer name grows the metadata over the first block only
+    let mut editor = ArcEditor::open(&path).unwrap();
+    editor.rename("b.bin", r"some\long\folder/b.bin").unwrap();
+    assert!(matches!(
+        editor.rename("a.txt", "c.txt"),
+        Err(ArcError::DuplicateEntry(_))
+    ));
+    assert!(matches!(
+        editor.remove("missing"),
+        Err(ArcError::MissingEntries(_))
+    ));
+    let summary = editor.commit().unwrap();
+    assert_eq!(summary.moved_blocks, 1);
+    assert_eq!(summary.written, before[0].compressed_size() as u64);
+    assert_eq!(summary.archive_size, fs::metadata(&path).unwrap().len());
+    let archive = ArcReader::open(&path).unwrap();
+    let names: Vec<&str> = archive.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(names, ["a.txt", r"some\long\folder\b.bin", "c.txt"]);
+    assert_eq!(archive.entries()[1].offset(), before[1].offset());
+    assert_eq!(read("a.txt"), "a".repeat(3000).as_bytes());
+    assert_eq!(read(r"some\long\folder\b.bin"), [7u8; 500]);
+
+    // Removing the last block truncates the file; new data goes after the
+    // last block that stays
+    let replacement = temp_dir.path().join("replacement.txt");
+    fs::write(&replacement, "r".repeat(2000)).unwrap();
+    let mut editor = ArcEditor::open(&path).unwrap();
+    editor.remove("c.txt").unwrap();
+    editor.replace("a.txt", &replacement).unwrap();
+    editor.add(r"new\r.txt", &replacement).unwrap();
+    let summary = editor.commit().unwrap();
+    assert_eq!(summary.moved_blocks, 0);
+    assert_eq!(summary.archive_size, fs::metadata(&path).unwrap().len());
+    let archive = ArcReader::open(&path).unwrap();
+    let names: Vec<&str> = archive.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(names, ["a.txt", r"some\long\folder\b.bin", r"new\r.txt"]);
+    let entries = archive.entries().to_vec();
+    assert!(entries[0].is_compressed());
+    assert!(!entries[2].is_compressed());
+    assert!(entries[0].offset() > entries[1].offset());
+    for name in ["a.txt", r"new\r.txt"] {
+        assert_eq!(read(name), "r".repeat(2000).as_bytes());
+    }
+    assert_eq!(read(r"some\long\folder\b.bin"), [7u8; 500]);
+}
+
+#[test]
+fn test_verify_archive() {
+    let checks = verify_archive("./test_assets/test.arc").unwrap();
+    assert_eq!(checks.len(), 2);
+    assert!(checks.iter().all(|check| check.passed()));
+
+    let temp_dir = tempdir().unwrap();
+    let truncated = temp_dir.path().join("truncated.arc");
+    let data = fs::read("./test_assets/test.arc").unwrap();
+    fs::write(&truncated, &data[..data.len() - 100]).unwrap();
+    let checks = verify_archive(&truncated).unwrap();
+    assert!(checks[0].passed());
+    assert!(checks[1].problem.as_ref().unwrap().contains("past the end"));
+
+    // Claim a larger original size for a compressed entry
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("a.txt"), "a".repeat(1000)).unwrap();
+    let archive = temp_dir.path().join("corrupt.arc");
+    handle_pack(&input_dir, &archive, true).unwrap();
+    let mut data = fs::read(&archive).unwrap();
+    let original_size = 4 + 1 + data[4] as usize + 4;
+    data[original_size..original_size + 4].copy_from_slice(&2000u32.to_be_bytes());
+    fs::write(&archive, data).unwrap();
+    let checks = verify_archive(&archive).unwrap();
+    assert!(!checks[0].passed());
+}
+
+#[test]
+fn test_diff_archives() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("same.txt"), "s".repeat(1000)).unwrap();
+    fs::write(input_dir.join("changed.txt"), "old").unwrap();
+    fs::write(input_dir.join("removed.txt"), "r").unwrap();
+    let old = temp_dir.path().join("old.arc");
+    handle_pack(&input_dir, &old, false).unwrap();
+
+    fs::write(input_dir.join("changed.txt"), "new").unwrap();
+    fs::remove_file(input_dir.join("removed.txt")).unwrap();
+    fs::write(input_dir.join("added.txt"), "a").unwrap();
+    let new = temp_dir.path().join("new.arc");
+    // Recompressing alone is not a modification
+    handle_pack(&input_dir, &new, true).unwrap();
+
+    let changes = diff_archives(&old, &new, false).unwrap();
+    let summary: Vec<(&str, &Change)> = changes
+        .iter()
+        .map(|change| (change.name.as_str(), &change.change))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("added.txt", &Change::Added),
+            ("changed.txt", &Change::Modified),
+            ("removed.txt", &Change::Removed)
+        ]
+    );
+
+    // Same sizes, so only the stored size of the recompressed entry differs
+    let changes = diff_archives(&old, &new, true).unwrap();
+    let modified: Vec<&str> = changes
+        .iter()
+        .filter(|change| change.change == Change::Modified)
+        .map(|change| change.name.as_str())
+        .collect();
+    assert_eq!(modified, ["same.txt"]);
+}
+
+#[test]
+fn test_overlay_edit() {
+    let temp_dir = tempdir().unwrap();
+    let overlay = temp_dir.path().join("overlay");
+    fs::create_dir_all(overlay.join("script")).unwrap();
+    fs::write(overlay.join("test.txt"), "translated").unwrap();
+    fs::write(overlay.join("script").join("new.txt"), "new").unwrap();
+
+    let edit = overlay_edit("./test_assets/test.arc", &overlay).unwrap();
+    assert_eq!(
+        edit.replacements,
+        HashMap::from([("test.txt".to_string(), overlay.join("test.txt"))])
+    );
+    assert_eq!(
+        edit.additions,
+        [(
+            r"script\new.txt".to_string(),
+            overlay.join("script").join("new.txt")
+        )]
+    );
+
+    let output = temp_dir.path().join("out.arc");
+    edit_archive(
+        "./test_assets/test.arc",
+        &output,
+        &edit,
+        &PackOptions::default(),
+    )
+    .unwrap();
+    let mut archive = ArcReader::open(&output).unwrap();
+    let entry = archive.get("test.txt").unwrap().clone();
+    assert_eq!(archive.read_entry(&entry).unwrap(), b"translated");
+    assert_eq!(archive.entries().len(), 3);
+}
+
+#[test]
+fn test_pack_zip() {
+    use std::io::Write;
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    let temp_dir = tempdir().unwrap();
+    let zip_path = temp_dir.path().join("patch.zip");
+    let mut zip = ZipWriter::new(fs::File::create(&zip_path).unwrap());
+    zip.add_directory("script/", SimpleFileOptions::default())
+        .unwrap();
+    zip.start_file("script/main.txt", SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all("テスト".as_bytes()).unwrap();
+    zip.add_directory("save/", SimpleFileOptions::default())
+        .unwrap();
+    zip.start_file("voice.ogg", SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(&[0u8; 600]).unwrap();
+    zip.finish().unwrap();
+
+    let output = temp_dir.path().join("patch.arc");
+    let options = PackOptions {
+        compress: true,
+        keep_empty_dirs: true,
+        ..Default::default()
+    };
+    let summary = pack_zip(&zip_path, &output, &options).unwrap();
+    assert_eq!(summary.entry_count(), 3);
+    let mut archive = ArcReader::open(&output).unwrap();
+    let names: Vec<&str> = archive.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(names, [r"script\main.txt", r"save\", "voice.ogg"]);
+    let entry = archive.get("voice.ogg").unwrap().clone();
+    assert!(entry.compressed_size() < entry.original_size());
+    assert_eq!(archive.read_entry(&entry).unwrap(), [0u8; 600]);
+    let entry = archive.get(r"script\main.txt").unwrap().clone();
+    assert_eq!(archive.read_entry(&entry).unwrap(), "テスト".as_bytes());
+
+    let empty_zip = temp_dir.path().join("empty.zip");
+    ZipWriter::new(fs::File::create(&empty_zip).unwrap())
+        .finish()
+        .unwrap();
+    assert!(matches!(
+        pack_zip(&empty_zip, &output, &PackOptions::default()),
+        Err(ArcError::EmptyInput(_))
+    ));
+}
+
+#[test]
+fn test_merge_archives() {
+    let temp_dir = tempdir().unwrap();
+    let base = temp_dir.path();
+    for (dir, own, dup) in [("a", "a.txt", "first"), ("b", "b.txt", "second")] {
+        fs::create_dir_all(base.join(dir)).unwrap();
+        fs::write(base.join(dir).join(own), dir).unwrap();
+        fs::write(base.join(dir).join("dup.txt"), dup).unwrap();
+        handle_pack(base.join(dir), base.join(format!("{dir}.arc")), false).unwrap();
+    }
+    let inputs = [base.join("a.arc"), base.join("b.arc")];
+    let output = base.join("merged.arc");
+    let read_dup = || {
+        let mut archive = ArcReader::open(&output).unwrap();
+        let mut names: Vec<String> = archive
+            .entries()
+            .iter()
+            .map(|e| e.name().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["a.txt", "b.txt", "dup.txt"]);
+        let entry = archive.get("dup.txt").unwrap().clone();
+        archive.read_entry(&entry).unwrap()
+    };
+
+    assert!(matches!(
+        merge_archives(&inputs, &output, ConflictPolicy::Error, &PackOptions::default()),
+        Err(ArcError::DuplicateEntry(name)) if name == "dup.txt"
+    ));
+    merge_archives(
+        &inputs,
+        &output,
+        ConflictPolicy::KeepFirst,
+        &PackOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(read_dup(), b"first");
+    merge_archives(
+        &inputs,
+        &output,
+        ConflictPolicy::KeepLast,
+        &PackOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(read_dup(), b"second");
+    assert_eq!("keep-last".parse(), Ok(ConflictPolicy::KeepLast));
+}
+
+#[test]
+fn test_offset_limit() {
+    // Two entries named "0.bin" and "1.bin": 2 * (1 + 5 + 12) metadata bytes
+    assert_eq!(layout_sizes(&[10, 20]).unwrap(), 36);
+    // Data may end exactly at the last u32 offset, not past it
+    let first = u32::MAX - 4 - 36 - 100;
+    assert_eq!(layout_sizes(&[first, 100]).unwrap(), 36);
+    for sizes in [[first, 101], [u32::MAX, u32::MAX]] {
+        let err = layout_sizes(&sizes).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("the archive exceeds the 4 GiB offset limit"),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+fn test_split_archive() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("big");
+    fs::create_dir_all(&input_dir).unwrap();
+    for i in 0..5 {
+        fs::write(input_dir.join(format!("f{i}.bin")), [i as u8; 100]).unwrap();
+    }
+    let archive_path = temp_dir.path().join("big.arc");
+    handle_pack(&input_dir, &archive_path, false).unwrap();
+    // Header plus two entries of 1 + 6 + 12 + 100 bytes each
+    let max_size = 4 + 2 * 119;
+    let entries = ArcReader::open(&archive_path).unwrap().entries().to_vec();
+    assert_eq!(plan_split(&entries, max_size), [0..2, 2..4, 4..5]);
+
+    let output_dir = temp_dir.path().join("parts");
+    fs::create_dir_all(&output_dir).unwrap();
+    let parts = split_archive(
+        &archive_path,
+        &output_dir,
+        max_size,
+        &PackOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(parts.len(), 3);
+    assert_eq!(parts[0].0, output_dir.join("big_001.arc"));
+    let mut names = Vec::new();
+    for (path, summary) in &parts {
+        assert!(fs::metadata(path).unwrap().len() <= max_size);
+        assert_eq!(summary.archive_size(), fs::metadata(path).unwrap().len());
+        let mut archive = ArcReader::open(path).unwrap();
+        for entry in archive.entries().to_vec() {
+            let data = archive.read_entry(&entry).unwrap();
+            assert_eq!(data, fs::read(input_dir.join(entry.name())).unwrap());
+            names.push(entry.name().to_string());
+        }
+    }
+    assert_eq!(names.len(), 5);
+
+    assert!(
+        split_archive(
+            &archive_path,
+            &output_dir,
+            50,
+            &PackOptions {
+                strict: true,
+                ..Default::default()
+            }
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_pack_dedup() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("copy")).unwrap();
+    let content = "the same line again\n".repeat(50);
+    fs::write(input_dir.join("a.txt"), &content).unwrap();
+    fs::write(input_dir.join("copy").join("a.txt"), &content).unwrap();
+    fs::write(input_dir.join("b.txt"), content.to_uppercase()).unwrap();
+    let plain = temp_dir.path().join("plain.arc");
+    let deduped = temp_dir.path().join("deduped.arc");
+
+    let options = PackOptions {
+        compress: true,
+        ..Default::default()
+    };
+    let plain_summary = handle_pack_with(&input_dir, &plain, &options).unwrap();
+    let options = PackOptions {
+        dedup: true,
+        ..options
+    };
+    let summary = handle_pack_with(&input_dir, &deduped, &options).unwrap();
+    let stored = |name: &str| {
+        summary
+            .entries
+            .iter()
+            .find(|e| e.name() == name)
+            .unwrap()
+            .compressed_size() as u64
+    };
+    assert_eq!(
+        summary.archive_size(),
+        plain_summary.archive_size() - stored("a.txt")
+    );
+    assert_eq!(
+        fs::metadata(&deduped).unwrap().len(),
+        summary.archive_size()
+    );
+    verify_pack_with(&input_dir, &deduped, &options).unwrap();
+
+    let archive = ArcReader::open(&deduped).unwrap();
+    let sharing = shared_data_sources(archive.entries())
+        .iter()
+        .flatten()
+        .count();
+    assert_eq!(sharing, 1);
+    assert_eq!(find_duplicates(&deduped).unwrap()[0].wasted_bytes, 0);
+}
+
+#[test]
+fn test_grep_archive() {
+    let temp_dir = tempdir().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("script")).unwrap();
+    // "こんにちは\r\n世界\n" in CP932
+    let script = [
+        &[
+            0x82, 0xb1, 0x82, 0xf1, 0x82, 0xc9, 0x82, 0xbf, 0x82, 0xcd, b'\r', b'\n',
+        ][..],
+        &[0x90, 0xa2, 0x8a, 0x45, b'\n'],
+    ]
+    .concat();
+    fs::write(input_dir.join("script").join("a.txt"), &script).unwrap();
+    fs::write(input_dir.join("data.bin"), [0u8, 0xde, 0xad, 0xbe, 0xef]).unwrap();
+    let archive = temp_dir.path().join("out.arc");
+    handle_pack(&input_dir, &archive, true).unwrap();
+
+    let options = GrepOptions::default();
+    let matches = grep_archive(
+        &archive,
+        &GrepPattern::text("世界", false).unwrap(),
+        &options,
+    )
+    .unwrap();
+    assert_eq!(
+        matches,
+        [GrepMatch {
+            entry: r"script\a.txt".to_string(),
+            offset: 12,
+            line_number: Some(2),
+            text: "世界".to_string(),
+        }]
+    );
+
+    let matches = grep_archive(&archive, &GrepPattern::Bytes(vec![0xad, 0xbe]), &options).unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        (matches[0].entry.as_str(), matches[0].offset),
+        ("data.bin", 2)
+    );
+    assert_eq!(matches[0].text, "ad be ef");
+
+    let options = GrepOptions {
+        include: Some(NamePatterns::new(["*.bin"]).unwrap()),
+        ..Default::default()
+    };
+    let matches = grep_archive(
+        &archive,
+        &GrepPattern::text("世界", false).unwrap(),
+        &options,
+    )
+    .unwrap();
+    assert!(matches.is_empty());
+    assert!(GrepPattern::text("(", false).is_err());
+}
+
+#[test]
+fn test_pack_manifest() {
+    let temp_dir = tempdir().unwrap();
+    let base = temp_dir.path();
+    fs::create_dir_all(base.join("text")).unwrap();
+    fs::write(base.join("text").join("main.txt"), "line\n".repeat(200)).unwrap();
+    fs::write(base.join("text").join("other.txt"), "line\n".repeat(200)).unwrap();
+    let manifest = base.join("files.json");
+    fs::write(
+        &manifest,
+        r#"[
+            { "source": "text/other.txt", "name": "z_first.txt", "compress": false },
+            { "source": "text/main.txt", "name": "script/main.txt" }
+        ]"#,
+    )
+    .unwrap();
+
+    let entries = read_manifest(&manifest).unwrap();
+    assert_eq!(entries[0].source, base.join("text/other.txt"));
+    assert_eq!(entries[1].name, r"script\main.txt");
+    assert_eq!(entries[1].compress, None);
+
+    let output = base.join("files.arc");
+    let options = PackOptions {
+        compress: true,
+        ..Default::default()
+    };
+    pack_manifest(&manifest, &output, &options).unwrap();
+    let mut archive = ArcReader::open(&output).unwrap();
+    let names: Vec<&str> = archive.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(names, ["z_first.txt", r"script\main.txt"]);
+    let [first, second] = archive.entries() else {
+        panic!("expected two entries");
+    };
+    let (first, second) = (first.clone(), second.clone());
+    assert_eq!(first.compressed_size(), first.original_size());
+    assert!(second.compressed_size() < second.original_size());
+    assert_eq!(
+        archive.read_entry(&second).unwrap(),
+        "line\n".repeat(200).as_bytes()
+    );
+
+    let duplicate = parse_manifest(
+        r#"[{"source": "text/main.txt"}, {"source": "text/other.txt", "name": "text/main.txt"}]"#,
+        base,
+    )
+    .unwrap();
+    assert!(matches!(
+        pack_manifest_entries(&duplicate, &output, &options),
+        Err(ArcError::DuplicateEntry(_))
+    ));
+    assert!(parse_manifest(r#"{"source": "a"}"#, base).is_err());
+    assert!(parse_manifest(r#"[{"source": "a", "compress": "yes"}]"#, base).is_err());
+}
+
+#[test]
+fn test_make_patch() {
+    let temp_dir = tempdir().unwrap();
+    let base = temp_dir.path();
+    let (old_dir, new_dir) = (base.join("old"), base.join("new"));
+    fs::create_dir_all(&old_dir).unwrap();
+    fs::create_dir_all(&new_dir).unwrap();
+    fs::write(old_dir.join("a.txt"), "unchanged").unwrap();
+    fs::write(old_dir.join("b.txt"), "before").unwrap();
+    fs::write(old_dir.join("c.txt"), "moved").unwrap();
+    fs::write(new_dir.join("a.txt"), "unchanged").unwrap();
+    fs::write(new_dir.join("b.txt"), "after translation").unwrap();
+    fs::write(new_dir.join("d.txt"), "moved").unwrap();
+    let (old, new) = (base.join("old.arc"), base.join("new.arc"));
+    handle_pack(&old_dir, &old, false).unwrap();
+    handle_pack(&new_dir, &new, false).unwrap();
+
+    let patch = base.join("game.arcpatch");
+    let summary = make_patch(&old, &new, &patch).unwrap();
+    assert_eq!(summary.copied, 2);
+    assert_eq!(summary.included, 1);
+    let content = fs::read(&patch).unwrap();
+    assert_eq!(content.len() as u64, summary.patch_size);
+    assert_eq!(&content[..8], PATCH_MAGIC);
+    assert!(content.ends_with(b"after translation"));
+
+    let patched = base.join("patched.arc");
+    apply_patch(&old, &patch, &patched, &PackOptions::default()).unwrap();
+    assert_eq!(fs::read(&patched).unwrap(), fs::read(&new).unwrap());
+
+    // A base that differs from the one the patch was made for is refused
+    fs::write(old_dir.join("a.txt"), "unchangeD").unwrap();
+    handle_pack(&old_dir, &old, false).unwrap();
+    let refused = base.join("refused.arc");
+    let err = apply_patch(&old, &patch, &refused, &PackOptions::default()).unwrap_err();
+    assert!(matches!(err, ArcError::VerificationFailed(name, _) if name == "a.txt"));
+    assert!(!refused.exists());
+    assert!(apply_patch(&new, &old, &refused, &PackOptions::default()).is_err());
+}
+
+#[cfg(feature = "tui")]
+#[test]
+fn test_browser() {
+    use silky_arc_tool::browse::{Browser, RowKind};
+
+    let temp_dir = tempdir().unwrap();
+    let input = temp_dir.path().join("input");
+    fs::create_dir_all(input.join("bg").join("day")).unwrap();
+    fs::write(input.join("bg").join("day").join("sky.png"), "sky").unwrap();
+    fs::write(input.join("bg").join("room.png"), "room").unwrap();
+    fs::write(input.join("main.txt"), "text").unwrap();
+    let archive = temp_dir.path().join("test.arc");
+    handle_pack(&input, &archive, false).unwrap();
+
+    let mut browser = Browser::new(ArcReader::open(&archive).unwrap().entries().to_vec());
+    let labels = |browser: &Browser| -> Vec<String> {
+        browser
+            .rows()
+            .into_iter()
+            .map(|row| match row.kind {
+                RowKind::Directory(path) => format!("{}{path}\\", row.depth),
+                RowKind::Entry(index) => {
+                    format!("{}{}", row.depth, browser.entries()[index].name())
+                }
+            })
+            .collect()
+    };
+    assert_eq!(labels(&browser), [r"0bg\", "0main.txt"]);
+    browser.toggle_folder();
+    assert_eq!(
+        labels(&browser),
+        [r"0bg\", r"1bg\day\", r"1bg\room.png", "0main.txt"]
+    );
+
+    // Selecting a closed folder selects everything below it
+    browser.move_cursor(1);
+    browser.toggle_selected();
+    assert_eq!(browser.targets(), [r"bg\day\sky.png"]);
+    browser.toggle_selected();
+    browser.move_cursor(1);
+    assert_eq!(browser.targets(), [r"bg\room.png"]);
+    browser.close_folder();
+    assert_eq!(browser.cursor(), 0);
+    browser.close_folder();
+    assert_eq!(labels(&browser), [r"0bg\", "0main.txt"]);
+    browser.move_cursor(10);
+    assert_eq!(browser.targets(), ["main.txt"]);
+}
+
+#[test]
+fn test_watch() {
+    let roots = [
+        Path::new("/game/script").to_path_buf(),
+        Path::new("/game/bg").to_path_buf(),
+    ];
+    let ignored = [Path::new("/game/bg/bg.arc").to_path_buf()];
+    let changed = changed_roots(
+        &roots,
+        &ignored,
+        &[
+            "/game/bg/bg.arc".into(),
+            "/game/bg/.silkyarcXYZ.tmp".into(),
+            "/game/other/a.txt".into(),
+            "/game/script/main.txt".into(),
+        ],
+    );
+    assert_eq!(changed.into_iter().collect::<Vec<_>>(), [0]);
+
+    let temp_dir = tempdir().unwrap();
+    let input = temp_dir.path().join("script");
+    fs::create_dir_all(&input).unwrap();
+    let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let writer = {
+        let (input, done) = (input.clone(), done.clone());
+        std::thread::spawn(move || {
+            // Keep changing the input until the watcher has seen it
+            for round in 0..100 {
+                if done.load(std::sync::atomic::Ordering::SeqCst) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+                fs::write(input.join("main.txt"), round.to_string()).unwrap();
+            }
+        })
+    };
+    let mut rebuilds = Vec::new();
+    watch(&[input], &[], Duration::from_millis(50), |changed| {
+        rebuilds.push(changed.clone());
+        false
+    })
+    .unwrap();
+    done.store(true, std::sync::atomic::Ordering::SeqCst);
+    writer.join().unwrap();
+    assert_eq!(rebuilds.len(), 1);
+    assert!(rebuilds[0].contains(&0));
+}
+
+#[test]
+fn test_optimize_archive() {
+    // The out of order, padded layout of `test_out_of_order_layout`
+    let data = fs::read("./test_assets/test.arc").unwrap();
+    let metadata_end = 4 + u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+    let first_len = 1 + data[4] as usize + 12;
+    let mut shuffled = data[..4].to_vec();
+    shuffled.extend_from_slice(&data[4 + first_len..metadata_end]);
+    shuffled.extend_from_slice(&data[4..4 + first_len]);
+    shuffled.extend_from_slice(&data[metadata_end..]);
+    shuffled.extend_from_slice(&[0; 16]);
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("shuffled.arc");
+    fs::write(&archive, shuffled).unwrap();
+
+    let summary = optimize_archive(&archive, &archive, &PackOptions::default()).unwrap();
+    assert_eq!(summary.saved(), 16);
+    assert_eq!(summary.recompressed, 0);
+    assert!(diagnose(&archive).unwrap().findings.is_empty());
+    let (expected_dir, output_dir) = (
+        temp_dir.path().join("expected"),
+        temp_dir.path().join("out"),
+    );
+    handle_unpack("./test_assets/test.arc", &expected_dir).unwrap();
+    handle_unpack(&archive, &output_dir).unwrap();
+    for name in ["KT_A0000.OGG", "test.txt"] {
+        assert_eq!(
+            fs::read(output_dir.join(name)).unwrap(),
+            fs::read(expected_dir.join(name)).unwrap()
+        );
+    }
+
+    // Stored entries are compressed again where it helps
+    let input = temp_dir.path().join("input");
+    fs::create_dir_all(&input).unwrap();
+    fs::write(input.join("script.txt"), "text ".repeat(200)).unwrap();
+    fs::write(input.join("tiny.txt"), "t").unwrap();
+    let stored = temp_dir.path().join("stored.arc");
+    handle_pack(&input, &stored, false).unwrap();
+    let options = PackOptions {
+        compress: true,
+        ..Default::default()
+    };
+    let optimized = temp_dir.path().join("optimized.arc");
+    let summary = optimize_archive(&stored, &optimized, &options).unwrap();
+    assert_eq!(summary.recompressed, 1);
+    assert!(summary.saved() > 0);
+    let reader = ArcReader::open(&optimized).unwrap();
+    let names: Vec<_> = reader.entries().iter().map(|e| e.name()).collect();
+    let original = ArcReader::open(&stored).unwrap();
+    let original_names: Vec<_> = original.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(names, original_names);
+    let output_dir = temp_dir.path().join("optimized");
+    handle_unpack(&optimized, &output_dir).unwrap();
+    assert_eq!(
+        fs::read(output_dir.join("script.txt")).unwrap(),
+        "text ".repeat(200).as_bytes()
+    );
+}
+
+#[test]
+fn test_normalize_archive() {
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("slashes.arc");
+    write_raw_archive(
+        &archive,
+        &[
+            (encrypt_name("./script/main.txt").unwrap(), b"main"),
+            (encrypt_name("bg/title.png").unwrap(), b"title"),
+            (encrypt_name("se.ogg").unwrap(), b"se"),
+        ],
+    );
+    let options = NormalizeOptions {
+        trim_current_dir: true,
+    };
+    let renamed = normalize_archive(&archive, &archive, &options).unwrap();
+    assert_eq!(
+        renamed,
+        [
+            (
+                "./script/main.txt".to_string(),
+                r"script\main.txt".to_string()
+            ),
+            ("bg/title.png".to_string(), r"bg\title.png".to_string()),
+        ]
+    );
+    let mut reader = ArcReader::open(&archive).unwrap();
+    let entries = reader.entries().to_vec();
+    let names: Vec<_> = entries.iter().map(|e| e.name()).collect();
+    assert_eq!(names, [r"script\main.txt", r"bg\title.png", "se.ogg"]);
+    assert_eq!(reader.read_entry(&entries[1]).unwrap(), b"title");
+    assert!(diagnose(&archive).unwrap().findings.is_empty());
+
+    // Already normalized archives are left alone
+    let modified = fs::metadata(&archive).unwrap().modified().unwrap();
+    assert!(
+        normalize_archive(&archive, &archive, &options)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        fs::metadata(&archive).unwrap().modified().unwrap(),
+        modified
+    );
+
+    write_raw_archive(
+        &archive,
+        &[
+            (encrypt_name("a/b.txt").unwrap(), b"1"),
+            (encrypt_name(r"a\b.txt").unwrap(), b"2"),
+        ],
+    );
+    let output = temp_dir.path().join("out.arc");
+    assert!(matches!(
+        normalize_archive(&archive, &output, &NormalizeOptions::default()),
+        Err(ArcError::DuplicateEntry(_))
+    ));
+    assert!(!output.exists());
+}
+
+#[test]
+fn test_arc_writer() {
+    let temp_dir = tempdir().unwrap();
+    let output = temp_dir.path().join("built.arc");
+    let script = "text ".repeat(100);
+    let mut writer = ArcWriter::create(&output);
+    writer
+        .add_file("script/main.txt", script.as_bytes(), true)
+        .unwrap();
+    writer.add_file("se.ogg", &b"OggS"[..], true).unwrap();
+    writer
+        .add_file("empty.txt", std::io::empty(), false)
+        .unwrap();
+    assert!(matches!(
+        writer.add_file(r"script\main.txt", &b""[..], false),
+        Err(ArcError::DuplicateEntry(_))
+    ));
+    assert!(writer.add_file("../escape.txt", &b""[..], false).is_err());
+    assert_eq!(writer.len(), 3);
+    assert!(!output.exists());
+    let summary = writer.finish().unwrap();
+    assert_eq!(summary.archive_size(), fs::metadata(&output).unwrap().len());
+
+    let mut reader = ArcReader::open(&output).unwrap();
+    let entries = reader.entries().to_vec();
+    let names: Vec<_> = entries.iter().map(|e| e.name()).collect();
+    assert_eq!(names, [r"script\main.txt", "se.ogg", "empty.txt"]);
+    assert!(entries[0].compressed_size() < entries[0].original_size());
+    // Too small to be worth compressing
+    assert_eq!(entries[1].compressed_size(), entries[1].original_size());
+    assert_eq!(reader.read_entry(&entries[0]).unwrap(), script.as_bytes());
+}
+
+#[test]
+fn test_entry_reader() {
+    let temp_dir = tempdir().unwrap();
+    let output = temp_dir.path().join("stream.arc");
+    // Longer than the LZSS window
+    let large: Vec<u8> = (0..20_000u32).map(|i| (i * i / 7 % 251) as u8).collect();
+    let mut writer = ArcWriter::create(&output);
+    writer.add_file("large.bin", &large[..], true).unwrap();
+    writer
+        .add_file("script.txt", "text ".repeat(100).as_bytes(), true)
+        .unwrap();
+    writer.add_file("stored.bin", &b"OggS"[..], false).unwrap();
+    writer
+        .add_file("empty.txt", std::io::empty(), false)
+        .unwrap();
+    writer.finish().unwrap();
+
+    let mut archive = ArcReader::open(&output).unwrap();
+    let entries = archive.entries().to_vec();
+    assert!(entries[0].compressed_size() < entries[0].original_size());
+    assert!(entries[0].is_compressed());
+    assert!(!entries[2].is_compressed());
+    for entry in &entries {
+        let expected = archive.read_entry(entry).unwrap();
+        let mut streamed = Vec::new();
+        archive
+            .entry_reader(entry.name())
+            .unwrap()
+            .read_to_end(&mut streamed)
+            .unwrap();
+        assert_eq!(streamed, expected, "{}", entry.name());
+    }
+    assert!(matches!(
+        archive.entry_reader("missing.txt"),
+        Err(ArcError::MissingEntries(_))
+    ));
+
+    let mut written = Vec::new();
+    let size = archive.extract_entry_to("large.bin", &mut written).unwrap();
+    assert_eq!(size, large.len() as u64);
+    assert_eq!(written, large);
+
+    // Small reads stop in the middle of back references
+    let mut reader = archive.entry_reader("large.bin").unwrap();
+    let mut streamed = Vec::new();
+    let mut chunk = [0u8; 7];
+    loop {
+        let read = reader.read(&mut chunk).unwrap();
+        if read == 0 {
+            break;
+        }
+        streamed.extend_from_slice(&chunk[..read]);
+    }
+    assert_eq!(streamed, large);
+}